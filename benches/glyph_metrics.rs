@@ -0,0 +1,50 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate criterion;
+extern crate dwrote;
+
+use criterion::{black_box, Criterion};
+use dwrote::{FontCollection, FontStretch, FontStyle, FontWeight};
+
+// A paragraph laid out a line at a time, the way a layout engine asks for
+// the same handful of glyphs again and again.
+const TEXT: &str = "The quick brown fox jumps over the lazy dog. \
+                    Pack my box with five dozen liquor jugs. \
+                    How vexingly quick daft zebras jump!";
+
+fn glyph_metrics(c: &mut Criterion) {
+    let system_fc = FontCollection::system();
+    let family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let face = font.create_font_face();
+    let code_points: Vec<u32> = TEXT.chars().map(|c| c as u32).collect();
+    let glyphs = face.get_glyph_indices(&code_points);
+    let lines: Vec<&[u16]> = glyphs.chunks(24).collect();
+
+    c.bench_function("design glyph metrics, uncached", |b| {
+        b.iter(|| {
+            for line in &lines {
+                for &glyph in line.iter() {
+                    black_box(face.get_design_glyph_metrics(&[glyph], false));
+                }
+            }
+        })
+    });
+
+    c.bench_function("design glyph metrics, cached", |b| {
+        let cache = face.metrics_cache();
+        b.iter(|| {
+            for line in &lines {
+                for &glyph in line.iter() {
+                    black_box(cache.get(glyph));
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(benches, glyph_metrics);
+criterion_main!(benches);
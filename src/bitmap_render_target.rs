@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use std::io;
 use std::slice;
 use std::cell::UnsafeCell;
 use std::mem::{zeroed, size_of};
@@ -10,11 +11,20 @@ use comptr::ComPtr;
 use winapi::um::dwrite::{DWRITE_GLYPH_RUN, DWRITE_GLYPH_OFFSET};
 use winapi::um::dwrite::IDWriteBitmapRenderTarget;
 use winapi::um::dcommon::{DWRITE_MEASURING_MODE};
-use winapi::um::wingdi::{BITMAP, OBJ_BITMAP, RGB, GetObjectW, GetCurrentObject};
-use winapi::shared::windef::{RECT, HDC};
+use winapi::um::wingdi::{BITMAP, OBJ_BITMAP, RGB, SRCCOPY, BitBlt, GetObjectW, GetCurrentObject};
+use winapi::shared::windef::{RECT, HDC, SIZE};
+use winapi::shared::winerror::{E_FAIL, HRESULT, HRESULT_FROM_WIN32};
 use winapi::ctypes::c_void;
 use super::{FontFace, RenderingParams};
 
+// A GDI memory DC that DirectWrite draws glyph runs into.  Its bitmap is a
+// 32 bits per pixel, top-down BGRX DIB whose fourth byte is not alpha:
+// DirectWrite leaves it alone when it draws, so GDI's AlphaBlend would see
+// garbage there.  Text is instead blended against whatever pixels the
+// bitmap already holds.  To paint antialiased text onto an existing GDI
+// surface, copy the area the target will cover from the surface with
+// copy_from_hdc, draw the glyph runs, then copy the result back with
+// blit_to_hdc.
 pub struct BitmapRenderTarget {
     native: UnsafeCell<ComPtr<IDWriteBitmapRenderTarget>>,
 }
@@ -43,6 +53,28 @@ impl BitmapRenderTarget {
         }
     }
 
+    // The size of the target's bitmap in pixels.
+    pub fn get_size(&self) -> (u32, u32) {
+        unsafe {
+            let mut size: SIZE = zeroed();
+            let hr = (*self.native.get()).GetSize(&mut size);
+            assert!(hr == 0);
+            (size.cx as u32, size.cy as u32)
+        }
+    }
+
+    // Fills the whole bitmap with the pixels of `hdc` starting at (x, y),
+    // as the background to draw text over.
+    pub unsafe fn copy_from_hdc(&self, hdc: HDC, x: i32, y: i32) -> Result<(), HRESULT> {
+        bit_blt(self.get_memory_dc(), (0, 0), hdc, (x, y), self.get_size())
+    }
+
+    // Copies the whole bitmap onto `hdc` with its top left corner at
+    // (x, y).
+    pub unsafe fn blit_to_hdc(&self, hdc: HDC, x: i32, y: i32) -> Result<(), HRESULT> {
+        bit_blt(hdc, (x, y), self.get_memory_dc(), (0, 0), self.get_size())
+    }
+
     pub fn draw_glyph_run(&self,
                           baseline_origin_x: f32,
                           baseline_origin_y: f32,
@@ -125,3 +157,13 @@ impl BitmapRenderTarget {
         }
     }
 }
+
+unsafe fn bit_blt(destination: HDC, (x, y): (i32, i32), source: HDC, (source_x, source_y): (i32, i32),
+                  (width, height): (u32, u32)) -> Result<(), HRESULT> {
+    if BitBlt(destination, x, y, width as i32, height as i32, source, source_x, source_y, SRCCOPY) == 0 {
+        return Err(io::Error::last_os_error().raw_os_error()
+                   .map(|code| HRESULT_FROM_WIN32(code as u32))
+                   .unwrap_or(E_FAIL));
+    }
+    Ok(())
+}
@@ -48,7 +48,7 @@ macro_rules! implement_iunknown {
                     let this = $typ::from_interface(This);
                     let count = this.refcount.fetch_sub(1, atomic::Ordering::Release) - 1;
                     if count == 0 {
-                        FontFileStream::destroy(This);
+                        $typ::destroy(This);
                     }
                     count as ULONG
                 }
@@ -0,0 +1,51 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::marker::PhantomData;
+use std::ptr;
+
+use winapi::shared::winerror::{RPC_E_CHANGED_MODE, S_FALSE, S_OK};
+use winapi::um::combaseapi::{CoInitializeEx, CoUninitialize};
+use winapi::um::objbase::COINIT_MULTITHREADED;
+use winapi::um::winnt::HRESULT;
+
+// Keeps COM initialized on the current thread until dropped.  It can't be
+// sent to another thread, because CoUninitialize has to be called on the
+// thread that initialized COM.
+pub struct ComThreadGuard {
+    uninitialize: bool,
+    not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ComThreadGuard {
+    fn drop(&mut self) {
+        if self.uninitialize {
+            unsafe {
+                CoUninitialize();
+            }
+        }
+    }
+}
+
+// Initializes COM on the calling thread, in the multithreaded apartment.
+//
+// Most of DirectWrite works without this, since the factory doesn't live
+// in a COM apartment, and nothing in this crate calls it for you.  It is
+// needed on threads the caller creates when they go on to use custom
+// font file loaders or other paths that call back into COM; symptoms of
+// forgetting it are CO_E_NOTINITIALIZED failures on those threads.
+//
+// If the thread was already initialized into a single-threaded apartment,
+// COM is still usable, so this succeeds with a guard that does nothing on
+// drop.  Any other failure is returned as the HRESULT.
+pub fn init_thread() -> Result<ComThreadGuard, HRESULT> {
+    let hr = unsafe { CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED) };
+    match hr {
+        // S_FALSE means COM was already initialized on this thread, but
+        // the call still has to be balanced by CoUninitialize.
+        S_OK | S_FALSE => Ok(ComThreadGuard { uninitialize: true, not_send: PhantomData }),
+        RPC_E_CHANGED_MODE => Ok(ComThreadGuard { uninitialize: false, not_send: PhantomData }),
+        _ => Err(hr),
+    }
+}
@@ -0,0 +1,50 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+
+use com_helpers::Com;
+use comptr::ComPtr;
+use drawing_effect_impl::{ColorDrawingEffectImpl, UuidOfColorDrawingEffect};
+use winapi::um::unknwnbase::IUnknown;
+
+// A drawing effect that carries an RGBA color, for coloring ranges of a
+// TextLayout.  DirectWrite only passes drawing effects through to the
+// renderer, so the renderer has to recognize them with from_unknown.
+pub struct ColorDrawingEffect {
+    native: UnsafeCell<ComPtr<IUnknown>>,
+}
+
+impl ColorDrawingEffect {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> ColorDrawingEffect {
+        let effect = ColorDrawingEffectImpl::new([r, g, b, a]);
+        ColorDrawingEffect {
+            native: UnsafeCell::new(ComPtr::from_ptr(effect.into_interface())),
+        }
+    }
+
+    // Returns None if `effect` is null or some other kind of effect.
+    pub unsafe fn from_unknown(effect: *mut IUnknown) -> Option<ColorDrawingEffect> {
+        if effect.is_null() {
+            return None;
+        }
+        let unknown: ComPtr<IUnknown> = ComPtr::from_ptr(effect);
+        unknown.query_interface(&UuidOfColorDrawingEffect).map(|native| {
+            ColorDrawingEffect {
+                native: UnsafeCell::new(native),
+            }
+        })
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IUnknown {
+        (*self.native.get()).as_ptr()
+    }
+
+    // (r, g, b, a)
+    pub fn color(&self) -> [f32; 4] {
+        unsafe {
+            ColorDrawingEffectImpl::from_interface(self.as_ptr()).color
+        }
+    }
+}
@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// A bare IUnknown carrying a color, used as a drawing effect.
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+use std::mem;
+use std::sync::atomic;
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::REFIID;
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::winerror::S_OK;
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::HRESULT;
+
+use com_helpers::*;
+
+// Not a system interface; QueryInterface for it succeeds only on
+// ColorDrawingEffects, which is how renderers tell them apart from other
+// effects.
+DEFINE_GUID!{UuidOfColorDrawingEffect, 0x6b1b1f41, 0x53a2, 0x4c0e, 0x9a, 0x4e, 0x2f, 0x3c, 0x8d, 0x71, 0x0b, 0x5e}
+
+pub struct ColorDrawingEffectImpl {
+    refcount: atomic::AtomicUsize,
+    pub color: [f32; 4],
+}
+
+const ColorDrawingEffectVtbl: &IUnknownVtbl =
+    &implement_iunknown!(IUnknown, UuidOfColorDrawingEffect, ColorDrawingEffectImpl);
+
+impl ColorDrawingEffectImpl {
+    pub fn new(color: [f32; 4]) -> ColorDrawingEffectImpl {
+        ColorDrawingEffectImpl {
+            refcount: atomic::AtomicUsize::new(0),
+            color,
+        }
+    }
+}
+
+impl Com<IUnknown> for ColorDrawingEffectImpl {
+    type Vtbl = IUnknownVtbl;
+    fn vtbl() -> &'static IUnknownVtbl { ColorDrawingEffectVtbl }
+}
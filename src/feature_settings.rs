@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::ops::Range;
+
+use winapi::shared::winerror::{E_INVALIDARG, HRESULT};
+use winapi::um::dwrite::{DWRITE_FONT_FEATURE, DWRITE_TYPOGRAPHIC_FEATURES};
+use tables::make_opentype_tag;
+
+// OpenType features to apply on top of the font's defaults when shaping,
+// each over a range of the text in UTF-16 code units.  The value is the
+// feature's parameter: 0 turns it off, 1 turns it on, and for features
+// with alternates (such as 'salt' or 'aalt') values above 1 pick an
+// alternate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeatureSettings {
+    features: Vec<([u8; 4], u32, Range<usize>)>,
+}
+
+impl FeatureSettings {
+    pub fn new() -> FeatureSettings {
+        FeatureSettings { features: vec![] }
+    }
+
+    // Adds `tag` = `value` over `range`, which is clipped to the text when
+    // shaping, so 0..usize::MAX applies it everywhere.  Fails with
+    // E_INVALIDARG if the tag isn't a valid OpenType tag (four printable
+    // ASCII characters, with spaces only as trailing padding) or the range
+    // is empty.
+    pub fn add(&mut self, tag: [u8; 4], value: u32, range: Range<usize>) -> Result<(), HRESULT> {
+        if !is_valid_tag(&tag) || range.start >= range.end {
+            return Err(E_INVALIDARG);
+        }
+        self.features.push((tag, value, range));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    // DirectWrite wants the text split into consecutive ranges, each with
+    // the complete list of features that apply to it.
+    pub(crate) fn to_raw(&self, text_len: usize) -> RawFeatureSettings {
+        let mut boundaries = vec![0, text_len];
+        for (_, _, range) in &self.features {
+            boundaries.push(range.start.min(text_len));
+            boundaries.push(range.end.min(text_len));
+        }
+        boundaries.sort();
+        boundaries.dedup();
+
+        let mut features = vec![];
+        let mut range_lengths = vec![];
+        for segment in boundaries.windows(2) {
+            features.push(self.features.iter()
+                .filter(|(_, _, range)| range.start <= segment[0] && segment[1] <= range.end)
+                .map(|&(tag, value, _)| DWRITE_FONT_FEATURE { nameTag: make_opentype_tag(&tag), parameter: value })
+                .collect::<Vec<_>>());
+            range_lengths.push((segment[1] - segment[0]) as u32);
+        }
+
+        let mut ranges: Vec<DWRITE_TYPOGRAPHIC_FEATURES> = features.iter_mut().map(|features| {
+            DWRITE_TYPOGRAPHIC_FEATURES { features: features.as_mut_ptr(), featureCount: features.len() as u32 }
+        }).collect();
+        let range_pointers = ranges.iter_mut().map(|range| range as *const _).collect();
+
+        RawFeatureSettings {
+            _features: features,
+            _ranges: ranges,
+            range_pointers,
+            range_lengths,
+        }
+    }
+}
+
+pub(crate) fn is_valid_tag(tag: &[u8; 4]) -> bool {
+    tag[0] != b' ' &&
+        tag.iter().all(|&c| (0x20..=0x7e).contains(&c)) &&
+        tag.iter().skip_while(|&&c| c != b' ').all(|&c| c == b' ')
+}
+
+// The arrays GetGlyphs and GetGlyphPlacements take, kept alive together
+// since they point into each other.
+pub(crate) struct RawFeatureSettings {
+    _features: Vec<Vec<DWRITE_FONT_FEATURE>>,
+    _ranges: Vec<DWRITE_TYPOGRAPHIC_FEATURES>,
+    pub range_pointers: Vec<*const DWRITE_TYPOGRAPHIC_FEATURES>,
+    pub range_lengths: Vec<u32>,
+}
@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// A C interface to font face loading and glyph rasterization, enabled by
+// the "ffi" feature.  Every function returns one of the DWROTE_* status
+// codes; panics are caught and reported as DWROTE_ERROR_INTERNAL, so
+// nothing unwinds into the caller (unless the crate is built with
+// panic=abort, in which case a failed assertion still aborts).
+//
+// Faces are opaque DWroteFontFace pointers that must be released with
+// dwrote_font_face_free.
+
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+use winapi::um::dcommon::DWRITE_MEASURING_MODE_NATURAL;
+use winapi::um::dwrite::{DWRITE_FONT_SIMULATIONS_NONE, DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC};
+use winapi::um::dwrite::DWRITE_TEXTURE_ALIASED_1x1;
+use super::{FontFace, FontFile, RenderingParams};
+
+pub const DWROTE_OK: i32 = 0;
+pub const DWROTE_ERROR_NULL_POINTER: i32 = -1;
+pub const DWROTE_ERROR_INVALID_ARGUMENT: i32 = -2;
+pub const DWROTE_ERROR_CANNOT_LOAD_FONT: i32 = -3;
+// The output buffer is too small; the required size has been written to
+// the output info.
+pub const DWROTE_ERROR_BUFFER_TOO_SMALL: i32 = -4;
+pub const DWROTE_ERROR_INTERNAL: i32 = -5;
+
+pub struct DWroteFontFace(FontFace);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DWroteRasterizedGlyph {
+    // Bitmap bounds in pixels relative to the glyph origin.
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    // 1 for aliased coverage, 3 for ClearType RGB coverage.
+    pub bytes_per_pixel: u32,
+    // (right - left) * (bottom - top) * bytes_per_pixel.
+    pub data_len: usize,
+}
+
+fn guard<F>(f: F) -> i32 where F: FnOnce() -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(DWROTE_ERROR_INTERNAL)
+}
+
+// Loads face `face_index` of the font file at `path`, a null-terminated
+// UTF-16 string, into `*out_face`.
+#[no_mangle]
+pub unsafe extern "C" fn dwrote_font_face_create_from_path(path: *const u16,
+                                                           face_index: u32,
+                                                           out_face: *mut *mut DWroteFontFace)
+                                                           -> i32 {
+    if path.is_null() || out_face.is_null() {
+        return DWROTE_ERROR_NULL_POINTER;
+    }
+    guard(|| {
+        let mut len = 0;
+        while *path.add(len) != 0 {
+            len += 1;
+        }
+        let path = OsString::from_wide(slice::from_raw_parts(path, len));
+        let file = match FontFile::new_from_path(path) {
+            Some(file) => file,
+            None => return DWROTE_ERROR_CANNOT_LOAD_FONT,
+        };
+        let face = match file.try_create_face(face_index, DWRITE_FONT_SIMULATIONS_NONE) {
+            Ok(face) => face,
+            Err(_) => return DWROTE_ERROR_CANNOT_LOAD_FONT,
+        };
+        *out_face = Box::into_raw(Box::new(DWroteFontFace(face)));
+        DWROTE_OK
+    })
+}
+
+// Maps `count` code points to glyph indices, writing `count` u16s to
+// `out_glyphs`.  Unmapped code points get glyph 0.
+#[no_mangle]
+pub unsafe extern "C" fn dwrote_font_face_get_glyph_indices(face: *const DWroteFontFace,
+                                                            code_points: *const u32,
+                                                            count: usize,
+                                                            out_glyphs: *mut u16)
+                                                            -> i32 {
+    if face.is_null() || (count > 0 && (code_points.is_null() || out_glyphs.is_null())) {
+        return DWROTE_ERROR_NULL_POINTER;
+    }
+    if count == 0 {
+        return DWROTE_OK;
+    }
+    guard(|| {
+        let glyphs = (*face).0.get_glyph_indices(slice::from_raw_parts(code_points, count));
+        slice::from_raw_parts_mut(out_glyphs, count).copy_from_slice(&glyphs);
+        DWROTE_OK
+    })
+}
+
+// Rasterizes `glyph` at `em_size` pixels with `rendering_mode` (a
+// DWRITE_RENDERING_MODE up to NATURAL_SYMMETRIC; 0 picks the recommended
+// mode for the primary monitor).  The bounds and size are always written to `out_info`; the
+// coverage is copied to `buffer` only if `buffer_len` is at least
+// out_info.data_len, and DWROTE_ERROR_BUFFER_TOO_SMALL is returned
+// otherwise, so callers can pass a null buffer first to get the size.
+#[no_mangle]
+pub unsafe extern "C" fn dwrote_font_face_rasterize_glyph(face: *const DWroteFontFace,
+                                                          glyph: u16,
+                                                          em_size: f32,
+                                                          rendering_mode: u32,
+                                                          out_info: *mut DWroteRasterizedGlyph,
+                                                          buffer: *mut u8,
+                                                          buffer_len: usize)
+                                                          -> i32 {
+    if face.is_null() || out_info.is_null() {
+        return DWROTE_ERROR_NULL_POINTER;
+    }
+    if em_size.is_nan() || em_size <= 0. || rendering_mode > DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC {
+        return DWROTE_ERROR_INVALID_ARGUMENT;
+    }
+    guard(|| {
+        let params = RenderingParams::create_for_primary_monitor();
+        let rasterized = (*face).0.rasterize_glyph(glyph, em_size, 1., rendering_mode,
+                                                   DWRITE_MEASURING_MODE_NATURAL, &params);
+        *out_info = DWroteRasterizedGlyph {
+            left: rasterized.bounds.left,
+            top: rasterized.bounds.top,
+            right: rasterized.bounds.right,
+            bottom: rasterized.bounds.bottom,
+            bytes_per_pixel: if rasterized.texture_type == DWRITE_TEXTURE_ALIASED_1x1 { 1 } else { 3 },
+            data_len: rasterized.data.len(),
+        };
+        if rasterized.data.is_empty() {
+            return DWROTE_OK;
+        }
+        if buffer.is_null() || buffer_len < rasterized.data.len() {
+            return DWROTE_ERROR_BUFFER_TOO_SMALL;
+        }
+        slice::from_raw_parts_mut(buffer, rasterized.data.len()).copy_from_slice(&rasterized.data);
+        DWROTE_OK
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dwrote_font_face_free(face: *mut DWroteFontFace) -> i32 {
+    if face.is_null() {
+        return DWROTE_ERROR_NULL_POINTER;
+    }
+    guard(|| {
+        drop(Box::from_raw(face));
+        DWROTE_OK
+    })
+}
@@ -6,7 +6,10 @@ use std::cell::UnsafeCell;
 
 use comptr::ComPtr;
 use winapi::um::dwrite::{IDWriteFontFace, IDWriteLocalizedStrings, IDWriteFont};
-use winapi::um::dwrite::IDWriteFontFamily;
+use winapi::um::dwrite::{IDWriteFontFamily, DWRITE_INFORMATIONAL_STRING_ID};
+use winapi::um::dwrite::{DWRITE_INFORMATIONAL_STRING_PREFERRED_FAMILY_NAMES, DWRITE_INFORMATIONAL_STRING_WIN32_FAMILY_NAMES};
+use winapi::um::dwrite::DWRITE_INFORMATIONAL_STRING_WWS_FAMILY_NAME;
+use winapi::shared::minwindef::{BOOL, FALSE};
 use std::mem;
 
 use super::*;
@@ -74,6 +77,60 @@ impl Font {
         }
     }
 
+    // The face name in `locale`, or the system locale if None, falling
+    // back to en-us and then to whatever name the font has.
+    pub fn localized_face_name(&self, locale: Option<&str>) -> String {
+        unsafe {
+            let mut names: ComPtr<IDWriteLocalizedStrings> = ComPtr::new();
+            let hr = (*self.native.get()).GetFaceNames(names.getter_addrefs());
+            assert!(hr == 0);
+
+            get_locale_string_for(&mut names, locale)
+        }
+    }
+
+    // One of the font's informational strings (copyright, preferred
+    // family name and so on) in `locale`, or the system locale if None,
+    // with the usual fallbacks.  Returns None if the font doesn't have it.
+    pub fn informational_string(&self, id: DWRITE_INFORMATIONAL_STRING_ID, locale: Option<&str>)
+                                -> Option<String> {
+        unsafe {
+            let mut strings: ComPtr<IDWriteLocalizedStrings> = ComPtr::new();
+            let mut exists: BOOL = FALSE;
+            let hr = (*self.native.get()).GetInformationalStrings(id, strings.getter_addrefs(), &mut exists);
+            if hr != 0 || exists == FALSE {
+                return None;
+            }
+
+            Some(get_locale_string_for(&mut strings, locale))
+        }
+    }
+
+    // The font's weight-width-slope family name, which groups faces that
+    // differ only in those three attributes, in `locale` or the system
+    // locale if None.  Most fonts don't declare one ('name' ID 21), since
+    // it's usually the same as the typographic family; those fall back to
+    // the typographic family name and then the RIBBI (Win32) family name,
+    // as DirectWrite does when grouping families.  None only if the font
+    // has none of these.
+    pub fn wws_family_name(&self, locale: Option<&str>) -> Option<String> {
+        self.informational_string(DWRITE_INFORMATIONAL_STRING_WWS_FAMILY_NAME, locale)
+            .or_else(|| self.informational_string(DWRITE_INFORMATIONAL_STRING_PREFERRED_FAMILY_NAMES, locale))
+            .or_else(|| self.informational_string(DWRITE_INFORMATIONAL_STRING_WIN32_FAMILY_NAMES, locale))
+    }
+
+    // The font's values of a font set property, such as
+    // DWRITE_FONT_PROPERTY_ID_FULL_NAME, POSTSCRIPT_NAME or
+    // DESIGN_SCRIPT_LANGUAGE_TAG, in the given locale or the system locale
+    // if None.  Most properties have one value; a few, like the script
+    // tags, can have several.  Empty if the font doesn't have the property,
+    // or on systems without font sets (before Windows 10).
+    pub fn get_property_values(&self, property_id: DWRITE_FONT_PROPERTY_ID, locale: Option<&str>) -> Vec<String> {
+        FontSet::from_font(self)
+            .map(|set| set.get_property_values(property_id, locale))
+            .unwrap_or_default()
+    }
+
     pub fn create_font_face(&self) -> FontFace {
         // FIXME create_font_face should cache the FontFace and return it,
         // there's a 1:1 relationship
@@ -84,4 +141,10 @@ impl Font {
             FontFace::take(face)
         }
     }
+
+    // Whether the font is installed, as opposed to coming from a custom
+    // collection such as one built with FontCollection::from_directory.
+    pub fn is_in_system_collection(&self) -> bool {
+        self.create_font_face().is_in_system_collection()
+    }
 }
@@ -3,9 +3,23 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use comptr::ComPtr;
-use winapi::um::dwrite::{IDWriteFontFamily, IDWriteFont, IDWriteFontCollection};
-use winapi::shared::minwindef::{BOOL, FALSE};
+use winapi::um::dwrite::{IDWriteFontFamily, IDWriteFont, IDWriteFontCollection, IDWriteFontFile};
+use winapi::um::dwrite::{DWRITE_FONT_FACE_TYPE_UNKNOWN, DWRITE_FONT_FILE_TYPE_UNKNOWN};
+use winapi::um::dwrite::{DWRITE_FONT_SIMULATIONS_NONE, DWRITE_INFORMATIONAL_STRING_PREFERRED_FAMILY_NAMES};
+use winapi::um::dwrite_3::{IDWriteFactory3, IDWriteFontCollection1, IDWriteFontFaceReference};
+use winapi::um::dwrite_3::{IDWriteFontSet, IDWriteFontSetBuilder};
+use winapi::shared::minwindef::{BOOL, FALSE, TRUE};
+use winapi::shared::winerror::{E_FAIL, E_NOINTERFACE, HRESULT, HRESULT_FROM_WIN32};
+use winapi::Interface;
 use std::cell::UnsafeCell;
+use std::collections::BTreeSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::ptr;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
 
 use super::{DWriteFactory, FontFamily, Font, FontFace, FontDescriptor};
 use helpers::*;
@@ -39,9 +53,16 @@ pub struct FontCollection {
 
 impl FontCollection {
     pub fn system() -> FontCollection {
+        FontCollection::get_system(false)
+    }
+
+    // If `update` is true, DirectWrite checks for newly installed or
+    // removed fonts instead of handing back its cached collection.
+    pub fn get_system(update: bool) -> FontCollection {
         unsafe {
             let mut native: ComPtr<IDWriteFontCollection> = ComPtr::new();
-            let hr = (*DWriteFactory()).GetSystemFontCollection(native.getter_addrefs(), FALSE);
+            let hr = (*DWriteFactory()).GetSystemFontCollection(native.getter_addrefs(),
+                                                                if update { TRUE } else { FALSE });
             assert!(hr == 0);
 
             FontCollection {
@@ -50,6 +71,149 @@ impl FontCollection {
         }
     }
 
+    // Fetches the system font collection on a new thread, so that a cold
+    // font cache doesn't block the caller; poll the receiver with try_recv
+    // or block on it with recv.  DirectWrite objects from the shared
+    // factory don't belong to a COM apartment, so the worker thread needs
+    // no COM initialization and the collection it sends back can be used
+    // from any thread.  If the worker panics, the receiver reports a
+    // disconnected channel.
+    pub fn system_async(update: bool) -> Receiver<FontCollection> {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("dwrote system font collection".to_owned())
+            .spawn(move || {
+                let _ = sender.send(FontCollection::get_system(update));
+            })
+            .expect("failed to spawn font collection thread");
+        receiver
+    }
+
+    // Returns a value that changes when the set of installed fonts
+    // changes, so that long-running callers can tell when to rebuild
+    // anything derived from the system collection.  DirectWrite has no
+    // native generation counter, so this is a hash of the family count
+    // and a sample of the family names; it is cheap, but it is not
+    // guaranteed to notice a change that leaves every sampled family
+    // and the family count intact.
+    pub fn system_font_set_generation() -> u64 {
+        const MAX_SAMPLED_FAMILIES: u32 = 64;
+
+        let collection = FontCollection::get_system(true);
+        let count = collection.get_font_family_count();
+
+        let mut hasher = DefaultHasher::new();
+        count.hash(&mut hasher);
+        let step = (count / MAX_SAMPLED_FAMILIES).max(1);
+        for index in (0..count).step_by(step as usize) {
+            let family = collection.get_font_family(index);
+            family.name().hash(&mut hasher);
+            family.get_font_count().hash(&mut hasher);
+        }
+        // Always include the last family, where new installs tend to land
+        // in an otherwise unchanged sample.
+        if count > 0 {
+            collection.get_font_family(count - 1).name().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    // Builds a private collection out of the .ttf, .otf, .ttc and .otc
+    // files directly inside `dir`.  Each file is checked with Analyze, and
+    // files DirectWrite can't load or add to the set are skipped rather
+    // than failing the whole call; the number skipped is returned with the
+    // collection.
+    // Needs IDWriteFactory3 (Windows 10); E_NOINTERFACE is returned if it
+    // isn't available.
+    pub fn from_directory(dir: &Path) -> Result<(FontCollection, usize), HRESULT> {
+        let entries = fs::read_dir(dir).map_err(|error| {
+            error.raw_os_error().map(|code| HRESULT_FROM_WIN32(code as u32)).unwrap_or(E_FAIL)
+        })?;
+
+        unsafe {
+            let factory = ComPtr::from_ptr(DWriteFactory())
+                .query_interface::<IDWriteFactory3>(&IDWriteFactory3::uuidof())
+                .ok_or(E_NOINTERFACE)?;
+            let mut builder: ComPtr<IDWriteFontSetBuilder> = ComPtr::new();
+            let hr = factory.CreateFontSetBuilder(builder.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+
+            let mut skipped = 0;
+            for entry in entries {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(_) => continue,
+                };
+                let is_font = path.extension()
+                    .and_then(|extension| extension.to_str())
+                    .map(|extension| {
+                        ["ttf", "otf", "ttc", "otc"].iter().any(|e| extension.eq_ignore_ascii_case(e))
+                    })
+                    .unwrap_or(false);
+                if !is_font || !path.is_file() {
+                    continue;
+                }
+
+                let mut file: ComPtr<IDWriteFontFile> = ComPtr::new();
+                let hr = (*DWriteFactory()).CreateFontFileReference(path.to_wide_null().as_ptr(),
+                                                                    ptr::null(),
+                                                                    file.getter_addrefs());
+                if hr != 0 {
+                    skipped += 1;
+                    continue;
+                }
+
+                let mut supported = 0;
+                let mut file_type = DWRITE_FONT_FILE_TYPE_UNKNOWN;
+                let mut face_type = DWRITE_FONT_FACE_TYPE_UNKNOWN;
+                let mut face_count = 0;
+                let hr = file.Analyze(&mut supported, &mut file_type, &mut face_type, &mut face_count);
+                if hr != 0 || supported == 0 {
+                    skipped += 1;
+                    continue;
+                }
+
+                // Gather the file's faces in a builder of their own, and add
+                // them to the collection only once all of them made it in,
+                // so a file that fails partway is left out entirely.
+                let mut file_builder: ComPtr<IDWriteFontSetBuilder> = ComPtr::new();
+                if factory.CreateFontSetBuilder(file_builder.getter_addrefs()) != 0 {
+                    skipped += 1;
+                    continue;
+                }
+                let added = (0..face_count).all(|face_index| {
+                    let mut face_reference: ComPtr<IDWriteFontFaceReference> = ComPtr::new();
+                    let hr = factory.CreateFontFaceReference_2(file.as_ptr(), face_index,
+                                                               DWRITE_FONT_SIMULATIONS_NONE,
+                                                               face_reference.getter_addrefs());
+                    hr == 0 && file_builder.AddFontFaceReference_1(face_reference.as_ptr()) == 0
+                });
+                let mut file_set: ComPtr<IDWriteFontSet> = ComPtr::new();
+                if !added ||
+                   file_builder.CreateFontSet(file_set.getter_addrefs()) != 0 ||
+                   builder.AddFontSet(file_set.as_ptr()) != 0 {
+                    skipped += 1;
+                }
+            }
+
+            let mut font_set: ComPtr<IDWriteFontSet> = ComPtr::new();
+            let hr = builder.CreateFontSet(font_set.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            let mut collection: ComPtr<IDWriteFontCollection1> = ComPtr::new();
+            let hr = factory.CreateFontCollectionFromFontSet(font_set.as_ptr(), collection.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+
+            let native = ComPtr::already_addrefed(collection.forget() as *mut IDWriteFontCollection);
+            Ok((FontCollection::take(native), skipped))
+        }
+    }
+
     pub fn take(native: ComPtr<IDWriteFontCollection>) -> FontCollection {
         FontCollection {
             native: UnsafeCell::new(native)
@@ -76,6 +240,33 @@ impl FontCollection {
         }
     }
 
+    // The name of every family in the collection, in `locale` (or the
+    // system locale if None, with the usual fallbacks), sorted and with
+    // duplicates removed.
+    pub fn family_names(&self, locale: Option<&str>) -> Vec<String> {
+        let mut names: Vec<String> = self.families_iter().map(|family| family.localized_name(locale)).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    // Like family_names, but with families that are really styles of a
+    // larger typographic family (such as "Arial Black", which belongs to
+    // "Arial") listed under the typographic family's name instead.  Fonts
+    // without a preferred family name are listed under their own family.
+    pub fn typographic_families(&self, locale: Option<&str>) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        for family in self.families_iter() {
+            for index in 0..family.get_font_count() {
+                let font = family.get_font(index);
+                let name = font.informational_string(DWRITE_INFORMATIONAL_STRING_PREFERRED_FAMILY_NAMES, locale)
+                    .unwrap_or_else(|| family.localized_name(locale));
+                names.insert(name);
+            }
+        }
+        names.into_iter().collect()
+    }
+
     pub fn get_font_family(&self, index: u32) -> FontFamily {
         unsafe {
             let mut family: ComPtr<IDWriteFontFamily> = ComPtr::new();
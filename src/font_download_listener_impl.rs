@@ -0,0 +1,65 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// An IDWriteFontDownloadListener that calls a closure with the result of
+// each download.
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+use std::mem;
+use std::sync::atomic;
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::REFIID;
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::winerror::S_OK;
+use winapi::um::dwrite_3::{IDWriteFontDownloadListener, IDWriteFontDownloadListenerVtbl, IDWriteFontDownloadQueue};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::HRESULT;
+
+use comptr::ComPtr;
+use com_helpers::*;
+
+DEFINE_GUID!{UuidOfIDWriteFontDownloadListener, 0xb06fe5b9, 0x43ec, 0x4393, 0x88, 0x1b, 0xdb, 0xe4, 0xdc, 0x72, 0xfd, 0xa7}
+
+pub struct FontDownloadListenerImpl {
+    refcount: atomic::AtomicUsize,
+    callback: Box<dyn Fn(HRESULT) + Send>,
+}
+
+const FontDownloadListenerImplVtbl: &IDWriteFontDownloadListenerVtbl = &IDWriteFontDownloadListenerVtbl {
+    parent: implement_iunknown!(IDWriteFontDownloadListener, UuidOfIDWriteFontDownloadListener,
+                                FontDownloadListenerImpl),
+    DownloadCompleted: {
+        unsafe extern "system" fn DownloadCompleted(
+            This: *mut IDWriteFontDownloadListener,
+            _downloadQueue: *mut IDWriteFontDownloadQueue,
+            _context: *mut IUnknown,
+            downloadResult: HRESULT)
+        {
+            let this = FontDownloadListenerImpl::from_interface(This);
+            (this.callback)(downloadResult);
+        }
+        DownloadCompleted
+    },
+};
+
+impl FontDownloadListenerImpl {
+    pub fn create<F>(callback: F) -> ComPtr<IDWriteFontDownloadListener> where F: Fn(HRESULT) + Send + 'static {
+        let native = FontDownloadListenerImpl {
+            refcount: atomic::AtomicUsize::new(0),
+            callback: Box::new(callback),
+        };
+        ComPtr::from_ptr(native.into_interface())
+    }
+}
+
+impl Com<IDWriteFontDownloadListener> for FontDownloadListenerImpl {
+    type Vtbl = IDWriteFontDownloadListenerVtbl;
+    fn vtbl() -> &'static IDWriteFontDownloadListenerVtbl { FontDownloadListenerImplVtbl }
+}
+
+impl Com<IUnknown> for FontDownloadListenerImpl {
+    type Vtbl = IUnknownVtbl;
+    fn vtbl() -> &'static IUnknownVtbl { &FontDownloadListenerImplVtbl.parent }
+}
@@ -0,0 +1,100 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+use std::ptr;
+
+use comptr::ComPtr;
+use font_download_listener_impl::FontDownloadListenerImpl;
+use font_set::factory3;
+use winapi::shared::winerror::{HRESULT, S_FALSE, S_OK};
+use winapi::um::dwrite_3::IDWriteFontDownloadQueue;
+
+// The factory's queue of pending requests for fonts that aren't local yet
+// (cloud fonts, which DirectWrite can list before it has their data).
+// Layouts and font faces that run into such fonts add requests to it;
+// begin_download fetches them.  Needs IDWriteFactory3 (Windows 10);
+// system returns E_NOINTERFACE if it isn't available.
+pub struct FontDownloadQueue {
+    native: UnsafeCell<ComPtr<IDWriteFontDownloadQueue>>,
+}
+
+impl FontDownloadQueue {
+    pub fn system() -> Result<FontDownloadQueue, HRESULT> {
+        unsafe {
+            let mut native: ComPtr<IDWriteFontDownloadQueue> = ComPtr::new();
+            let hr = factory3()?.GetFontDownloadQueue(native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(FontDownloadQueue::take(native))
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteFontDownloadQueue>) -> FontDownloadQueue {
+        FontDownloadQueue {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteFontDownloadQueue {
+        (*self.native.get()).as_ptr()
+    }
+
+    // Whether there are no requests waiting to be downloaded.
+    pub fn is_empty(&self) -> bool {
+        unsafe {
+            (*self.native.get()).IsEmpty() != 0
+        }
+    }
+
+    // Starts downloading everything in the queue, returning immediately;
+    // listeners are called when it finishes.  Returns false, and starts
+    // nothing, if the queue is empty and no download is in progress.
+    pub fn begin_download(&self) -> Result<bool, HRESULT> {
+        unsafe {
+            match (*self.native.get()).BeginDownload(ptr::null_mut()) {
+                S_OK => Ok(true),
+                S_FALSE => Ok(false),
+                hr => Err(hr),
+            }
+        }
+    }
+
+    // Drops the queued requests and stops any download in progress.
+    pub fn cancel_download(&self) {
+        unsafe {
+            let hr = (*self.native.get()).CancelDownload();
+            assert!(hr == 0);
+        }
+    }
+
+    // Goes up each time a download adds font data, so anything measured
+    // or laid out with an older count may be out of date.
+    pub fn generation_count(&self) -> u64 {
+        unsafe {
+            (*self.native.get()).GetGenerationCount()
+        }
+    }
+
+    // Calls `callback` with the result of every download from now on, on
+    // whichever thread DirectWrite finishes it on.  Returns a token for
+    // remove_listener.
+    pub fn add_listener<F>(&self, callback: F) -> u32 where F: Fn(HRESULT) + Send + 'static {
+        let listener = FontDownloadListenerImpl::create(callback);
+        unsafe {
+            let mut token = 0;
+            let hr = (*self.native.get()).AddListener(listener.as_ptr(), &mut token);
+            assert!(hr == 0);
+            token
+        }
+    }
+
+    pub fn remove_listener(&self, token: u32) {
+        unsafe {
+            let hr = (*self.native.get()).RemoveListener(token);
+            assert!(hr == 0);
+        }
+    }
+}
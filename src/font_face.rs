@@ -2,36 +2,230 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use std::fmt;
 use std::slice;
 use std::ptr;
-use std::cell::UnsafeCell;
+use std::cell::{RefCell, UnsafeCell};
+use std::collections::HashMap;
 use std::mem::zeroed;
 
 use comptr::ComPtr;
-use super::{FontMetrics, FontFile, DefaultDWriteRenderParams, DWriteFactory};
+use geometry_sink_impl::{self, GeometrySinkImpl};
+use super::{FaceType, FontCollection, FontMetrics, FontFile, DefaultDWriteRenderParams, DWriteFactory, RenderingParams};
+use super::{AlphaBlendParams, GlyphMetricsCache, GlyphOffset, GlyphRunAnalysis};
+use glyph_run_analysis::rasterizable_rendering_mode;
+use helpers::{locale_name_to_language_id, system_locale_name};
+use tables::{self, ColorPalettes, FvarAxis, FvarInstance, HeadTable, NameRecord, Os2Metrics, StatAxis};
+use tables::VerticalGlyphMetrics;
+use tables::make_opentype_tag;
 
-use winapi::um::dwrite::{DWRITE_RENDERING_MODE, DWRITE_RENDERING_MODE_DEFAULT};
+use winapi::um::dwrite::{DWRITE_RENDERING_MODE, DWRITE_RENDERING_MODE_DEFAULT, DWRITE_RENDERING_MODE_ALIASED};
+use winapi::um::dwrite::{DWRITE_GLYPH_RUN, DWRITE_TEXTURE_TYPE};
+use winapi::um::dwrite::{DWRITE_TEXTURE_ALIASED_1x1, DWRITE_TEXTURE_CLEARTYPE_3x1};
+use winapi::um::dwrite::{DWRITE_PIXEL_GEOMETRY_BGR, DWRITE_PIXEL_GEOMETRY_FLAT};
 use winapi::um::dwrite::{DWRITE_FONT_METRICS, DWRITE_FONT_SIMULATIONS, DWRITE_MATRIX};
 use winapi::um::dwrite::{DWRITE_GLYPH_METRICS, DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC};
 use winapi::um::dwrite::{IDWriteRenderingParams, IDWriteFontFace, IDWriteFontFile};
 use winapi::shared::minwindef::{BOOL, FALSE};
+use winapi::shared::winerror::HRESULT;
 use winapi::ctypes::c_void;
-use winapi::um::dcommon::DWRITE_MEASURING_MODE;
+use winapi::shared::windef::RECT;
+use winapi::um::dcommon::{DWRITE_MEASURING_MODE, DWRITE_MEASURING_MODE_NATURAL};
+use winapi::um::dwrite_1::{IDWriteFontFace1, DWRITE_OUTLINE_THRESHOLD_ANTIALIASED, DWRITE_UNICODE_RANGE};
+use winapi::um::dwrite_2::{IDWriteColorGlyphRunEnumerator, IDWriteFactory2, DWRITE_COLOR_GLYPH_RUN};
+use winapi::um::dwrite_2::{IDWriteFontFace2, DWRITE_GRID_FIT_MODE, DWRITE_GRID_FIT_MODE_DEFAULT};
+use winapi::um::dwrite_3::{IDWriteFontFace3, IDWriteFontFace4, IDWriteFontFace5, DWRITE_FONT_AXIS_VALUE};
+use winapi::um::dwrite_3::DWRITE_RENDERING_MODE1_NATURAL_SYMMETRIC_DOWNSAMPLED;
+use winapi::um::dcommon::{DWRITE_GLYPH_IMAGE_DATA, DWRITE_GLYPH_IMAGE_FORMATS, DWRITE_GLYPH_IMAGE_FORMATS_JPEG};
+use winapi::um::dcommon::{DWRITE_GLYPH_IMAGE_FORMATS_PNG, DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8};
+use winapi::um::dcommon::{DWRITE_GLYPH_IMAGE_FORMATS_SVG, DWRITE_GLYPH_IMAGE_FORMATS_TIFF, D2D1_POINT_2L};
+use winapi::um::d2d1::ID2D1SimplifiedGeometrySink;
+use winapi::Interface;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecommendedRendering {
+    // IDWriteFontFace3 answers with a DWRITE_RENDERING_MODE1, but its one
+    // extra mode, NATURAL_SYMMETRIC_DOWNSAMPLED, is reported as
+    // NATURAL_SYMMETRIC, so this is always a DWRITE_RENDERING_MODE.  It can
+    // be DWRITE_RENDERING_MODE_OUTLINE for very large text, which the
+    // rasterizing methods treat as NATURAL_SYMMETRIC.
+    pub mode: DWRITE_RENDERING_MODE,
+    // Always DWRITE_GRID_FIT_MODE_DEFAULT below IDWriteFontFace2.
+    pub grid_fit: DWRITE_GRID_FIT_MODE,
+}
+
+// Line metrics scaled to an em size.  Descent is positive, measured
+// downwards from the baseline, as in DWRITE_FONT_METRICS.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    // ascent + descent + line_gap
+    pub line_height: f32,
+}
+
+impl LineMetrics {
+    fn from_design_units(ascent: f32, descent: f32, line_gap: f32, scale: f32) -> LineMetrics {
+        LineMetrics {
+            ascent: ascent * scale,
+            descent: descent * scale,
+            line_gap: line_gap * scale,
+            line_height: (ascent + descent + line_gap) * scale,
+        }
+    }
+}
+
+// Glyph metrics after applying a DWRITE_MATRIX, in design units and
+// DirectWrite's y-down rendering coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransformedGlyphMetrics {
+    // (left, top, right, bottom) of the axis-aligned box around the
+    // transformed ink box, relative to the glyph origin.
+    pub ink_bounds: (f32, f32, f32, f32),
+    // The transformed advance vector.
+    pub advance: (f32, f32),
+}
+
+#[derive(Clone)]
+pub struct RasterizedGlyph {
+    // Pixel bounds relative to the glyph origin on the baseline, so left
+    // and top are usually negative.  Empty for glyphs with no ink.
+    pub bounds: RECT,
+    pub texture_type: DWRITE_TEXTURE_TYPE,
+    // Row-major coverage: one byte per pixel for
+    // DWRITE_TEXTURE_ALIASED_1x1, three (R, G, B) for
+    // DWRITE_TEXTURE_CLEARTYPE_3x1.
+    pub data: Vec<u8>,
+    // The gamma, enhanced contrast and ClearType level to blend data with,
+    // derived from the rendering params passed to rasterize_glyph.
+    pub alpha_blend: AlphaBlendParams,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StylisticSet {
+    // 1 for 'ss01' up to 20 for 'ss20'.
+    pub number: u8,
+    // The font's display name for the set, such as "Alternate a".
+    pub name: Option<String>,
+}
+
+// One layer of a color glyph: glyphs to draw in a single color, on top of
+// the layers before it.
+#[derive(Clone)]
+pub struct ColorGlyphLayer {
+    pub glyph_indices: Vec<u16>,
+    pub glyph_advances: Vec<f32>,
+    pub glyph_offsets: Vec<GlyphOffset>,
+    // Where to draw the layer's glyph run, relative to the color glyph's
+    // baseline origin.
+    pub baseline_origin: (f32, f32),
+    // RGBA from the palette, or None if the layer should be drawn in the
+    // current text color.
+    pub color: Option<[f32; 4]>,
+}
+
+// A glyph's image from an embedded bitmap strike.  Origins are in pixels
+// from the image's top left corner: place horizontal_left_origin on the
+// pen position for horizontal text, vertical_top_origin for vertical.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapGlyph {
+    // DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8, in which case
+    // data is width * height BGRA pixels, or PNG, JPEG or TIFF, in which
+    // case data is the encoded image as stored in the font.
+    pub format: DWRITE_GLYPH_IMAGE_FORMATS,
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub horizontal_left_origin: (i32, i32),
+    pub horizontal_right_origin: (i32, i32),
+    pub vertical_top_origin: (i32, i32),
+    pub vertical_bottom_origin: (i32, i32),
+}
+
+// Something wrong with a font's tables, found by FontFace::validate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationProblem {
+    MissingTable([u8; 4]),
+    // Present but too short or with an unknown version.
+    MalformedTable([u8; 4]),
+    // The face has neither 'glyf' and 'loca' nor 'CFF ' or 'CFF2'.
+    MissingOutlines,
+    // 'maxp' and the CFF CharStrings disagree on the number of glyphs.
+    GlyphCountMismatch { maxp: u16, cff: u16 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    pub problems: Vec<ValidationProblem>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tag = |tag: &[u8; 4]| String::from_utf8_lossy(tag).into_owned();
+        let descriptions: Vec<String> = self.problems.iter().map(|problem| match *problem {
+            ValidationProblem::MissingTable(ref t) => format!("missing '{}' table", tag(t)),
+            ValidationProblem::MalformedTable(ref t) => format!("malformed '{}' table", tag(t)),
+            ValidationProblem::MissingOutlines => "no 'glyf'/'loca' or 'CFF ' outlines".to_owned(),
+            ValidationProblem::GlyphCountMismatch { maxp, cff } => {
+                format!("'maxp' has {} glyphs but 'CFF ' has {}", maxp, cff)
+            }
+        }).collect();
+        write!(f, "invalid font: {}", descriptions.join(", "))
+    }
+}
+
+// The whole number of pixels per em that GDI-compatible measuring and
+// rendering (GetGdiCompatibleGlyphMetrics, DWRITE_MEASURING_MODE_GDI_*)
+// actually use for em_size DIPs: the size in pixels, rounded to the
+// nearest integer with halves rounded up, as GDI rounds font heights.
+// Divide by pixels_per_dip for the equivalent em size in DIPs, which is
+// what GDI text of the same size measures at.
+pub fn gdi_compatible_ppem(em_size: f32, pixels_per_dip: f32) -> f32 {
+    (em_size * pixels_per_dip + 0.5).floor()
+}
+
+// Whether an SVG document from FontFace::get_glyph_svg is gzip-compressed,
+// as the OpenType 'SVG ' table allows; such documents start with the gzip
+// magic bytes rather than markup and must be inflated before parsing.
+// This crate has no gzip decoder, so that is up to the caller.
+pub fn svg_document_is_compressed(document: &[u8]) -> bool {
+    document.starts_with(&[0x1f, 0x8b])
+}
 
 pub struct FontFace {
     native: UnsafeCell<ComPtr<IDWriteFontFace>>,
     metrics: FontMetrics,
+    // GSUB and GPOS feature tags, parsed on first use by has_feature.
+    feature_tags: RefCell<Option<Vec<[u8; 4]>>>,
+    // 'post' glyph names, parsed on first use by glyph_name or
+    // glyph_for_name; empty if the font has none.
+    glyph_names: RefCell<Option<Vec<String>>>,
+    // The newest IDWriteFontFaceN the face supports, and N, probed once
+    // here rather than by QueryInterface in every method that needs one.
+    // Each interface extends the one before it, so this pointer serves as
+    // any of them up to N.  By level:
+    //   1 (Windows 8): unicode_ranges, recommended rendering modes
+    //   2 (Windows 8.1): recommended grid fitting
+    //   3 (Windows 10): recommended rendering with outline thresholds
+    //   4 (Windows 10 1607): embedded bitmaps and SVG glyph images
+    //   5 (Windows 10 1803): font variations
+    // N is 0, and the pointer is native itself, before Windows 8.
+    newest_interface: (ComPtr<IDWriteFontFace>, u32),
 }
 
 impl FontFace {
     pub fn take(native: ComPtr<IDWriteFontFace>) -> FontFace {
         unsafe {
             let mut metrics: FontMetrics = zeroed();
-            let cell = UnsafeCell::new(native);
-            (*cell.get()).GetMetrics(&mut metrics);
+            native.GetMetrics(&mut metrics);
+            let newest_interface = newest_font_face_interface(&native);
             FontFace {
-                native: cell,
+                native: UnsafeCell::new(native),
                 metrics: metrics,
+                feature_tags: RefCell::new(None),
+                glyph_names: RefCell::new(None),
+                newest_interface,
             }
         }
     }
@@ -40,28 +234,61 @@ impl FontFace {
         (*self.native.get()).as_ptr()
     }
 
-    unsafe fn get_raw_files(&self) -> Vec<*mut IDWriteFontFile> {
+    // Whether this is a variable font the system can vary: it must have
+    // 'fvar' axes, and DirectWrite must be new enough (IDWriteFontFace5)
+    // to apply axis values to it.  Without that, variable fonts only ever
+    // draw at their default instance.
+    pub fn supports_variations(&self) -> bool {
+        match self.font_face_interface::<IDWriteFontFace5>(5) {
+            Some(face) => unsafe { face.HasVariations() != 0 },
+            None => false,
+        }
+    }
+
+    // The face as IDWriteFontFace`version`, which T must be, if the system
+    // supports it.
+    fn font_face_interface<T>(&self, version: u32) -> Option<&T> {
+        let (ref newest, newest_version) = self.newest_interface;
+        if newest_version < version {
+            return None;
+        }
+        unsafe { Some(&*(newest.as_ptr() as *const T)) }
+    }
+
+    unsafe fn get_raw_files(&self) -> Result<Vec<*mut IDWriteFontFile>, HRESULT> {
         let mut number_of_files: u32 = 0;
         let hr = (*self.native.get()).GetFiles(&mut number_of_files, ptr::null_mut());
-        assert!(hr == 0);
+        if hr != 0 {
+            return Err(hr);
+        }
 
         let mut file_ptrs: Vec<*mut IDWriteFontFile> =
             vec![ptr::null_mut(); number_of_files as usize];
         let hr = (*self.native.get()).GetFiles(&mut number_of_files, file_ptrs.as_mut_ptr());
-        assert!(hr == 0);
-        file_ptrs
+        if hr != 0 {
+            return Err(hr);
+        }
+        Ok(file_ptrs)
     }
 
     pub fn get_files(&self) -> Vec<FontFile> {
+        self.try_get_files().unwrap()
+    }
+
+    // The try_ methods are the fallible versions of the methods of the same
+    // name without the prefix, returning DirectWrite's error rather than
+    // panicking, for callers that can't afford a panic (or abort) on a
+    // broken font.
+    pub fn try_get_files(&self) -> Result<Vec<FontFile>, HRESULT> {
         unsafe {
-            let file_ptrs = self.get_raw_files();
-            file_ptrs.iter().map(|p| FontFile::take(ComPtr::already_addrefed(*p))).collect()
+            let file_ptrs = self.get_raw_files()?;
+            Ok(file_ptrs.iter().map(|p| FontFile::take(ComPtr::already_addrefed(*p))).collect())
         }
     }
 
     pub fn create_font_face_with_simulations(&self, simulations: DWRITE_FONT_SIMULATIONS) -> FontFace {
         unsafe {
-            let file_ptrs = self.get_raw_files();
+            let file_ptrs = self.get_raw_files().unwrap();
             let face_type = (*self.native.get()).GetType();
             let face_index = (*self.native.get()).GetIndex();
             let mut face: ComPtr<IDWriteFontFace> = ComPtr::new();
@@ -81,6 +308,88 @@ impl FontFace {
         }
     }
 
+    pub fn face_type(&self) -> FaceType {
+        unsafe {
+            FaceType::from_u32((*self.native.get()).GetType())
+        }
+    }
+
+    // Splits a glyph into its COLR layers, each with its color from CPAL
+    // palette `palette_index`.  Empty if the glyph has no color layers,
+    // the palette doesn't exist, or the system is older than Windows 8.1
+    // (no IDWriteFactory2); draw the glyph normally in that case.
+    pub fn get_color_glyph_layers(&self, glyph: u16, em_size: f32, palette_index: u32) -> Vec<ColorGlyphLayer> {
+        unsafe {
+            let factory = match ComPtr::from_ptr(DWriteFactory())
+                    .query_interface::<IDWriteFactory2>(&IDWriteFactory2::uuidof()) {
+                Some(factory) => factory,
+                None => return vec![],
+            };
+
+            let advance = 0f32;
+            let offset = GlyphOffset { advanceOffset: 0., ascenderOffset: 0. };
+            let mut glyph_run: DWRITE_GLYPH_RUN = zeroed();
+            glyph_run.fontFace = self.as_ptr();
+            glyph_run.fontEmSize = em_size;
+            glyph_run.glyphCount = 1;
+            glyph_run.glyphIndices = &glyph;
+            glyph_run.glyphAdvances = &advance;
+            glyph_run.glyphOffsets = &offset;
+
+            let mut enumerator: ComPtr<IDWriteColorGlyphRunEnumerator> = ComPtr::new();
+            let hr = factory.TranslateColorGlyphRun(0., 0., &glyph_run, ptr::null(), DWRITE_MEASURING_MODE_NATURAL,
+                                                    ptr::null(), palette_index, enumerator.getter_addrefs());
+            if hr != 0 {
+                return vec![];
+            }
+
+            let mut layers = vec![];
+            loop {
+                let mut has_run: BOOL = FALSE;
+                let hr = enumerator.MoveNext(&mut has_run);
+                if hr != 0 || has_run == FALSE {
+                    break;
+                }
+                let mut color_run: *const DWRITE_COLOR_GLYPH_RUN = ptr::null();
+                let hr = enumerator.GetCurrentRun(&mut color_run);
+                if hr != 0 {
+                    break;
+                }
+
+                let color_run = &*color_run;
+                let run = &color_run.glyphRun;
+                let count = run.glyphCount as usize;
+                let color = &color_run.runColor;
+                layers.push(ColorGlyphLayer {
+                    glyph_indices: slice::from_raw_parts(run.glyphIndices, count).to_vec(),
+                    glyph_advances: if run.glyphAdvances.is_null() {
+                        vec![0.; count]
+                    } else {
+                        slice::from_raw_parts(run.glyphAdvances, count).to_vec()
+                    },
+                    glyph_offsets: if run.glyphOffsets.is_null() {
+                        vec![GlyphOffset { advanceOffset: 0., ascenderOffset: 0. }; count]
+                    } else {
+                        slice::from_raw_parts(run.glyphOffsets, count).to_vec()
+                    },
+                    baseline_origin: (color_run.baselineOriginX, color_run.baselineOriginY),
+                    color: if color_run.paletteIndex == 0xffff {
+                        None
+                    } else {
+                        Some([color.r, color.g, color.b, color.a])
+                    },
+                });
+            }
+            layers
+        }
+    }
+
+    // Whether the face belongs to an installed font.  Faces created from
+    // font data in memory, or from files that aren't installed, are not.
+    pub fn is_in_system_collection(&self) -> bool {
+        FontCollection::system().get_font_from_face(self).is_some()
+    }
+
     pub fn get_glyph_count(&self) -> u16 {
         unsafe {
             (*self.native.get()).GetGlyphCount()
@@ -91,6 +400,130 @@ impl FontFace {
         &self.metrics
     }
 
+    // The size of the em square in design units; glyph metrics are scaled
+    // to a font size by em_size / units_per_em.
+    pub fn units_per_em(&self) -> u16 {
+        self.metrics.designUnitsPerEm
+    }
+
+    // Ascent, descent and line gap from DirectWrite's font metrics, scaled
+    // to em_size.
+    pub fn line_metrics(&self, em_size: f32) -> LineMetrics {
+        let scale = em_size / self.metrics.designUnitsPerEm as f32;
+        LineMetrics::from_design_units(self.metrics.ascent as f32,
+                                       self.metrics.descent as f32,
+                                       self.metrics.lineGap as f32,
+                                       scale)
+    }
+
+    // Like line_metrics, but uses the OS/2 typo metrics instead when the
+    // font sets USE_TYPO_METRICS, which is what CSS line boxes follow.
+    pub fn preferred_line_metrics(&self, em_size: f32) -> LineMetrics {
+        match self.get_os2_metrics() {
+            Some(ref os2) if os2.use_typo_metrics() => {
+                let scale = em_size / self.metrics.designUnitsPerEm as f32;
+                LineMetrics::from_design_units(os2.typo_ascender as f32,
+                                               -(os2.typo_descender as f32),
+                                               os2.typo_line_gap as f32,
+                                               scale)
+            }
+            _ => self.line_metrics(em_size),
+        }
+    }
+
+    // The underline for a run `width` wide at em_size, as (left, top,
+    // right, bottom) relative to the run's baseline origin, with y growing
+    // downwards as in a bitmap.  The font gives the position of the top of
+    // the line, as a distance above the baseline, so a typical underline
+    // has a positive top.
+    pub fn underline_rect(&self, em_size: f32, width: f32) -> (f32, f32, f32, f32) {
+        self.decoration_rect(self.metrics.underlinePosition, self.metrics.underlineThickness, em_size, width)
+    }
+
+    // Like underline_rect, for a line through the text; the top is
+    // negative for any sensible font.
+    pub fn strikethrough_rect(&self, em_size: f32, width: f32) -> (f32, f32, f32, f32) {
+        self.decoration_rect(self.metrics.strikethroughPosition, self.metrics.strikethroughThickness,
+                             em_size, width)
+    }
+
+    fn decoration_rect(&self, position: i16, thickness: u16, em_size: f32, width: f32) -> (f32, f32, f32, f32) {
+        let scale = em_size / self.metrics.designUnitsPerEm as f32;
+        let top = -(position as f32) * scale;
+        (0., top, width, top + thickness as f32 * scale)
+    }
+
+    // The tight bounds of the glyph's actual outline, in ems, as (left,
+    // top, right, bottom) relative to its origin with y growing downwards;
+    // multiply by an em size for DIPs.  Unlike the 'glyf' bounding box or
+    // the design metrics, which font tools don't always keep up to date,
+    // this is measured from the outline DirectWrite draws, curves
+    // included.  None for glyphs with no outline, such as the space.
+    pub fn glyph_outline_bounds(&self, glyph: u16) -> Option<(f32, f32, f32, f32)> {
+        let sink = self.get_glyph_run_outline(1., &[glyph]);
+        geometry_sink_impl::path_bounds(unsafe { GeometrySinkImpl::commands(&sink) })
+    }
+
+    // The outlines of `glyphs` at em_size, laid out from the origin with
+    // their default advances, as polygons ready for triangulation: one
+    // contour per figure of the outline, in DIPs with y growing downwards,
+    // with curves split into line segments no more than `tolerance` DIPs
+    // from the true curve.  Outer contours and holes wind in opposite
+    // directions, so fill with the nonzero rule.
+    pub fn glyph_run_flattened(&self, glyphs: &[u16], em_size: f32, tolerance: f32) -> Vec<Vec<(f32, f32)>> {
+        let sink = self.get_glyph_run_outline(em_size, glyphs);
+        geometry_sink_impl::flatten_path(unsafe { GeometrySinkImpl::commands(&sink) }, tolerance)
+    }
+
+    // The outline of `glyphs` at em_size, laid out with their default
+    // advances from the origin.
+    fn get_glyph_run_outline(&self, em_size: f32, glyphs: &[u16]) -> ComPtr<ID2D1SimplifiedGeometrySink> {
+        let sink = GeometrySinkImpl::create();
+        unsafe {
+            let hr = (*self.native.get()).GetGlyphRunOutline(em_size,
+                                                             glyphs.as_ptr(),
+                                                             ptr::null(),
+                                                             ptr::null(),
+                                                             glyphs.len() as u32,
+                                                             FALSE,
+                                                             FALSE,
+                                                             sink.as_ptr());
+            assert!(hr == 0);
+        }
+        sink
+    }
+
+    // The line height browsers use for `line-height: normal`, scaled to
+    // em_size.  With USE_TYPO_METRICS that's the typo ascender, descender
+    // and line gap.  Otherwise it's the win ascent and descent, plus
+    // whatever extra line gap the 'hhea' metrics ask for beyond them.
+    // Fonts without usable win metrics fall back to 'hhea' alone, and
+    // fonts with neither table to DirectWrite's own metrics.
+    pub fn css_line_height(&self, em_size: f32) -> f32 {
+        let scale = em_size / self.metrics.designUnitsPerEm as f32;
+        let os2 = self.get_os2_metrics();
+        let hhea = self.get_font_table(make_opentype_tag(b"hhea"))
+                       .and_then(|table| tables::parse_hhea_line_metrics(&table))
+                       .map(|(ascender, descender, line_gap)| {
+                           ascender as i32 - descender as i32 + line_gap as i32
+                       });
+
+        let design_units = match (os2, hhea) {
+            (Some(ref os2), _) if os2.use_typo_metrics() => {
+                os2.typo_ascender as i32 - os2.typo_descender as i32 + os2.typo_line_gap as i32
+            }
+            (Some(ref os2), hhea) if os2.win_ascent as i32 + os2.win_descent as i32 > 0 => {
+                let win = os2.win_ascent as i32 + os2.win_descent as i32;
+                win + hhea.map(|hhea| (hhea - win).max(0)).unwrap_or(0)
+            }
+            (_, Some(hhea)) => hhea,
+            _ => {
+                self.metrics.ascent as i32 + self.metrics.descent as i32 + self.metrics.lineGap as i32
+            }
+        };
+        design_units as f32 * scale
+    }
+
     pub fn get_metrics(&self) -> FontMetrics {
         unsafe {
             let mut metrics: DWRITE_FONT_METRICS = zeroed();
@@ -100,31 +533,178 @@ impl FontFace {
     }
 
     pub fn get_glyph_indices(&self, code_points: &[u32]) -> Vec<u16> {
+        self.try_get_glyph_indices(code_points).unwrap()
+    }
+
+    pub fn try_get_glyph_indices(&self, code_points: &[u32]) -> Result<Vec<u16>, HRESULT> {
         unsafe {
             let mut glyph_indices: Vec<u16> = vec![0; code_points.len()];
             let hr = (*self.native.get()).GetGlyphIndices(code_points.as_ptr(),
                                                           code_points.len() as u32,
                                                           glyph_indices.as_mut_ptr());
-            assert!(hr == 0);
-            glyph_indices
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(glyph_indices)
         }
     }
 
+    // Whether the face's cmap maps `code_point` to a real glyph (not
+    // .notdef).  Unlike get_glyph_indices this allocates nothing, so it is
+    // cheap enough to call per character in a fallback loop.
+    pub fn has_glyph(&self, code_point: u32) -> bool {
+        let mut glyph: u16 = 0;
+        unsafe {
+            let hr = (*self.native.get()).GetGlyphIndices(&code_point, 1, &mut glyph);
+            hr == 0 && glyph != 0
+        }
+    }
+
+    // The nominal glyph for each char of `text`, one glyph per char, so
+    // characters outside the BMP map to a single glyph rather than one per
+    // UTF-16 surrogate.  This is a straight cmap lookup, not shaping: there
+    // are no ligatures, contextual forms or combining-mark handling.
+    pub fn get_glyph_indices_for_str(&self, text: &str) -> Vec<u16> {
+        let code_points: Vec<u32> = text.chars().map(|c| c as u32).collect();
+        self.get_glyph_indices(&code_points)
+    }
+
+    // Like get_glyph_indices, but code points that map to .notdef (glyph 0)
+    // come back as None.
+    pub fn map_codepoints(&self, code_points: &[u32]) -> Vec<Option<u16>> {
+        self.get_glyph_indices(code_points)
+            .into_iter()
+            .map(|glyph| if glyph == 0 { None } else { Some(glyph) })
+            .collect()
+    }
+
     pub fn get_design_glyph_metrics(&self, glyph_indices: &[u16], is_sideways: bool) -> Vec<DWRITE_GLYPH_METRICS> {
+        self.try_get_design_glyph_metrics(glyph_indices, is_sideways).unwrap()
+    }
+
+    pub fn try_get_design_glyph_metrics(&self, glyph_indices: &[u16], is_sideways: bool)
+                                        -> Result<Vec<DWRITE_GLYPH_METRICS>, HRESULT> {
         unsafe {
             let mut metrics: Vec<DWRITE_GLYPH_METRICS> = vec![zeroed(); glyph_indices.len()];
             let hr = (*self.native.get()).GetDesignGlyphMetrics(glyph_indices.as_ptr(),
                                                                 glyph_indices.len() as u32,
                                                                 metrics.as_mut_ptr(),
                                                                 is_sideways as BOOL);
-            assert!(hr == 0);
-            metrics
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(metrics)
         }
     }
 
+    // For each glyph, the offset in DIPs from its horizontal origin (the
+    // left end of its baseline) to its vertical origin, with y pointing
+    // down.  The vertical origin sits horizontally centered on the advance
+    // and verticalOriginY design units above the baseline; in vertical
+    // runs glyphs are positioned by it, with each vertical advance
+    // measured down from there.
+    pub fn get_vertical_origins(&self, glyph_indices: &[u16], em_size: f32) -> Vec<(f32, f32)> {
+        let scale = em_size / self.metrics.designUnitsPerEm as f32;
+        self.get_design_glyph_metrics(glyph_indices, false).iter().map(|metrics| {
+            (metrics.advanceWidth as f32 / 2. * scale, -(metrics.verticalOriginY as f32) * scale)
+        }).collect()
+    }
+
+    // A cache of this face's horizontal design glyph metrics; use
+    // GlyphMetricsCache::new for sideways metrics.
+    pub fn metrics_cache(&self) -> GlyphMetricsCache<'_> {
+        GlyphMetricsCache::new(self, false)
+    }
+
+    // get_design_glyph_metrics with a transform applied, e.g. the skew used
+    // for a synthetic oblique.  Only the ink box and the advance are
+    // transformed; side bearings aren't meaningful after a skew or
+    // rotation, so they are folded into ink_bounds.  Only the linear part
+    // of the matrix (m11, m12, m21, m22) is used: dx and dy are in DIPs and
+    // have no meaning in design units.
+    pub fn get_transformed_design_glyph_metrics(&self,
+                                                glyph_indices: &[u16],
+                                                is_sideways: bool,
+                                                transform: &DWRITE_MATRIX)
+                                                -> Vec<TransformedGlyphMetrics> {
+        let apply = |x: f32, y: f32| {
+            (x * transform.m11 + y * transform.m21, x * transform.m12 + y * transform.m22)
+        };
+
+        self.get_design_glyph_metrics(glyph_indices, is_sideways).iter().map(|metrics| {
+            // The ink box, y-down from the baseline.  verticalOriginY is the
+            // top side bearing plus the top of the ink box.
+            let left = metrics.leftSideBearing as f32;
+            let right = metrics.advanceWidth as f32 - metrics.rightSideBearing as f32;
+            let top = -(metrics.verticalOriginY as f32 - metrics.topSideBearing as f32);
+            let bottom = -(metrics.verticalOriginY as f32 - metrics.advanceHeight as f32 +
+                           metrics.bottomSideBearing as f32);
+
+            let corners = [apply(left, top), apply(right, top), apply(left, bottom), apply(right, bottom)];
+            let mut ink_bounds = (corners[0].0, corners[0].1, corners[0].0, corners[0].1);
+            for &(x, y) in &corners[1..] {
+                ink_bounds.0 = ink_bounds.0.min(x);
+                ink_bounds.1 = ink_bounds.1.min(y);
+                ink_bounds.2 = ink_bounds.2.max(x);
+                ink_bounds.3 = ink_bounds.3.max(y);
+            }
+
+            TransformedGlyphMetrics {
+                ink_bounds,
+                advance: apply(metrics.advanceWidth as f32, 0.),
+            }
+        }).collect()
+    }
+
+    // The horizontal advance of a glyph at the given em size, scaled from
+    // design units.  Units are whatever em_size is in (pixels or DIPs).
+    pub fn glyph_advance_px(&self, glyph: u16, em_size: f32) -> f32 {
+        self.glyph_advances_px(&[glyph], em_size)[0]
+    }
+
+    pub fn glyph_advances_px(&self, glyph_indices: &[u16], em_size: f32) -> Vec<f32> {
+        let scale = em_size / self.metrics.designUnitsPerEm as f32;
+        self.get_design_glyph_metrics(glyph_indices, false)
+            .iter()
+            .map(|metrics| metrics.advanceWidth as f32 * scale)
+            .collect()
+    }
+
+    // Advance widths in a 1000-unit em, as PDF /W arrays and PostScript
+    // font metrics expect, rounded to the nearest unit with halves rounded
+    // up.  The scaling is done in integers, so a font with 1000 units per
+    // em gets its advances back exactly.
+    pub fn glyph_widths_1000(&self, glyph_indices: &[u16]) -> Vec<i32> {
+        let units_per_em = self.metrics.designUnitsPerEm as i64;
+        self.get_design_glyph_metrics(glyph_indices, false)
+            .iter()
+            .map(|metrics| ((metrics.advanceWidth as i64 * 1000 + units_per_em / 2) / units_per_em) as i32)
+            .collect()
+    }
+
+    // An approximate width for `text`: the sum of the nominal advances of
+    // each character's glyph, found as get_glyph_indices_for_str does, so a
+    // character outside the BMP counts once.  There is no shaping, kerning
+    // or font fallback, so it will be off for complex scripts, ligatures
+    // and characters this face doesn't cover; use DirectWrite's text layout
+    // (IDWriteTextLayout) when that matters.
+    pub fn measure_string_advance(&self, text: &str, em_size: f32) -> f32 {
+        let glyph_indices = self.get_glyph_indices_for_str(text);
+        self.glyph_advances_px(&glyph_indices, em_size).iter().sum()
+    }
+
     pub fn get_gdi_compatible_glyph_metrics(&self, em_size: f32, pixels_per_dip: f32, transform: *const DWRITE_MATRIX,
                                             use_gdi_natural: bool, glyph_indices: &[u16], is_sideways: bool)
                                             -> Vec<DWRITE_GLYPH_METRICS>
+    {
+        self.try_get_gdi_compatible_glyph_metrics(em_size, pixels_per_dip, transform, use_gdi_natural,
+                                                  glyph_indices, is_sideways).unwrap()
+    }
+
+    pub fn try_get_gdi_compatible_glyph_metrics(&self, em_size: f32, pixels_per_dip: f32,
+                                                transform: *const DWRITE_MATRIX, use_gdi_natural: bool,
+                                                glyph_indices: &[u16], is_sideways: bool)
+                                                -> Result<Vec<DWRITE_GLYPH_METRICS>, HRESULT>
     {
         unsafe {
             let mut metrics: Vec<DWRITE_GLYPH_METRICS> = vec![zeroed(); glyph_indices.len()];
@@ -135,12 +715,19 @@ impl FontFace {
                                                                        glyph_indices.len() as u32,
                                                                        metrics.as_mut_ptr(),
                                                                        is_sideways as BOOL);
-            assert!(hr == 0);
-            metrics
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(metrics)
         }
     }
 
     pub fn get_font_table(&self, opentype_table_tag: u32) -> Option<Vec<u8>> {
+        self.try_get_font_table(opentype_table_tag).unwrap()
+    }
+
+    // Ok(None) if the font has no such table.
+    pub fn try_get_font_table(&self, opentype_table_tag: u32) -> Result<Option<Vec<u8>>, HRESULT> {
         unsafe {
             let mut table_data_ptr: *const u8 = ptr::null_mut();
             let mut table_size: u32 = 0;
@@ -152,18 +739,609 @@ impl FontFace {
                                                           &mut table_size,
                                                           &mut table_context,
                                                           &mut exists);
-            assert!(hr == 0);
+            if hr != 0 {
+                return Err(hr);
+            }
 
             if exists == FALSE {
-                return None;
+                return Ok(None);
             }
 
             let table_bytes = slice::from_raw_parts(table_data_ptr, table_size as usize).to_vec();
 
             (*self.native.get()).ReleaseFontTable(table_context);
 
-            Some(table_bytes)
+            Ok(Some(table_bytes))
+        }
+    }
+
+    // The table's length in bytes, without copying it out of the font.
+    // None if the font has no such table or it couldn't be read.
+    fn font_table_size(&self, opentype_table_tag: u32) -> Option<usize> {
+        unsafe {
+            let mut table_data_ptr: *const c_void = ptr::null();
+            let mut table_size: u32 = 0;
+            let mut table_context: *mut c_void = ptr::null_mut();
+            let mut exists: BOOL = FALSE;
+
+            let hr = (*self.native.get()).TryGetFontTable(opentype_table_tag,
+                                                          &mut table_data_ptr,
+                                                          &mut table_size,
+                                                          &mut table_context,
+                                                          &mut exists);
+            if hr != 0 || exists == FALSE {
+                return None;
+            }
+            (*self.native.get()).ReleaseFontTable(table_context);
+            Some(table_size as usize)
+        }
+    }
+
+    // All records of the 'name' table, including the platform, encoding
+    // and language IDs that the informational strings API hides.  Strings
+    // in encodings other than UTF-16BE and Mac Roman are left undecoded.
+    pub fn get_name_records(&self) -> Vec<NameRecord> {
+        self.get_font_table(make_opentype_tag(b"name"))
+            .and_then(|table| tables::parse_name_table(&table))
+            .unwrap_or_default()
+    }
+
+    // Looks up a 'name' table string in the given locale, or the system
+    // locale if None, falling back to en-us and then to any language.
+    pub fn get_name_string(&self, name_id: u16, locale: Option<&str>) -> Option<String> {
+        let locale = locale.map(|locale| locale.to_owned()).unwrap_or_else(system_locale_name);
+        let mut language_ids = vec![];
+        language_ids.extend(locale_name_to_language_id(&locale));
+        language_ids.push(tables::EN_US_LANGUAGE_ID);
+        tables::find_name(&self.get_name_records(), name_id, &language_ids)
+    }
+
+    // The face's current variation axis values.  Empty if the system has no
+    // IDWriteFontFace5, in which case the face is at the font's defaults.
+    fn get_font_axis_values(&self) -> Vec<DWRITE_FONT_AXIS_VALUE> {
+        unsafe {
+            let face = match self.font_face_interface::<IDWriteFontFace5>(5) {
+                Some(face) => face,
+                None => return vec![],
+            };
+            let count = face.GetFontAxisValueCount();
+            let mut values: Vec<DWRITE_FONT_AXIS_VALUE> = vec![zeroed(); count as usize];
+            let hr = face.GetFontAxisValues(values.as_mut_ptr(), count);
+            assert!(hr == 0);
+            values
+        }
+    }
+
+    // For a variable font face sitting exactly on one of the named
+    // instances in 'fvar', that instance's subfamily name (e.g.
+    // "SemiBold"), in the given locale or the system locale if None.
+    pub fn named_instance_name(&self, locale: Option<&str>) -> Option<String> {
+        let fvar = self.get_fvar_table()?;
+        let values = self.get_font_axis_values();
+
+        let current: Vec<f32> = fvar.axes.iter().map(|axis| {
+            let tag = make_opentype_tag(&axis.tag);
+            values.iter()
+                  .find(|value| value.axisTag == tag)
+                  .map(|value| value.value)
+                  .unwrap_or(axis.default_value)
+        }).collect();
+
+        let instance = fvar.instances.iter().find(|instance| {
+            instance.coordinates.iter().zip(&current).all(|(a, b)| (a - b).abs() < 0.01)
+        })?;
+        self.get_name_string(instance.subfamily_name_id, locale)
+    }
+
+    // The variation axes from 'fvar', with the range each one supports.
+    // Empty if the font isn't a variable font or the table can't be
+    // parsed.
+    pub fn get_fvar_axes(&self) -> Vec<FvarAxis> {
+        self.get_fvar_table().map(|fvar| fvar.axes).unwrap_or_default()
+    }
+
+    // The named instances from 'fvar', with one coordinate per axis from
+    // get_fvar_axes.
+    pub fn get_fvar_instances(&self) -> Vec<FvarInstance> {
+        self.get_fvar_table().map(|fvar| fvar.instances).unwrap_or_default()
+    }
+
+    fn get_fvar_table(&self) -> Option<tables::FvarTable> {
+        tables::parse_fvar_table(&self.get_font_table(make_opentype_tag(b"fvar"))?)
+    }
+
+    // The design axes from the 'STAT' table with their named values.  Use
+    // get_name_string to turn the name IDs into display strings.  Empty if
+    // the font has no STAT table or it can't be parsed.
+    pub fn get_stat_axes(&self) -> Vec<StatAxis> {
+        self.get_font_table(make_opentype_tag(b"STAT"))
+            .and_then(|table| tables::parse_stat_table(&table))
+            .map(|stat| stat.axes)
+            .unwrap_or_default()
+    }
+
+    // The STAT elided fallback name, such as "Regular", for a face whose
+    // axis value names are all elidable.  None if the STAT table is
+    // missing or older than version 1.1.
+    pub fn get_stat_elided_fallback_name(&self, locale: Option<&str>) -> Option<String> {
+        let stat = tables::parse_stat_table(&self.get_font_table(make_opentype_tag(b"STAT"))?)?;
+        self.get_name_string(stat.elided_fallback_name_id?, locale)
+    }
+
+    // Vertical advances and top side bearings in design units, read from
+    // 'vhea'/'vmtx'.  Returns an empty vector if the font has no vertical
+    // metrics or they can't be parsed.
+    pub fn get_vertical_glyph_metrics(&self, glyph_indices: &[u16]) -> Vec<VerticalGlyphMetrics> {
+        let vhea = match self.get_font_table(make_opentype_tag(b"vhea")) {
+            Some(vhea) => vhea,
+            None => return vec![],
+        };
+        let vmtx = match self.get_font_table(make_opentype_tag(b"vmtx")) {
+            Some(vmtx) => vmtx,
+            None => return vec![],
+        };
+        tables::parse_vertical_metrics(&vhea, &vmtx, glyph_indices).unwrap_or_default()
+    }
+
+    // The advance width in design units of every glyph in the face,
+    // indexed by glyph ID.  Read straight from 'hmtx', which is much faster
+    // than asking DirectWrite for every glyph; fonts without a usable
+    // 'hmtx' fall back to the design glyph metrics.
+    pub fn all_glyph_advances(&self) -> Vec<u16> {
+        let glyph_count = self.get_glyph_count();
+        let advances = self.get_font_table(make_opentype_tag(b"hhea")).and_then(|hhea| {
+            self.get_font_table(make_opentype_tag(b"hmtx")).and_then(|hmtx| {
+                tables::parse_horizontal_advances(&hhea, &hmtx, glyph_count)
+            })
+        });
+        advances.unwrap_or_else(|| {
+            let glyph_indices: Vec<u16> = (0..glyph_count).collect();
+            self.get_design_glyph_metrics(&glyph_indices, false)
+                .iter()
+                .map(|metrics| metrics.advanceWidth as u16)
+                .collect()
+        })
+    }
+
+    // The code points the face has glyphs for, as sorted, inclusive
+    // (first, last) ranges.  Empty before Windows 8 (no IDWriteFontFace1).
+    pub fn unicode_ranges(&self) -> Vec<(u32, u32)> {
+        unsafe {
+            let face = match self.font_face_interface::<IDWriteFontFace1>(1) {
+                Some(face) => face,
+                None => return vec![],
+            };
+            let mut count = 0;
+            face.GetUnicodeRanges(0, ptr::null_mut(), &mut count);
+            let mut ranges: Vec<DWRITE_UNICODE_RANGE> = vec![zeroed(); count as usize];
+            let hr = face.GetUnicodeRanges(count, ranges.as_mut_ptr(), &mut count);
+            assert!(hr == 0);
+            ranges.iter().map(|range| (range.first, range.last)).collect()
+        }
+    }
+
+    // Every code point in unicode_ranges, in ascending order.  This is
+    // lazy because it can be big: CJK fonts cover tens of thousands of
+    // code points, and pan-Unicode fonts over a hundred thousand.
+    pub fn covered_codepoints(&self) -> impl Iterator<Item = u32> {
+        self.unicode_ranges().into_iter().flat_map(|(first, last)| first..=last)
+    }
+
+    // The code points this face covers and `other` doesn't, as sorted,
+    // inclusive (first, last) ranges with adjacent ranges merged; that
+    // is, what `other` gains from having this face after it in a fallback
+    // chain.
+    pub fn coverage_difference(&self, other: &FontFace) -> Vec<(u32, u32)> {
+        let others = other.unicode_ranges();
+        let mut others = others.iter().peekable();
+        let mut difference: Vec<(u32, u32)> = vec![];
+        for (first, last) in self.unicode_ranges() {
+            let mut start = first;
+            while start <= last {
+                // Skip ranges of `other` that end before this one starts.
+                while let Some(&&(_, other_last)) = others.peek() {
+                    if other_last >= start {
+                        break;
+                    }
+                    others.next();
+                }
+                let end = match others.peek() {
+                    Some(&&(other_first, other_last)) if other_first <= start => {
+                        // Covered by `other` up to other_last.
+                        start = match other_last.checked_add(1) {
+                            Some(next) => next,
+                            None => break,
+                        };
+                        continue;
+                    }
+                    Some(&&(other_first, _)) if other_first <= last => other_first - 1,
+                    _ => last,
+                };
+                match difference.last_mut() {
+                    Some(previous) if previous.1 + 1 == start => previous.1 = end,
+                    _ => difference.push((start, end)),
+                }
+                start = match end.checked_add(1) {
+                    Some(next) => next,
+                    None => break,
+                };
+            }
+        }
+        difference
+    }
+
+    // For each glyph that some code point maps to, all of the code points
+    // that do, from the font's best Unicode 'cmap' subtable.  This is the
+    // mapping a PDF /ToUnicode CMap needs.  Empty if the font has no usable
+    // Unicode cmap.
+    pub fn glyph_to_unicode_map(&self) -> HashMap<u16, Vec<u32>> {
+        self.get_font_table(make_opentype_tag(b"cmap"))
+            .and_then(|table| tables::parse_cmap_glyph_to_unicode(&table))
+            .unwrap_or_default()
+    }
+
+    // Whether the font carries embedded bitmap strikes, in 'EBLC'/'EBDT',
+    // their color 'CBLC'/'CBDT' variant, or 'sbix'.  Many East Asian fonts
+    // have these for crisp rendering at small sizes.
+    pub fn has_embedded_bitmaps(&self) -> bool {
+        [b"EBLC", b"CBLC", b"sbix"].iter().any(|tag| {
+            self.get_font_table(make_opentype_tag(tag)).is_some()
+        })
+    }
+
+    // Checks that the tables every scalable OpenType font needs are there
+    // and agree with each other, for rejecting broken or hostile fonts
+    // before rendering with them.  DirectWrite's own checks when creating
+    // the face are much looser.  Lists every problem found, not just the
+    // first.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        // Tables that are only checked for presence or length aren't
+        // copied out of the font.
+        let table = |tag: &[u8; 4]| self.get_font_table(make_opentype_tag(tag));
+        let table_size = |tag: &[u8; 4]| self.font_table_size(make_opentype_tag(tag));
+        let head_table = table(b"head");
+        let hhea_table = table(b"hhea");
+        let maxp_table = table(b"maxp");
+        let hmtx_size = table_size(b"hmtx");
+
+        let mut problems = vec![];
+        let required = [
+            (b"cmap", table_size(b"cmap").is_some()),
+            (b"head", head_table.is_some()),
+            (b"hhea", hhea_table.is_some()),
+            (b"hmtx", hmtx_size.is_some()),
+            (b"maxp", maxp_table.is_some()),
+        ];
+        for &(tag, present) in &required {
+            if !present {
+                problems.push(ValidationProblem::MissingTable(*tag));
+            }
+        }
+
+        let head = head_table.map(|head| tables::parse_head_table(&head));
+        if let Some(None) = head {
+            problems.push(ValidationProblem::MalformedTable(*b"head"));
+        }
+        let glyph_count = match maxp_table.map(|maxp| tables::parse_maxp_glyph_count(&maxp)) {
+            Some(Some(count)) => Some(count),
+            Some(None) => {
+                problems.push(ValidationProblem::MalformedTable(*b"maxp"));
+                None
+            }
+            None => None,
+        };
+        // hmtx has a full record for each of hhea's long metrics and a
+        // side bearing for every glyph after them.
+        if let (Some(hhea), Some(count)) = (hhea_table, glyph_count) {
+            match tables::parse_hhea_long_metrics_count(&hhea) {
+                Some(long_metrics) if long_metrics > 0 && long_metrics <= count => {
+                    let needed = long_metrics as usize * 4 + (count - long_metrics) as usize * 2;
+                    if hmtx_size.unwrap_or(needed) < needed {
+                        problems.push(ValidationProblem::MalformedTable(*b"hmtx"));
+                    }
+                }
+                _ => problems.push(ValidationProblem::MalformedTable(*b"hhea")),
+            }
+        }
+
+        // DirectWrite takes its glyph count from 'maxp', so a CFF font
+        // whose CharStrings say otherwise has glyphs it can't reach, or
+        // glyph IDs with no outline.
+        let cff = table(b"CFF ");
+        if let (Some(cff), Some(count)) = (cff.as_ref(), glyph_count) {
+            match tables::parse_cff_glyph_count(cff) {
+                Some(cff_count) if cff_count != count => {
+                    problems.push(ValidationProblem::GlyphCountMismatch { maxp: count, cff: cff_count });
+                }
+                Some(_) => {}
+                None => problems.push(ValidationProblem::MalformedTable(*b"CFF ")),
+            }
+        }
+
+        let has_cff = cff.is_some() || table_size(b"CFF2").is_some();
+        match (table_size(b"glyf"), table_size(b"loca")) {
+            (Some(_), Some(loca_size)) => {
+                if let (Some(Some(head)), Some(count)) = (head, glyph_count) {
+                    let entry_size = if head.index_to_loc_format == 0 { 2 } else { 4 };
+                    if loca_size < (count as usize + 1) * entry_size {
+                        problems.push(ValidationProblem::MalformedTable(*b"loca"));
+                    }
+                }
+            }
+            (Some(_), None) => problems.push(ValidationProblem::MissingTable(*b"loca")),
+            (None, Some(_)) => problems.push(ValidationProblem::MissingTable(*b"glyf")),
+            (None, None) if !has_cff => problems.push(ValidationProblem::MissingOutlines),
+            (None, None) => {}
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { problems })
+        }
+    }
+
+    // The glyph's PostScript name from the 'post' table, such as "a.sc"
+    // or "uni0410".  None if the font has no glyph names ('post' format
+    // 3, as many CFF fonts use) or the glyph doesn't exist.
+    pub fn glyph_name(&self, glyph: u16) -> Option<String> {
+        self.with_glyph_names(|names| names.get(glyph as usize).cloned())
+    }
+
+    // The reverse of glyph_name: the first glyph with the given 'post'
+    // name.  None if no glyph has it or the font has no glyph names.
+    pub fn glyph_for_name(&self, name: &str) -> Option<u16> {
+        self.with_glyph_names(|names| names.iter().position(|glyph_name| glyph_name == name))
+            .map(|glyph| glyph as u16)
+    }
+
+    fn with_glyph_names<R, F: FnOnce(&[String]) -> R>(&self, f: F) -> R {
+        let mut glyph_names = self.glyph_names.borrow_mut();
+        if glyph_names.is_none() {
+            *glyph_names = Some(self.get_font_table(make_opentype_tag(b"post"))
+                                    .and_then(|post| tables::parse_post_glyph_names(&post, self.get_glyph_count()))
+                                    .unwrap_or_default());
+        }
+        f(glyph_names.as_ref().unwrap())
+    }
+
+    // Whether the font has TrueType hinting programs ('fpgm', 'prep' or
+    // 'cvt ').  Always false for CFF fonts, whose hints DirectWrite
+    // handles differently.  Unhinted fonts tend to look better without
+    // grid fitting.
+    pub fn is_hinted(&self) -> bool {
+        [b"fpgm", b"prep", b"cvt "].iter().any(|tag| {
+            self.get_font_table(make_opentype_tag(tag)).is_some()
+        })
+    }
+
+    // The ppem sizes that have embedded bitmap strikes, ascending and
+    // without duplicates.  Empty if the font has no bitmap tables.
+    pub fn preferred_bitmap_sizes(&self) -> Vec<u32> {
+        let mut sizes = vec![];
+        for tag in &[b"EBLC", b"CBLC"] {
+            if let Some(table) = self.get_font_table(make_opentype_tag(tag)) {
+                sizes.extend(tables::parse_bitmap_location_sizes(&table).unwrap_or_default());
+            }
+        }
+        if let Some(table) = self.get_font_table(make_opentype_tag(b"sbix")) {
+            sizes.extend(tables::parse_sbix_sizes(&table).unwrap_or_default());
+        }
+        sizes.sort();
+        sizes.dedup();
+        sizes
+    }
+
+    // The glyph's image from the embedded bitmap strike for exactly `ppem`
+    // pixels per em, preferring raw pixels over encoded formats.  None if
+    // there is no such strike, the glyph has no image in it, or the system
+    // has no IDWriteFontFace4 (before Windows 10).  DirectWrite only
+    // exposes 'sbix' and 'CBDT' strikes this way, not monochrome 'EBDT'
+    // ones, which it uses itself when rasterizing at those sizes.
+    pub fn get_embedded_bitmap(&self, glyph: u16, ppem: u32) -> Option<BitmapGlyph> {
+        unsafe {
+            let face = self.font_face_interface::<IDWriteFontFace4>(4)?;
+            let mut formats = 0;
+            let hr = face.GetGlyphImageFormats_2(glyph, ppem, ppem, &mut formats);
+            if hr != 0 {
+                return None;
+            }
+            let format = *[DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8,
+                           DWRITE_GLYPH_IMAGE_FORMATS_PNG,
+                           DWRITE_GLYPH_IMAGE_FORMATS_JPEG,
+                           DWRITE_GLYPH_IMAGE_FORMATS_TIFF].iter().find(|&&format| formats & format != 0)?;
+
+            let mut image: DWRITE_GLYPH_IMAGE_DATA = zeroed();
+            let mut context: *mut c_void = ptr::null_mut();
+            let hr = face.GetGlyphImageData(glyph, ppem, format, &mut image, &mut context);
+            if hr != 0 {
+                return None;
+            }
+            // DirectWrite falls back to the nearest strike.
+            let bitmap = if image.pixelsPerEm != ppem || image.imageData.is_null() {
+                None
+            } else {
+                let point = |p: D2D1_POINT_2L| (p.x, p.y);
+                Some(BitmapGlyph {
+                    format,
+                    data: slice::from_raw_parts(image.imageData as *const u8,
+                                                image.imageDataSize as usize).to_vec(),
+                    width: image.pixelSize.width,
+                    height: image.pixelSize.height,
+                    horizontal_left_origin: point(image.horizontalLeftOrigin),
+                    horizontal_right_origin: point(image.horizontalRightOrigin),
+                    vertical_top_origin: point(image.verticalTopOrigin),
+                    vertical_bottom_origin: point(image.verticalBottomOrigin),
+                })
+            };
+            face.ReleaseGlyphImageData(context);
+            bitmap
+        }
+    }
+
+    // The SVG document that draws `glyph`, from the font's 'SVG ' table, as
+    // stored: possibly shared with other glyphs, in which case the glyph's
+    // element has the id "glyph<index>", and possibly gzip-compressed.
+    // Compressed documents are returned as they are; check with
+    // svg_document_is_compressed and inflate them before parsing.  None if
+    // the glyph has no SVG image or the system has no IDWriteFontFace4
+    // (before Windows 10).
+    pub fn get_glyph_svg(&self, glyph: u16) -> Option<Vec<u8>> {
+        unsafe {
+            let face = self.font_face_interface::<IDWriteFontFace4>(4)?;
+            let mut formats = 0;
+            let hr = face.GetGlyphImageFormats_2(glyph, 0, u32::MAX, &mut formats);
+            if hr != 0 || formats & DWRITE_GLYPH_IMAGE_FORMATS_SVG == 0 {
+                return None;
+            }
+
+            // SVG images are scalable, so any size will do.
+            let mut image: DWRITE_GLYPH_IMAGE_DATA = zeroed();
+            let mut context: *mut c_void = ptr::null_mut();
+            let hr = face.GetGlyphImageData(glyph,
+                                            self.metrics.designUnitsPerEm as u32,
+                                            DWRITE_GLYPH_IMAGE_FORMATS_SVG,
+                                            &mut image,
+                                            &mut context);
+            if hr != 0 {
+                return None;
+            }
+            let document = if image.imageData.is_null() || image.imageDataSize == 0 {
+                None
+            } else {
+                Some(slice::from_raw_parts(image.imageData as *const u8, image.imageDataSize as usize).to_vec())
+            };
+            face.ReleaseGlyphImageData(context);
+            document
+        }
+    }
+
+    // The parts of the 'OS/2' table that DirectWrite doesn't surface, such
+    // as the typo and win metrics separately.  Returns None if the font
+    // has no OS/2 table or it is too short to be valid.
+    pub fn get_os2_metrics(&self) -> Option<Os2Metrics> {
+        self.get_font_table(make_opentype_tag(b"OS/2"))
+            .and_then(|table| tables::parse_os2_table(&table))
+    }
+
+    // The OS/2 achVendID of the font's foundry, such as "MS" or "ADBE",
+    // without the trailing spaces or nulls that pad it to four bytes.
+    // None if there's no OS/2 table or the ID is blank.
+    pub fn vendor_id(&self) -> Option<String> {
+        let vendor_id = self.get_os2_metrics()?.vendor_id;
+        let vendor_id = String::from_utf8_lossy(&vendor_id);
+        let vendor_id = vendor_id.trim_end_matches(&[' ', '\0'][..]);
+        if vendor_id.is_empty() {
+            None
+        } else {
+            Some(vendor_id.to_owned())
+        }
+    }
+
+    // The 'head' table, or the equivalent 'bhed' table of bitmap-only
+    // fonts.  Mostly useful for the font-wide bounding box, which
+    // DirectWrite doesn't expose.
+    pub fn get_head(&self) -> Option<HeadTable> {
+        self.get_font_table(make_opentype_tag(b"head"))
+            .or_else(|| self.get_font_table(make_opentype_tag(b"bhed")))
+            .and_then(|table| tables::parse_head_table(&table))
+    }
+
+    // Whether the font sets USE_TYPO_METRICS in OS/2 fsSelection, in which
+    // case line height should be computed from the typo metrics, as
+    // browsers do.  False if there is no OS/2 table.
+    pub fn prefers_typo_metrics(&self) -> bool {
+        self.get_os2_metrics().map(|os2| os2.use_typo_metrics()).unwrap_or(false)
+    }
+
+    // The font's 'CPAL' color palettes as (r, g, b, a) entries, one inner
+    // vector per palette, with the default palette first.  Color glyph
+    // layers refer to colors by their index within a palette.  Empty if
+    // the font has no CPAL table or it can't be parsed.
+    pub fn get_color_palettes(&self) -> Vec<Vec<(u8, u8, u8, u8)>> {
+        self.get_color_palette_table().map(|cpal| cpal.palettes).unwrap_or_default()
+    }
+
+    pub fn get_color_palette_count(&self) -> usize {
+        self.get_color_palette_table().map(|cpal| cpal.palettes.len()).unwrap_or(0)
+    }
+
+    // The CPAL_PALETTE_* flags saying which palettes suit light or dark
+    // backgrounds, one per palette.  Empty unless the font has a version 1
+    // CPAL table with palette types.
+    pub fn get_color_palette_types(&self) -> Vec<u32> {
+        self.get_color_palette_table().map(|cpal| cpal.palette_types).unwrap_or_default()
+    }
+
+    // Each palette's label (e.g. "Dark on light") from the 'name' table, in
+    // the given locale or the system locale if None, for choosing palettes
+    // by name.  None for palettes without a label; every entry is None
+    // unless the font has a version 1 CPAL table with palette labels.
+    pub fn color_palette_labels(&self, locale: Option<&str>) -> Vec<Option<String>> {
+        let cpal = match self.get_color_palette_table() {
+            Some(cpal) => cpal,
+            None => return vec![],
+        };
+        (0..cpal.palettes.len()).map(|i| {
+            cpal.palette_labels.get(i).cloned().and_then(|name_id| name_id)
+                .and_then(|name_id| self.get_name_string(name_id, locale))
+        }).collect()
+    }
+
+    fn get_color_palette_table(&self) -> Option<ColorPalettes> {
+        self.get_font_table(make_opentype_tag(b"CPAL"))
+            .and_then(|table| tables::parse_cpal_table(&table))
+    }
+
+    // The stylistic sets ('ss01' to 'ss20') the font's GSUB table defines,
+    // in order, with their UI names from the 'name' table in the system
+    // locale where the font provides them.
+    pub fn stylistic_sets(&self) -> Vec<StylisticSet> {
+        let sets = self.get_font_table(make_opentype_tag(b"GSUB"))
+            .and_then(|gsub| tables::parse_stylistic_sets(&gsub))
+            .unwrap_or_default();
+        sets.into_iter().map(|(number, name_id)| {
+            StylisticSet {
+                number,
+                name: name_id.and_then(|name_id| self.get_name_string(name_id, None)),
+            }
+        }).collect()
+    }
+
+    // Whether the font's GSUB or GPOS tables define the given OpenType
+    // feature (e.g. *b"smcp"), for any script.  DirectWrite silently
+    // ignores features a font doesn't have, so this lets callers find out
+    // up front.
+    pub fn has_feature(&self, feature_tag: [u8; 4]) -> bool {
+        let mut feature_tags = self.feature_tags.borrow_mut();
+        if feature_tags.is_none() {
+            let mut tags = vec![];
+            for table_tag in &[b"GSUB", b"GPOS"] {
+                if let Some(table) = self.get_font_table(make_opentype_tag(table_tag)) {
+                    tags.extend(tables::parse_layout_feature_tags(&table).unwrap_or_default());
+                }
+            }
+            tags.sort();
+            tags.dedup();
+            *feature_tags = Some(tags);
+        }
+        feature_tags.as_ref().unwrap().binary_search(&feature_tag).is_ok()
+    }
+
+    // The OpenType script tags (e.g. *b"latn", *b"arab") that the font's
+    // GSUB or GPOS tables have lookups for, sorted.  A font missing a
+    // script here can still have the glyphs, but won't shape them beyond
+    // the defaults.
+    pub fn supported_scripts(&self) -> Vec<[u8; 4]> {
+        let mut tags = vec![];
+        for table_tag in &[b"GSUB", b"GPOS"] {
+            if let Some(table) = self.get_font_table(make_opentype_tag(table_tag)) {
+                tags.extend(tables::parse_layout_script_tags(&table).unwrap_or_default());
+            }
         }
+        tags.sort();
+        tags.dedup();
+        tags
     }
 
     pub fn get_recommended_rendering_mode(&self,
@@ -180,7 +1358,7 @@ impl FontFace {
                                                                   rendering_params,
                                                                   &mut render_mode);
 
-        if !(hr != 0) {
+        if hr != 0 {
           return DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC;
         }
 
@@ -198,4 +1376,242 @@ impl FontFace {
                                           measure_mode,
                                           DefaultDWriteRenderParams())
     }
+
+    // Asks the newest IDWriteFontFaceN interface available for its
+    // recommended rendering mode (and, from IDWriteFontFace2 on, grid fit
+    // mode), falling back to older interfaces if it isn't there or fails.
+    pub fn recommended_rendering_mode(&self,
+                                      em_size: f32,
+                                      pixels_per_dip: f32,
+                                      measure_mode: DWRITE_MEASURING_MODE,
+                                      rendering_params: &RenderingParams)
+                                      -> RecommendedRendering {
+        self.recommended_rendering_mode_up_to(3, em_size, pixels_per_dip, measure_mode, rendering_params)
+    }
+
+    // recommended_rendering_mode, but never using an interface newer than
+    // IDWriteFontFace<max_version>, so each step of the fallback chain
+    // can be exercised on systems that have all of them.
+    pub(crate) fn recommended_rendering_mode_up_to(&self,
+                                                   max_version: u32,
+                                                   em_size: f32,
+                                                   pixels_per_dip: f32,
+                                                   measure_mode: DWRITE_MEASURING_MODE,
+                                                   rendering_params: &RenderingParams)
+                                                   -> RecommendedRendering {
+        unsafe {
+            let dpi = pixels_per_dip * 96.0;
+            let mut mode = DWRITE_RENDERING_MODE_DEFAULT;
+            let mut grid_fit = DWRITE_GRID_FIT_MODE_DEFAULT;
+
+            if max_version >= 3 {
+                if let Some(face) = self.font_face_interface::<IDWriteFontFace3>(3) {
+                    let hr = face.GetRecommendedRenderingMode(em_size, dpi, dpi, ptr::null(), FALSE,
+                                                              DWRITE_OUTLINE_THRESHOLD_ANTIALIASED,
+                                                              measure_mode, rendering_params.as_ptr(),
+                                                              &mut mode, &mut grid_fit);
+                    if hr == 0 {
+                        // Only IDWriteFontFace3 recommends this mode, and
+                        // CreateGlyphRunAnalysis rejects it, so pass on its
+                        // closest relative instead.
+                        if mode == DWRITE_RENDERING_MODE1_NATURAL_SYMMETRIC_DOWNSAMPLED {
+                            mode = DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC;
+                        }
+                        return RecommendedRendering { mode, grid_fit };
+                    }
+                }
+            }
+
+            if max_version >= 2 {
+                if let Some(face) = self.font_face_interface::<IDWriteFontFace2>(2) {
+                    let hr = face.GetRecommendedRenderingMode(em_size, dpi, dpi, ptr::null(), FALSE,
+                                                              DWRITE_OUTLINE_THRESHOLD_ANTIALIASED,
+                                                              measure_mode, rendering_params.as_ptr(),
+                                                              &mut mode, &mut grid_fit);
+                    if hr == 0 {
+                        return RecommendedRendering { mode, grid_fit };
+                    }
+                }
+            }
+
+            grid_fit = DWRITE_GRID_FIT_MODE_DEFAULT;
+            if max_version >= 1 {
+                if let Some(face) = self.font_face_interface::<IDWriteFontFace1>(1) {
+                    let hr = face.GetRecommendedRenderingMode(em_size, dpi, dpi, ptr::null(), FALSE,
+                                                              DWRITE_OUTLINE_THRESHOLD_ANTIALIASED,
+                                                              measure_mode, &mut mode);
+                    if hr == 0 {
+                        return RecommendedRendering { mode, grid_fit };
+                    }
+                }
+            }
+
+            RecommendedRendering {
+                mode: self.get_recommended_rendering_mode(em_size, pixels_per_dip, measure_mode,
+                                                          rendering_params.as_ptr()),
+                grid_fit,
+            }
+        }
+    }
+
+    // The pixel bounds, relative to the run's baseline origin, that
+    // rasterizing the glyphs would cover, without allocating or filling a
+    // texture.  Useful for invalidation and layout.  The texture type
+    // follows rasterize_glyph: aliased for DWRITE_RENDERING_MODE_ALIASED,
+    // ClearType otherwise, with DEFAULT resolved for the default rendering
+    // params, and OUTLINE measured as NATURAL_SYMMETRIC.  Runs without ink,
+    // such as whitespace, give an empty rect.
+    #[allow(clippy::too_many_arguments)]
+    pub fn glyph_run_pixel_bounds(&self,
+                                  glyph_indices: &[u16],
+                                  advances: &[f32],
+                                  em_size: f32,
+                                  pixels_per_dip: f32,
+                                  transform: Option<DWRITE_MATRIX>,
+                                  rendering_mode: DWRITE_RENDERING_MODE,
+                                  measuring_mode: DWRITE_MEASURING_MODE)
+                                  -> RECT {
+        assert!(glyph_indices.len() == advances.len());
+        let empty = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        if glyph_indices.is_empty() {
+            return empty;
+        }
+
+        let rendering_mode = if rendering_mode == DWRITE_RENDERING_MODE_DEFAULT {
+            self.get_recommended_rendering_mode_default_params(em_size, pixels_per_dip, measuring_mode)
+        } else {
+            rendering_mode
+        };
+        let rendering_mode = rasterizable_rendering_mode(rendering_mode);
+        let texture_type = if rendering_mode == DWRITE_RENDERING_MODE_ALIASED {
+            DWRITE_TEXTURE_ALIASED_1x1
+        } else {
+            DWRITE_TEXTURE_CLEARTYPE_3x1
+        };
+
+        unsafe {
+            let mut glyph_run: DWRITE_GLYPH_RUN = zeroed();
+            glyph_run.fontFace = self.as_ptr();
+            glyph_run.fontEmSize = em_size;
+            glyph_run.glyphCount = glyph_indices.len() as u32;
+            glyph_run.glyphIndices = glyph_indices.as_ptr();
+            glyph_run.glyphAdvances = advances.as_ptr();
+
+            let analysis = GlyphRunAnalysis::create(&glyph_run, pixels_per_dip, transform, rendering_mode,
+                                                    measuring_mode, 0., 0.);
+            let bounds = analysis.get_alpha_texture_bounds(texture_type);
+            if bounds.right <= bounds.left || bounds.bottom <= bounds.top {
+                return empty;
+            }
+            bounds
+        }
+    }
+
+    // Rasterizes a single glyph through a GlyphRunAnalysis.  Aliased
+    // rendering produces a 1x1 texture and every other mode a ClearType
+    // 3x1 texture.  The glyph origin is at (0, 0), so the bitmap is
+    // pixel-snapped; draw it at snap_baseline(y) to match snapped layout.
+    // DWRITE_RENDERING_MODE_DEFAULT is resolved against rendering_params,
+    // since glyph run analysis doesn't accept it.
+    //
+    // Glyph run analysis always produces ClearType coverage in RGB order,
+    // so the channels are swapped for BGR params and averaged for FLAT
+    // ones.  The coverage is otherwise left as is; the params' gamma and
+    // enhanced contrast are returned in alpha_blend for the blending step,
+    // so custom params built with RenderingParamsBuilder can be used to
+    // match another platform's text darkness.
+    pub fn rasterize_glyph(&self,
+                           glyph: u16,
+                           em_size: f32,
+                           pixels_per_dip: f32,
+                           rendering_mode: DWRITE_RENDERING_MODE,
+                           measuring_mode: DWRITE_MEASURING_MODE,
+                           rendering_params: &RenderingParams)
+                           -> RasterizedGlyph {
+        let rendering = RecommendedRendering {
+            mode: rendering_mode,
+            grid_fit: DWRITE_GRID_FIT_MODE_DEFAULT,
+        };
+        self.rasterize_glyph_with_grid_fit(glyph, em_size, pixels_per_dip, rendering, measuring_mode,
+                                           rendering_params).unwrap()
+    }
+
+    // rasterize_glyph with rendering.grid_fit passed on to
+    // GlyphRunAnalysis::create_with_grid_fit, whose errors are returned
+    // here; notably, disabling grid fitting needs natural measuring.  The
+    // result of recommended_rendering_mode can be passed straight in:
+    // DWRITE_RENDERING_MODE_OUTLINE, which it gives for very large text,
+    // is rasterized as DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC.
+    pub fn rasterize_glyph_with_grid_fit(&self,
+                                         glyph: u16,
+                                         em_size: f32,
+                                         pixels_per_dip: f32,
+                                         rendering: RecommendedRendering,
+                                         measuring_mode: DWRITE_MEASURING_MODE,
+                                         rendering_params: &RenderingParams)
+                                         -> Result<RasterizedGlyph, HRESULT> {
+        let rendering_mode = if rendering.mode == DWRITE_RENDERING_MODE_DEFAULT {
+            unsafe {
+                self.get_recommended_rendering_mode(em_size, pixels_per_dip, measuring_mode,
+                                                    rendering_params.as_ptr())
+            }
+        } else {
+            rendering.mode
+        };
+        let rendering_mode = rasterizable_rendering_mode(rendering_mode);
+        let texture_type = if rendering_mode == DWRITE_RENDERING_MODE_ALIASED {
+            DWRITE_TEXTURE_ALIASED_1x1
+        } else {
+            DWRITE_TEXTURE_CLEARTYPE_3x1
+        };
+
+        unsafe {
+            let advance = 0f32;
+            let offset = GlyphOffset { advanceOffset: 0., ascenderOffset: 0. };
+            let mut glyph_run: DWRITE_GLYPH_RUN = zeroed();
+            glyph_run.fontFace = self.as_ptr();
+            glyph_run.fontEmSize = em_size;
+            glyph_run.glyphCount = 1;
+            glyph_run.glyphIndices = &glyph;
+            glyph_run.glyphAdvances = &advance;
+            glyph_run.glyphOffsets = &offset;
+
+            let analysis = GlyphRunAnalysis::create_with_grid_fit(&glyph_run, pixels_per_dip, None,
+                                                                  rendering_mode, measuring_mode,
+                                                                  rendering.grid_fit, (0., 0.))?;
+            let bounds = analysis.get_alpha_texture_bounds(texture_type);
+            let mut data = if bounds.right > bounds.left && bounds.bottom > bounds.top {
+                analysis.create_alpha_texture(texture_type, bounds)
+            } else {
+                vec![]
+            };
+            if texture_type == DWRITE_TEXTURE_CLEARTYPE_3x1 {
+                match rendering_params.get_pixel_geometry() {
+                    DWRITE_PIXEL_GEOMETRY_BGR => {
+                        for pixel in data.chunks_mut(3) {
+                            pixel.swap(0, 2);
+                        }
+                    }
+                    DWRITE_PIXEL_GEOMETRY_FLAT => {
+                        for pixel in data.chunks_mut(3) {
+                            let average = ((pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 3) as u8;
+                            pixel.copy_from_slice(&[average; 3]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let alpha_blend = analysis.get_alpha_blend_params(rendering_params);
+            Ok(RasterizedGlyph { bounds, texture_type, data, alpha_blend })
+        }
+    }
+}
+
+fn newest_font_face_interface(native: &ComPtr<IDWriteFontFace>) -> (ComPtr<IDWriteFontFace>, u32) {
+    let newest = native.query_interface::<IDWriteFontFace>(&IDWriteFontFace5::uuidof()).map(|face| (face, 5))
+        .or_else(|| native.query_interface(&IDWriteFontFace4::uuidof()).map(|face| (face, 4)))
+        .or_else(|| native.query_interface(&IDWriteFontFace3::uuidof()).map(|face| (face, 3)))
+        .or_else(|| native.query_interface(&IDWriteFontFace2::uuidof()).map(|face| (face, 2)))
+        .or_else(|| native.query_interface(&IDWriteFontFace1::uuidof()).map(|face| (face, 1)));
+    newest.unwrap_or_else(|| (native.clone(), 0))
 }
@@ -0,0 +1,190 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+use std::ops::Range;
+use std::ptr;
+
+use comptr::ComPtr;
+use helpers::{system_locale_name, ToWide};
+use text_analysis_source_impl::TextAnalysisSourceImpl;
+use winapi::um::dwrite::{IDWriteFont, IDWriteFontCollection, IDWriteFontFamily, IDWriteTextAnalysisSource};
+use winapi::um::dwrite::DWRITE_READING_DIRECTION_LEFT_TO_RIGHT;
+use winapi::um::dwrite_2::{IDWriteFactory2, IDWriteFontFallback};
+use winapi::shared::winerror::HRESULT;
+use winapi::Interface;
+use super::{DWriteFactory, Font, Utf16AnalysisSource};
+
+// A stretch of text that one font, as chosen by font fallback, covers.
+pub struct FallbackRun {
+    // Byte range into the mapped string, or a range of UTF-16 code units
+    // for map_characters_utf16.
+    pub text: Range<usize>,
+    // None if no font covers the text; draw it with the base font.
+    pub font: Option<Font>,
+    // How much to scale the em size by so the fallback font matches the
+    // base font's size.
+    pub scale: f32,
+}
+
+pub struct FontFallback {
+    native: UnsafeCell<ComPtr<IDWriteFontFallback>>,
+}
+
+impl FontFallback {
+    // The system's fallback, which is what text layouts use by default.
+    // None before Windows 8.1 (no IDWriteFactory2).
+    pub fn system() -> Option<FontFallback> {
+        unsafe {
+            let factory = ComPtr::from_ptr(DWriteFactory())
+                .query_interface::<IDWriteFactory2>(&IDWriteFactory2::uuidof())?;
+            let mut native: ComPtr<IDWriteFontFallback> = ComPtr::new();
+            let hr = factory.GetSystemFontFallback(native.getter_addrefs());
+            if hr != 0 {
+                return None;
+            }
+            Some(FontFallback::take(native))
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteFontFallback>) -> FontFallback {
+        FontFallback {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteFontFallback {
+        (*self.native.get()).as_ptr()
+    }
+
+    // Splits `text` into runs that each map to a single font, trying
+    // base_font's family (in its own collection) first and then the
+    // fallback's choices for `locale`, or the system locale if None.  Runs
+    // are byte ranges that always fall on char boundaries, so a character
+    // outside the BMP is never split between fonts.  Fails with
+    // DirectWrite's error if it can't map some part of the text.
+    pub fn map_characters(&self, text: &str, base_font: &Font, locale: Option<&str>)
+                          -> Result<Vec<FallbackRun>, HRESULT> {
+        let locale = locale.map(|locale| locale.to_owned()).unwrap_or_else(system_locale_name);
+        let source = TextAnalysisSourceImpl::create(text, &locale, DWRITE_READING_DIRECTION_LEFT_TO_RIGHT);
+        let runs = unsafe {
+            self.map_source(source.as_ptr(), text.encode_utf16().count() as u32, base_font)?
+        };
+
+        // DirectWrite works in UTF-16 code units; walk the string
+        // alongside to turn them back into byte offsets.
+        let mut chars = text.chars();
+        let mut byte_offset = 0;
+        let mut position = 0;
+        Ok(runs.into_iter().filter_map(|mut run| {
+            let start_byte = byte_offset;
+            while position < run.text.end {
+                match chars.next() {
+                    Some(c) => {
+                        position += c.len_utf16();
+                        byte_offset += c.len_utf8();
+                    }
+                    None => break,
+                }
+            }
+            // A run can only come out empty if the one before it already
+            // took the rest of a character it ended partway through.
+            if start_byte == byte_offset {
+                return None;
+            }
+            run.text = start_byte..byte_offset;
+            Some(run)
+        }).collect())
+    }
+
+    // Like map_characters, for text that is already UTF-16.  The runs'
+    // ranges are in UTF-16 code units.
+    pub fn map_characters_utf16(&self, source: &Utf16AnalysisSource, base_font: &Font)
+                                -> Result<Vec<FallbackRun>, HRESULT> {
+        unsafe {
+            self.map_source(source.as_ptr(), source.len() as u32, base_font)
+        }
+    }
+
+    // Runs in UTF-16 code units, each ending on a character boundary.
+    unsafe fn map_source(&self, source: *mut IDWriteTextAnalysisSource, text_length: u32, base_font: &Font)
+                         -> Result<Vec<FallbackRun>, HRESULT> {
+        let mut family_name = base_font.family_name().to_wide_null();
+        let mut family: ComPtr<IDWriteFontFamily> = ComPtr::new();
+        let mut collection: ComPtr<IDWriteFontCollection> = ComPtr::new();
+        let hr = (*base_font.as_ptr()).GetFontFamily(family.getter_addrefs());
+        if hr == 0 {
+            family.GetFontCollection(collection.getter_addrefs());
+        }
+
+        let mut position = 0;
+        let mut runs = vec![];
+        while position < text_length {
+            let mut mapped_length = 0;
+            let mut mapped_font: ComPtr<IDWriteFont> = ComPtr::new();
+            let mut scale = 1.0;
+            let hr = (*self.native.get()).MapCharacters(source,
+                                                        position,
+                                                        text_length - position,
+                                                        collection.as_ptr(),
+                                                        family_name.as_mut_ptr(),
+                                                        base_font.weight().t(),
+                                                        base_font.style().t(),
+                                                        base_font.stretch().t(),
+                                                        &mut mapped_length,
+                                                        mapped_font.getter_addrefs(),
+                                                        &mut scale);
+            if hr != 0 {
+                return Err(hr);
+            }
+            // Always make progress, even if DirectWrite maps nothing, but
+            // without splitting a surrogate pair.
+            if mapped_length == 0 {
+                mapped_length = character_length(source, position);
+            }
+            let end = (position + mapped_length).min(text_length);
+
+            runs.push(FallbackRun {
+                text: position as usize..end as usize,
+                font: if mapped_font.as_ptr().is_null() { None } else { Some(Font::take(mapped_font)) },
+                scale,
+            });
+            position = end;
+        }
+        Ok(runs)
+    }
+}
+
+// The width of `text` at em_size, measured like
+// FontFace::measure_string_advance but with each run in the font that
+// system font fallback picks for it, so characters base_font lacks (emoji,
+// other scripts) are counted at their real width.  Like
+// measure_string_advance, characters outside the BMP count once.  Without
+// system fallback support, or if fallback fails, this measures everything
+// with base_font.
+pub fn measure_string_with_fallback(text: &str, base_font: &Font, em_size: f32) -> f32 {
+    let runs = FontFallback::system().and_then(|fallback| fallback.map_characters(text, base_font, None).ok());
+    let runs = match runs {
+        Some(runs) => runs,
+        None => return base_font.create_font_face().measure_string_advance(text, em_size),
+    };
+
+    runs.iter().map(|run| {
+        let font = run.font.as_ref().unwrap_or(base_font);
+        font.create_font_face().measure_string_advance(&text[run.text.clone()], em_size * run.scale)
+    }).sum()
+}
+
+// The length in UTF-16 code units of the character at `position`: 2 for a
+// surrogate pair, otherwise 1.
+unsafe fn character_length(source: *mut IDWriteTextAnalysisSource, position: u32) -> u32 {
+    let mut text = ptr::null();
+    let mut length = 0;
+    let hr = (*source).GetTextAtPosition(position, &mut text, &mut length);
+    if hr == 0 && length >= 2 && (0xd800..0xdc00).contains(&*text) && (0xdc00..0xe000).contains(&*text.add(1)) {
+        2
+    } else {
+        1
+    }
+}
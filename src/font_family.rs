@@ -6,7 +6,7 @@ use std::cell::UnsafeCell;
 
 use comptr::ComPtr;
 use winapi::um::dwrite::{IDWriteFont, IDWriteFontFamily, IDWriteFontCollection};
-use winapi::um::dwrite::{IDWriteLocalizedStrings};
+use winapi::um::dwrite::{IDWriteFontList, IDWriteLocalizedStrings};
 
 use super::*;
 use helpers::*;
@@ -36,6 +36,18 @@ impl FontFamily {
         }
     }
 
+    // The family name in `locale`, or the system locale if None, falling
+    // back to en-us and then to whatever name the family has.
+    pub fn localized_name(&self, locale: Option<&str>) -> String {
+        unsafe {
+            let mut family_names: ComPtr<IDWriteLocalizedStrings> = ComPtr::new();
+            let hr = (*self.native.get()).GetFamilyNames(family_names.getter_addrefs());
+            assert!(hr == 0);
+
+            get_locale_string_for(&mut family_names, locale)
+        }
+    }
+
     pub fn get_first_matching_font(&self,
                                    weight: FontWeight,
                                    stretch: FontStretch,
@@ -50,6 +62,22 @@ impl FontFamily {
         }
     }
 
+    // All fonts in the family, ordered from the best match for the given
+    // attributes to the worst.
+    pub fn get_matching_fonts(&self,
+                              weight: FontWeight,
+                              stretch: FontStretch,
+                              style: FontStyle)
+        -> FontList
+    {
+        unsafe {
+            let mut fonts: ComPtr<IDWriteFontList> = ComPtr::new();
+            let hr = (*self.native.get()).GetMatchingFonts(weight.t(), stretch.t(), style.t(), fonts.getter_addrefs());
+            assert!(hr == 0);
+            FontList::take(fonts)
+        }
+    }
+
     pub fn get_font_collection(&self) -> FontCollection {
         unsafe {
             let mut collection: ComPtr<IDWriteFontCollection> = ComPtr::new();
@@ -73,4 +101,17 @@ impl FontFamily {
             Font::take(font)
         }
     }
+
+    // Every font in the family with its face name ("Regular", "Bold
+    // Italic" and so on) in `locale`, or the system locale if None, for
+    // listing in a font picker.  Sorted by weight, then style, keeping the
+    // family's own order otherwise.
+    pub fn list_faces(&self, locale: Option<&str>) -> Vec<(String, Font)> {
+        let mut faces: Vec<(String, Font)> = (0..self.get_font_count()).map(|index| {
+            let font = self.get_font(index);
+            (font.localized_face_name(locale), font)
+        }).collect();
+        faces.sort_by_key(|(_, font)| (font.weight().to_u32(), font.style().to_u32()));
+        faces
+    }
 }
@@ -5,23 +5,28 @@
 use std::slice;
 use std::ptr;
 use std::cell::UnsafeCell;
+use std::path::Path;
 
 use comptr::ComPtr;
 
 use winapi::ctypes::c_void;
 use winapi::um::dwrite::{IDWriteFontFace, IDWriteFontFile, IDWriteFontFileStream};
-use winapi::um::dwrite::{IDWriteFontFileLoader, DWRITE_FONT_SIMULATIONS};
+use winapi::shared::winerror::HRESULT;
+use winapi::um::dwrite::{IDWriteFontFileLoader, DWRITE_FONT_SIMULATIONS, DWRITE_FONT_SIMULATIONS_NONE};
 use winapi::um::dwrite::{DWRITE_FONT_FACE_TYPE_UNKNOWN, DWRITE_FONT_FACE_TYPE};
-use winapi::um::dwrite::DWRITE_FONT_FILE_TYPE_UNKNOWN;
+use winapi::um::dwrite::{DWRITE_FONT_FILE_TYPE, DWRITE_FONT_FILE_TYPE_UNKNOWN};
 
 use font_file_loader_impl::DataFontHelper;
 use font_face::FontFace;
-use super::DWriteFactory;
+use helpers::ToWide;
+use super::{DWriteFactory, FaceType, FontFileType};
 
 pub struct FontFile {
     native: UnsafeCell<ComPtr<IDWriteFontFile>>,
     data_key: usize,
     face_type: DWRITE_FONT_FACE_TYPE,
+    face_count: u32,
+    file_type: DWRITE_FONT_FILE_TYPE,
 }
 
 impl FontFile {
@@ -32,6 +37,8 @@ impl FontFile {
             native: UnsafeCell::new(font_file),
             data_key: key,
             face_type: DWRITE_FONT_FACE_TYPE_UNKNOWN,
+            face_count: 0,
+            file_type: DWRITE_FONT_FILE_TYPE_UNKNOWN,
         };
 
         if ff.analyze() == false {
@@ -42,17 +49,46 @@ impl FontFile {
         Some(ff)
     }
 
+    // A file on disk, which DirectWrite reads as needed rather than all
+    // at once.  Returns None if it can't be opened or isn't a supported
+    // font format.
+    pub fn new_from_path<P: AsRef<Path>>(path: P) -> Option<FontFile> {
+        unsafe {
+            let mut native: ComPtr<IDWriteFontFile> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateFontFileReference(path.as_ref().to_wide_null().as_ptr(),
+                                                                ptr::null(),
+                                                                native.getter_addrefs());
+            if hr != 0 {
+                return None;
+            }
+
+            let mut ff = FontFile {
+                native: UnsafeCell::new(native),
+                data_key: 0,
+                face_type: DWRITE_FONT_FACE_TYPE_UNKNOWN,
+                face_count: 0,
+                file_type: DWRITE_FONT_FILE_TYPE_UNKNOWN,
+            };
+            if !ff.analyze() {
+                return None;
+            }
+            Some(ff)
+        }
+    }
+
     fn analyze(&mut self) -> bool {
         let mut face_type = DWRITE_FONT_FACE_TYPE_UNKNOWN;
         unsafe {
             let mut supported = 0;
-            let mut _file_type = DWRITE_FONT_FILE_TYPE_UNKNOWN;
-            let mut _num_faces = 0;
+            let mut file_type = DWRITE_FONT_FILE_TYPE_UNKNOWN;
+            let mut face_count = 0;
 
-            let hr = (*self.as_ptr()).Analyze(&mut supported, &mut _file_type, &mut face_type, &mut _num_faces);
+            let hr = (*self.as_ptr()).Analyze(&mut supported, &mut file_type, &mut face_type, &mut face_count);
             if hr != 0 || supported == 0 {
                 return false;
             }
+            self.face_count = face_count;
+            self.file_type = file_type;
         }
         self.face_type = face_type;
         true
@@ -63,6 +99,8 @@ impl FontFile {
             native: UnsafeCell::new(native),
             data_key: 0,
             face_type: DWRITE_FONT_FACE_TYPE_UNKNOWN,
+            face_count: 0,
+            file_type: DWRITE_FONT_FILE_TYPE_UNKNOWN,
         };
         ff.analyze();
         ff
@@ -116,14 +154,46 @@ impl FontFile {
         }
     }
 
+    pub fn file_type(&self) -> FontFileType {
+        FontFileType::from_u32(self.file_type)
+    }
+
+    // The type of the faces in the file.
+    pub fn face_type(&self) -> FaceType {
+        FaceType::from_u32(self.face_type)
+    }
+
+    // The number of faces in the file: 1 unless it's a collection (.ttc or
+    // .otc).  0 if the file isn't a supported font.
+    pub fn face_count(&self) -> u32 {
+        self.face_count
+    }
+
     pub fn create_face(&self, face_index: u32, simulations: DWRITE_FONT_SIMULATIONS) -> FontFace {
+        self.try_create_face(face_index, simulations).unwrap()
+    }
+
+    pub(crate) fn try_create_face(&self, face_index: u32, simulations: DWRITE_FONT_SIMULATIONS)
+                                  -> Result<FontFace, HRESULT> {
         unsafe {
             let mut face: ComPtr<IDWriteFontFace> = ComPtr::new();
             let ptr = self.as_ptr();
             let hr = (*DWriteFactory()).CreateFontFace(self.face_type, 1, &ptr,
                                                        face_index, simulations, face.getter_addrefs());
-            assert!(hr == 0);
-            FontFace::take(face)
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(FontFace::take(face))
         }
     }
+
+    // Creates the faces of the file one at a time as the iterator is
+    // advanced, so a large collection can be processed without holding
+    // every face at once.  A face that fails to load yields its error and
+    // iteration carries on with the next one.
+    pub fn faces(&self) -> impl Iterator<Item = Result<FontFace, HRESULT>> + '_ {
+        (0..self.face_count).map(move |face_index| {
+            self.try_create_face(face_index, DWRITE_FONT_SIMULATIONS_NONE)
+        })
+    }
 }
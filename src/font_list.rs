@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+
+use comptr::ComPtr;
+use winapi::um::dwrite::{IDWriteFont, IDWriteFontList};
+
+use super::*;
+
+pub struct FontListIterator<'a> {
+    list: &'a FontList,
+    curr: u32,
+    count: u32,
+}
+
+impl<'a> Iterator for FontListIterator<'a> {
+    type Item = Font;
+    fn next(&mut self) -> Option<Font> {
+        if self.curr == self.count {
+            return None;
+        }
+
+        let font = self.list.get_font(self.curr);
+        self.curr += 1;
+        Some(font)
+    }
+}
+
+pub struct FontList {
+    native: UnsafeCell<ComPtr<IDWriteFontList>>,
+}
+
+impl FontList {
+    pub fn take(native: ComPtr<IDWriteFontList>) -> FontList {
+        FontList {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteFontList {
+        (*self.native.get()).as_ptr()
+    }
+
+    pub fn get_font_count(&self) -> u32 {
+        unsafe {
+            (*self.native.get()).GetFontCount()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.get_font_count() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.get_font_count() == 0
+    }
+
+    pub fn get_font(&self, index: u32) -> Font {
+        unsafe {
+            let mut font: ComPtr<IDWriteFont> = ComPtr::new();
+            let hr = (*self.native.get()).GetFont(index, font.getter_addrefs());
+            assert!(hr == 0);
+            Font::take(font)
+        }
+    }
+
+    pub fn iter(&self) -> FontListIterator<'_> {
+        FontListIterator {
+            list: self,
+            curr: 0,
+            count: self.get_font_count(),
+        }
+    }
+
+    // The first font in the list with exactly these attributes.
+    pub fn find(&self, weight: FontWeight, stretch: FontStretch, style: FontStyle) -> Option<Font> {
+        self.iter().find(|font| {
+            font.weight() == weight && font.stretch() == stretch && font.style() == style
+        })
+    }
+}
@@ -0,0 +1,341 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+
+use comptr::ComPtr;
+use winapi::shared::minwindef::{BOOL, FALSE};
+use winapi::shared::winerror::{E_NOINTERFACE, HRESULT};
+use winapi::um::dwrite::{IDWriteFontCollection, IDWriteFontFace, IDWriteLocalizedStrings};
+use winapi::um::dwrite::DWRITE_FONT_SIMULATIONS_NONE;
+use winapi::um::dwrite_3::{IDWriteFactory3, IDWriteFont3, IDWriteFontCollection1, IDWriteFontFace3, IDWriteFontFaceReference};
+use winapi::um::dwrite_3::{IDWriteFontSet, IDWriteFontSetBuilder, IDWriteStringList, DWRITE_FONT_PROPERTY};
+use winapi::um::dwrite_3::{DWRITE_FONT_PROPERTY_ID, DWRITE_FONT_PROPERTY_ID_FAMILY_NAME, DWRITE_FONT_PROPERTY_ID_TOTAL};
+use winapi::Interface;
+use super::{DWriteFactory, Font, FontCollection, FontFace, FontFile, FontStretch, FontStyle, FontWeight};
+use helpers::*;
+
+// A flat list of font faces that can be queried by their properties
+// (DWRITE_FONT_PROPERTY_ID_*), unlike a FontCollection, which only groups
+// fonts into families.  Needs IDWriteFactory3 (Windows 10); constructors
+// return E_NOINTERFACE if it isn't available.
+pub struct FontSet {
+    native: UnsafeCell<ComPtr<IDWriteFontSet>>,
+}
+
+impl FontSet {
+    // A set of every face of every file, without installing them.
+    pub fn from_files(files: &[FontFile]) -> Result<FontSet, HRESULT> {
+        let builder = FontSetBuilder::new()?;
+        for file in files {
+            for face_index in 0..file.face_count() {
+                builder.add(file, face_index)?;
+            }
+        }
+        builder.build()
+    }
+
+    // The installed fonts.
+    pub fn system() -> Result<FontSet, HRESULT> {
+        unsafe {
+            let mut native: ComPtr<IDWriteFontSet> = ComPtr::new();
+            let hr = factory3()?.GetSystemFontSet(native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(FontSet::take(native))
+        }
+    }
+
+    // A set of just `font`, for asking about it through the property
+    // model.  Fails with E_NOINTERFACE if the font has no IDWriteFont3.
+    pub fn from_font(font: &Font) -> Result<FontSet, HRESULT> {
+        unsafe {
+            let font = ComPtr::from_ptr(font.as_ptr())
+                .query_interface::<IDWriteFont3>(&IDWriteFont3::uuidof())
+                .ok_or(E_NOINTERFACE)?;
+            let mut reference: ComPtr<IDWriteFontFaceReference> = ComPtr::new();
+            let hr = font.GetFontFaceReference(reference.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            let builder = FontSetBuilder::new()?;
+            let hr = (*builder.native.get()).AddFontFaceReference_1(reference.as_ptr());
+            if hr != 0 {
+                return Err(hr);
+            }
+            builder.build()
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteFontSet>) -> FontSet {
+        FontSet {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteFontSet {
+        (*self.native.get()).as_ptr()
+    }
+
+    pub fn get_font_count(&self) -> u32 {
+        unsafe {
+            (*self.native.get()).GetFontCount()
+        }
+    }
+
+    pub fn create_font_face(&self, index: u32) -> Result<FontFace, HRESULT> {
+        unsafe {
+            let mut face_reference: ComPtr<IDWriteFontFaceReference> = ComPtr::new();
+            let hr = (*self.native.get()).GetFontFaceReference(index, face_reference.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            let mut face: ComPtr<IDWriteFontFace3> = ComPtr::new();
+            let hr = face_reference.CreateFontFace(face.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            let face = ComPtr::already_addrefed(face.forget() as *mut IDWriteFontFace);
+            Ok(FontFace::take(face))
+        }
+    }
+
+    // Every distinct value of a property across the set, e.g. all family
+    // names, in the given locale or the system locale if None.
+    pub fn get_property_values(&self, property_id: DWRITE_FONT_PROPERTY_ID, locale: Option<&str>) -> Vec<String> {
+        let locale = locale.map(|locale| locale.to_owned()).unwrap_or_else(system_locale_name);
+        unsafe {
+            let mut values: ComPtr<IDWriteStringList> = ComPtr::new();
+            let hr = (*self.native.get()).GetPropertyValues_2(property_id,
+                                                              locale.to_wide_null().as_ptr(),
+                                                              values.getter_addrefs());
+            if hr != 0 {
+                return vec![];
+            }
+            (0..values.GetCount()).filter_map(|index| {
+                let mut length = 0;
+                let hr = values.GetStringLength(index, &mut length);
+                if hr != 0 {
+                    return None;
+                }
+                let mut buffer: Vec<u16> = vec![0; length as usize + 1];
+                let hr = values.GetString(index, buffer.as_mut_ptr(), buffer.len() as u32);
+                if hr != 0 {
+                    return None;
+                }
+                Some(String::from_utf16_lossy(&buffer[..length as usize]))
+            }).collect()
+        }
+    }
+
+    // A property of the font at `index`, in the given locale or the system
+    // locale if None, falling back to en-us and then any locale.  None if
+    // the font doesn't have the property.
+    pub fn get_font_property(&self, index: u32, property_id: DWRITE_FONT_PROPERTY_ID, locale: Option<&str>)
+                             -> Option<String> {
+        unsafe {
+            let mut exists: BOOL = FALSE;
+            let mut strings: ComPtr<IDWriteLocalizedStrings> = ComPtr::new();
+            let hr = (*self.native.get()).GetPropertyValues_1(index, property_id, &mut exists,
+                                                              strings.getter_addrefs());
+            if hr != 0 || exists == FALSE || strings.as_ptr().is_null() {
+                return None;
+            }
+            Some(get_locale_string_for(&mut strings, locale))
+        }
+    }
+
+    // Every (locale, value) pair of a property of the font at `index`.
+    fn get_font_property_all_locales(&self, index: u32, property_id: DWRITE_FONT_PROPERTY_ID)
+                                     -> Vec<(String, String)> {
+        unsafe {
+            let mut exists: BOOL = FALSE;
+            let mut strings: ComPtr<IDWriteLocalizedStrings> = ComPtr::new();
+            let hr = (*self.native.get()).GetPropertyValues_1(index, property_id, &mut exists,
+                                                              strings.getter_addrefs());
+            if hr != 0 || exists == FALSE || strings.as_ptr().is_null() {
+                return vec![];
+            }
+            (0..strings.GetCount()).filter_map(|string_index| {
+                let mut length = 0;
+                if strings.GetLocaleNameLength(string_index, &mut length) != 0 {
+                    return None;
+                }
+                let mut locale: Vec<u16> = vec![0; length as usize + 1];
+                if strings.GetLocaleName(string_index, locale.as_mut_ptr(), locale.len() as u32) != 0 {
+                    return None;
+                }
+                if strings.GetStringLength(string_index, &mut length) != 0 {
+                    return None;
+                }
+                let mut value: Vec<u16> = vec![0; length as usize + 1];
+                if strings.GetString(string_index, value.as_mut_ptr(), value.len() as u32) != 0 {
+                    return None;
+                }
+                Some((String::from_utf16_lossy(&locale[..locale.len() - 1]),
+                      String::from_utf16_lossy(&value[..length as usize])))
+            }).collect()
+        }
+    }
+
+    // The subset of fonts in the family that best match the given
+    // properties, best match first.
+    pub fn get_matching_fonts(&self, family_name: &str, weight: FontWeight, stretch: FontStretch, style: FontStyle)
+                              -> Result<FontSet, HRESULT> {
+        unsafe {
+            let mut native: ComPtr<IDWriteFontSet> = ComPtr::new();
+            let hr = (*self.native.get()).GetMatchingFonts_2(family_name.to_wide_null().as_ptr(),
+                                                             weight.t(),
+                                                             stretch.t(),
+                                                             style.t(),
+                                                             native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(FontSet::take(native))
+        }
+    }
+
+    // A collection grouping the set's fonts into families, for APIs that
+    // take a FontCollection.
+    pub fn create_font_collection(&self) -> Result<FontCollection, HRESULT> {
+        unsafe {
+            let mut collection: ComPtr<IDWriteFontCollection1> = ComPtr::new();
+            let hr = factory3()?.CreateFontCollectionFromFontSet(self.as_ptr(), collection.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            let native = ComPtr::already_addrefed(collection.forget() as *mut IDWriteFontCollection);
+            Ok(FontCollection::take(native))
+        }
+    }
+}
+
+// The property values given for a face, as (property, value, locale).
+// The locale is the empty string for locale-independent properties such
+// as weight.
+pub type FontPropertyOverride<'a> = (DWRITE_FONT_PROPERTY_ID, &'a str, &'a str);
+
+pub struct FontSetBuilder {
+    native: UnsafeCell<ComPtr<IDWriteFontSetBuilder>>,
+}
+
+impl FontSetBuilder {
+    pub fn new() -> Result<FontSetBuilder, HRESULT> {
+        unsafe {
+            let mut native: ComPtr<IDWriteFontSetBuilder> = ComPtr::new();
+            let hr = factory3()?.CreateFontSetBuilder(native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(FontSetBuilder::take(native))
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteFontSetBuilder>) -> FontSetBuilder {
+        FontSetBuilder {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteFontSetBuilder {
+        (*self.native.get()).as_ptr()
+    }
+
+    // Adds face `face_index` of `file` with the properties read from the
+    // font.
+    pub fn add(&self, file: &FontFile, face_index: u32) -> Result<(), HRESULT> {
+        unsafe {
+            let reference = face_reference(file, face_index)?;
+            let hr = (*self.native.get()).AddFontFaceReference_1(reference.as_ptr());
+            if hr != 0 {
+                return Err(hr);
+            }
+        }
+        Ok(())
+    }
+
+    // Like add, but each property in `overrides` replaces all of the
+    // font's own values for that property (in every locale), so a library
+    // with inconsistent metadata can be made to appear under one family
+    // name, say.  DirectWrite only knows the properties it is given when
+    // they're given explicitly, so the font's other properties are read
+    // and passed along unchanged.
+    pub fn add_with_properties(&self, file: &FontFile, face_index: u32, overrides: &[FontPropertyOverride])
+                               -> Result<(), HRESULT> {
+        unsafe {
+            let reference = face_reference(file, face_index)?;
+
+            let mut properties: Vec<(DWRITE_FONT_PROPERTY_ID, Vec<u16>, Vec<u16>)> = vec![];
+            let single = FontSetBuilder::new()?;
+            let hr = (*single.native.get()).AddFontFaceReference_1(reference.as_ptr());
+            if hr != 0 {
+                return Err(hr);
+            }
+            let single = single.build()?;
+            for property_id in DWRITE_FONT_PROPERTY_ID_FAMILY_NAME..DWRITE_FONT_PROPERTY_ID_TOTAL {
+                if overrides.iter().any(|&(id, _, _)| id == property_id) {
+                    continue;
+                }
+                for (locale, value) in single.get_font_property_all_locales(0, property_id) {
+                    properties.push((property_id, value.to_wide_null(), locale.to_wide_null()));
+                }
+            }
+            for &(property_id, value, locale) in overrides {
+                properties.push((property_id, value.to_wide_null(), locale.to_wide_null()));
+            }
+
+            let raw: Vec<DWRITE_FONT_PROPERTY> = properties.iter().map(|(property_id, value, locale)| {
+                DWRITE_FONT_PROPERTY {
+                    propertyId: *property_id,
+                    propertyValue: value.as_ptr(),
+                    localeName: locale.as_ptr(),
+                }
+            }).collect();
+            let hr = (*self.native.get()).AddFontFaceReference_2(reference.as_ptr(), raw.as_ptr(), raw.len() as u32);
+            if hr != 0 {
+                return Err(hr);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_font_set(&self, font_set: &FontSet) -> Result<(), HRESULT> {
+        unsafe {
+            let hr = (*self.native.get()).AddFontSet(font_set.as_ptr());
+            if hr != 0 {
+                return Err(hr);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<FontSet, HRESULT> {
+        unsafe {
+            let mut native: ComPtr<IDWriteFontSet> = ComPtr::new();
+            let hr = (*self.native.get()).CreateFontSet(native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(FontSet::take(native))
+        }
+    }
+}
+
+unsafe fn face_reference(file: &FontFile, face_index: u32) -> Result<ComPtr<IDWriteFontFaceReference>, HRESULT> {
+    let mut reference: ComPtr<IDWriteFontFaceReference> = ComPtr::new();
+    let hr = factory3()?.CreateFontFaceReference_2(file.as_ptr(), face_index, DWRITE_FONT_SIMULATIONS_NONE,
+                                                   reference.getter_addrefs());
+    if hr != 0 {
+        return Err(hr);
+    }
+    Ok(reference)
+}
+
+pub(crate) unsafe fn factory3() -> Result<ComPtr<IDWriteFactory3>, HRESULT> {
+    ComPtr::from_ptr(DWriteFactory())
+        .query_interface::<IDWriteFactory3>(&IDWriteFactory3::uuidof())
+        .ok_or(E_NOINTERFACE)
+}
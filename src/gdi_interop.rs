@@ -6,6 +6,7 @@ use std::ptr;
 use std::cell::UnsafeCell;
 
 use comptr::ComPtr;
+use winapi::shared::windef::HDC;
 use winapi::um::dwrite::IDWriteBitmapRenderTarget;
 use winapi::um::dwrite::IDWriteGdiInterop;
 use super::{DWriteFactory, BitmapRenderTarget};
@@ -40,4 +41,15 @@ impl GdiInterop {
             BitmapRenderTarget::take(native)
         }
     }
+
+    // Like create_bitmap_render_target, but with a bitmap compatible with
+    // `hdc`, for drawing text to copy onto it (see
+    // BitmapRenderTarget::copy_from_hdc).
+    pub unsafe fn create_bitmap_render_target_for_hdc(&self, hdc: HDC, width: u32, height: u32)
+                                                      -> BitmapRenderTarget {
+        let mut native: ComPtr<IDWriteBitmapRenderTarget> = ComPtr::new();
+        let hr = (*self.native.get()).CreateBitmapRenderTarget(hdc, width, height, native.getter_addrefs());
+        assert!(hr == 0);
+        BitmapRenderTarget::take(native)
+    }
 }
@@ -0,0 +1,276 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// An IDWriteGeometrySink (ID2D1SimplifiedGeometrySink) that records the
+// outline DirectWrite sends it, for GetGlyphRunOutline callers that want
+// the path itself rather than a Direct2D geometry.
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+use std::mem;
+use std::slice;
+use std::sync::atomic;
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::REFIID;
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::winerror::S_OK;
+use winapi::um::d2d1::{ID2D1SimplifiedGeometrySink, ID2D1SimplifiedGeometrySinkVtbl, D2D1_BEZIER_SEGMENT};
+use winapi::um::d2d1::{D2D1_FIGURE_BEGIN, D2D1_FIGURE_END, D2D1_FIGURE_END_CLOSED, D2D1_FILL_MODE};
+use winapi::um::d2d1::{D2D1_PATH_SEGMENT, D2D1_POINT_2F};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::HRESULT;
+
+use comptr::ComPtr;
+use com_helpers::*;
+
+DEFINE_GUID!{UuidOfID2D1SimplifiedGeometrySink, 0x2cd9069e, 0x12e2, 0x11dc, 0x9f, 0xed, 0x00, 0x11, 0x43, 0xa0, 0x55, 0xf9}
+
+// One step of a recorded outline, in the coordinates DirectWrite used: the
+// run's baseline origin at (0, 0) and y growing downwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    // Two control points, then the end point.
+    CubicTo((f32, f32), (f32, f32), (f32, f32)),
+    // Ends the current figure; true if it closes back to its start.
+    EndFigure(bool),
+}
+
+pub struct GeometrySinkImpl {
+    refcount: atomic::AtomicUsize,
+    pub commands: Vec<PathCommand>,
+}
+
+fn point(p: &D2D1_POINT_2F) -> (f32, f32) {
+    (p.x, p.y)
+}
+
+const GeometrySinkImplVtbl: &ID2D1SimplifiedGeometrySinkVtbl = &ID2D1SimplifiedGeometrySinkVtbl {
+    parent: implement_iunknown!(ID2D1SimplifiedGeometrySink, UuidOfID2D1SimplifiedGeometrySink, GeometrySinkImpl),
+    SetFillMode: {
+        unsafe extern "system" fn SetFillMode(_This: *mut ID2D1SimplifiedGeometrySink,
+                                              _fillMode: D2D1_FILL_MODE) {}
+        SetFillMode
+    },
+    SetSegmentFlags: {
+        unsafe extern "system" fn SetSegmentFlags(_This: *mut ID2D1SimplifiedGeometrySink,
+                                                  _vertexFlags: D2D1_PATH_SEGMENT) {}
+        SetSegmentFlags
+    },
+    BeginFigure: {
+        unsafe extern "system" fn BeginFigure(
+            This: *mut ID2D1SimplifiedGeometrySink,
+            startPoint: D2D1_POINT_2F,
+            _figureBegin: D2D1_FIGURE_BEGIN)
+        {
+            let this = GeometrySinkImpl::from_interface(This);
+            this.commands.push(PathCommand::MoveTo(startPoint.x, startPoint.y));
+        }
+        BeginFigure
+    },
+    AddLines: {
+        unsafe extern "system" fn AddLines(
+            This: *mut ID2D1SimplifiedGeometrySink,
+            points: *const D2D1_POINT_2F,
+            pointsCount: u32)
+        {
+            if points.is_null() {
+                return
+            }
+            let this = GeometrySinkImpl::from_interface(This);
+            for p in slice::from_raw_parts(points, pointsCount as usize) {
+                this.commands.push(PathCommand::LineTo(p.x, p.y));
+            }
+        }
+        AddLines
+    },
+    AddBeziers: {
+        unsafe extern "system" fn AddBeziers(
+            This: *mut ID2D1SimplifiedGeometrySink,
+            beziers: *const D2D1_BEZIER_SEGMENT,
+            beziersCount: u32)
+        {
+            if beziers.is_null() {
+                return
+            }
+            let this = GeometrySinkImpl::from_interface(This);
+            for bezier in slice::from_raw_parts(beziers, beziersCount as usize) {
+                this.commands.push(PathCommand::CubicTo(point(&bezier.point1),
+                                                        point(&bezier.point2),
+                                                        point(&bezier.point3)));
+            }
+        }
+        AddBeziers
+    },
+    EndFigure: {
+        unsafe extern "system" fn EndFigure(
+            This: *mut ID2D1SimplifiedGeometrySink,
+            figureEnd: D2D1_FIGURE_END)
+        {
+            let this = GeometrySinkImpl::from_interface(This);
+            this.commands.push(PathCommand::EndFigure(figureEnd == D2D1_FIGURE_END_CLOSED));
+        }
+        EndFigure
+    },
+    Close: {
+        unsafe extern "system" fn Close(_This: *mut ID2D1SimplifiedGeometrySink) -> HRESULT {
+            S_OK
+        }
+        Close
+    },
+};
+
+impl GeometrySinkImpl {
+    pub fn create() -> ComPtr<ID2D1SimplifiedGeometrySink> {
+        let native = GeometrySinkImpl {
+            refcount: atomic::AtomicUsize::new(0),
+            commands: vec![],
+        };
+        ComPtr::from_ptr(native.into_interface())
+    }
+
+    // The commands recorded by a sink made with create.
+    pub unsafe fn commands(sink: &ComPtr<ID2D1SimplifiedGeometrySink>) -> &[PathCommand] {
+        &GeometrySinkImpl::from_interface(sink.as_ptr()).commands
+    }
+}
+
+// The tight bounding box of a path, as (left, top, right, bottom): curves
+// count only as far as they actually reach, not out to their control
+// points.  None if the path has no points.
+pub fn path_bounds(commands: &[PathCommand]) -> Option<(f32, f32, f32, f32)> {
+    let mut bounds: Option<(f32, f32, f32, f32)> = None;
+    let mut add = |(x, y): (f32, f32)| {
+        bounds = Some(match bounds {
+            Some((left, top, right, bottom)) => (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+            None => (x, y, x, y),
+        });
+    };
+
+    let mut current = (0., 0.);
+    for command in commands {
+        match *command {
+            PathCommand::MoveTo(x, y) | PathCommand::LineTo(x, y) => {
+                current = (x, y);
+                add(current);
+            }
+            PathCommand::CubicTo(control1, control2, end) => {
+                add(end);
+                for t in cubic_extrema(current.0, control1.0, control2.0, end.0).iter()
+                    .chain(cubic_extrema(current.1, control1.1, control2.1, end.1).iter())
+                    .filter_map(|&t| t)
+                {
+                    add(cubic_point(current, control1, control2, end, t));
+                }
+                current = end;
+            }
+            PathCommand::EndFigure(_) => {}
+        }
+    }
+    bounds
+}
+
+fn cubic_point(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32), t: f32) -> (f32, f32) {
+    let u = 1. - t;
+    let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+    (a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+     a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1)
+}
+
+// The parameters strictly between 0 and 1 at which one coordinate of a
+// cubic Bezier curve turns around, where its derivative is zero.
+fn cubic_extrema(p0: f32, p1: f32, p2: f32, p3: f32) -> [Option<f32>; 2] {
+    // The derivative is 3 times a*t^2 + b*t + c.
+    let a = -p0 + 3. * p1 - 3. * p2 + p3;
+    let b = 2. * (p0 - 2. * p1 + p2);
+    let c = p1 - p0;
+    let inside = |t: f32| if t > 0. && t < 1. { Some(t) } else { None };
+    if a.abs() < 1e-6 {
+        if b.abs() < 1e-6 {
+            return [None, None];
+        }
+        return [inside(-c / b), None];
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return [None, None];
+    }
+    let root = discriminant.sqrt();
+    [inside((-b + root) / (2. * a)), inside((-b - root) / (2. * a))]
+}
+
+// The path as polygons, one per figure, with each curve replaced by line
+// segments that stray no more than `tolerance` from it.  Closed figures
+// don't repeat their first point at the end.
+pub fn flatten_path(commands: &[PathCommand], tolerance: f32) -> Vec<Vec<(f32, f32)>> {
+    let tolerance = tolerance.max(1e-4);
+    let mut contours = vec![];
+    let mut contour: Vec<(f32, f32)> = vec![];
+    for command in commands {
+        match *command {
+            PathCommand::MoveTo(x, y) => {
+                if !contour.is_empty() {
+                    contours.push(contour);
+                }
+                contour = vec![(x, y)];
+            }
+            PathCommand::LineTo(x, y) => contour.push((x, y)),
+            PathCommand::CubicTo(control1, control2, end) => {
+                let start = contour.last().cloned().unwrap_or((0., 0.));
+                flatten_cubic(start, control1, control2, end, tolerance, 0, &mut contour);
+            }
+            PathCommand::EndFigure(closed) => {
+                if closed && contour.len() > 1 && contour.first() == contour.last() {
+                    contour.pop();
+                }
+                if !contour.is_empty() {
+                    contours.push(contour);
+                }
+                contour = vec![];
+            }
+        }
+    }
+    if !contour.is_empty() {
+        contours.push(contour);
+    }
+    contours
+}
+
+// Appends points along the curve, not including its start, splitting it
+// in half until each piece is flat enough to draw as a line: both control
+// points within `tolerance` of the line between the piece's ends.  The
+// depth limit stops runaway splitting on degenerate curves.
+fn flatten_cubic(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32), tolerance: f32, depth: u32,
+                 points: &mut Vec<(f32, f32)>) {
+    if depth >= 16 || (distance_to_line(p1, p0, p3) <= tolerance && distance_to_line(p2, p0, p3) <= tolerance) {
+        points.push(p3);
+        return;
+    }
+    let middle = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2., (a.1 + b.1) / 2.);
+    let (p01, p12, p23) = (middle(p0, p1), middle(p1, p2), middle(p2, p3));
+    let (p012, p123) = (middle(p01, p12), middle(p12, p23));
+    let split = middle(p012, p123);
+    flatten_cubic(p0, p01, p012, split, tolerance, depth + 1, points);
+    flatten_cubic(split, p123, p23, p3, tolerance, depth + 1, points);
+}
+
+fn distance_to_line(p: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0. {
+        return ((p.0 - start.0).powi(2) + (p.1 - start.1).powi(2)).sqrt();
+    }
+    ((p.0 - start.0) * dy - (p.1 - start.1) * dx).abs() / length
+}
+
+impl Com<ID2D1SimplifiedGeometrySink> for GeometrySinkImpl {
+    type Vtbl = ID2D1SimplifiedGeometrySinkVtbl;
+    fn vtbl() -> &'static ID2D1SimplifiedGeometrySinkVtbl { GeometrySinkImplVtbl }
+}
+
+impl Com<IUnknown> for GeometrySinkImpl {
+    type Vtbl = IUnknownVtbl;
+    fn vtbl() -> &'static IUnknownVtbl { &GeometrySinkImplVtbl.parent }
+}
@@ -0,0 +1,214 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cmp;
+
+use winapi::um::dcommon::DWRITE_MEASURING_MODE_NATURAL;
+use winapi::um::dwrite::{DWRITE_RENDERING_MODE_DEFAULT, DWRITE_TEXTURE_ALIASED_1x1};
+use winapi::um::dwrite_2::DWRITE_GRID_FIT_MODE_DEFAULT;
+use super::{FontFace, RasterizedGlyph, RecommendedRendering, RenderingParams};
+
+// Finds space for glyph bitmaps in an atlas of a fixed size.
+pub trait AtlasPacker {
+    // Returns the top-left corner of a free width x height area, or None
+    // if there is no room left.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)>;
+}
+
+// Fills the atlas a row ("shelf") at a time, starting a new shelf below
+// the tallest glyph of the current one when a glyph doesn't fit across.
+// Each allocation is padded by a pixel on the right and bottom so that
+// filtering doesn't bleed neighbouring glyphs into each other.
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    shelf_top: u32,
+    shelf_height: u32,
+    cursor_x: u32,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32) -> ShelfPacker {
+        ShelfPacker {
+            width,
+            height,
+            shelf_top: 0,
+            shelf_height: 0,
+            cursor_x: 0,
+        }
+    }
+}
+
+impl AtlasPacker for ShelfPacker {
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let padded_width = width + 1;
+        let padded_height = height + 1;
+        if padded_width > self.width {
+            return None;
+        }
+
+        if self.cursor_x + padded_width > self.width {
+            self.shelf_top += self.shelf_height;
+            self.shelf_height = 0;
+            self.cursor_x = 0;
+        }
+        if self.shelf_top + padded_height > self.height {
+            return None;
+        }
+
+        let position = (self.cursor_x, self.shelf_top);
+        self.cursor_x += padded_width;
+        self.shelf_height = cmp::max(self.shelf_height, padded_height);
+        Some(position)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtlasFormat {
+    // One coverage byte per pixel.  ClearType coverage is averaged down.
+    Alpha,
+    // Four bytes per pixel: ClearType R, G, B coverage and the averaged
+    // coverage in A.  Aliased glyphs have the same value in all four.
+    Rgba,
+}
+
+impl AtlasFormat {
+    fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            AtlasFormat::Alpha => 1,
+            AtlasFormat::Rgba => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasGlyph {
+    // Where the glyph's pixels are in the atlas.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Offset from the glyph origin on the baseline to the top-left corner
+    // of the bitmap, in pixels.
+    pub left: i32,
+    pub top: i32,
+    // (u0, v0, u1, v1) texture coordinates of the glyph, normalized to
+    // the atlas size.
+    pub uv: (f32, f32, f32, f32),
+}
+
+pub struct GlyphAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub format: AtlasFormat,
+    pub data: Vec<u8>,
+    // One entry per request, in request order; None if the glyph didn't
+    // fit or couldn't be rasterized.  Glyphs without ink get a zero-sized
+    // entry.
+    pub glyphs: Vec<Option<AtlasGlyph>>,
+}
+
+impl GlyphAtlas {
+    // Rasterizes each (face, glyph index, em size) request with the face's
+    // recommended rendering mode for rendering_params and packs the
+    // results with a ShelfPacker.
+    pub fn build(requests: &[(&FontFace, u16, f32)],
+                 width: u32,
+                 height: u32,
+                 format: AtlasFormat,
+                 rendering_params: &RenderingParams)
+                 -> GlyphAtlas {
+        GlyphAtlas::build_with_packer(requests, width, height, format, rendering_params,
+                                      &mut ShelfPacker::new(width, height))
+    }
+
+    pub fn build_with_packer<P>(requests: &[(&FontFace, u16, f32)],
+                                width: u32,
+                                height: u32,
+                                format: AtlasFormat,
+                                rendering_params: &RenderingParams,
+                                packer: &mut P)
+                                -> GlyphAtlas
+                                where P: AtlasPacker {
+        let rendering = RecommendedRendering {
+            mode: DWRITE_RENDERING_MODE_DEFAULT,
+            grid_fit: DWRITE_GRID_FIT_MODE_DEFAULT,
+        };
+        let rasterized: Vec<Option<RasterizedGlyph>> = requests.iter().map(|&(face, glyph, em_size)| {
+            face.rasterize_glyph_with_grid_fit(glyph, em_size, 1.0, rendering,
+                                               DWRITE_MEASURING_MODE_NATURAL, rendering_params).ok()
+        }).collect();
+
+        let mut atlas = GlyphAtlas {
+            width,
+            height,
+            format,
+            data: vec![0; width as usize * height as usize * format.bytes_per_pixel()],
+            glyphs: vec![None; requests.len()],
+        };
+
+        // Shelf packing wastes the least space when the tallest glyphs go
+        // first.
+        let mut order: Vec<usize> = (0..rasterized.len()).filter(|&i| rasterized[i].is_some()).collect();
+        order.sort_by_key(|&i| {
+            let bounds = &rasterized[i].as_ref().unwrap().bounds;
+            cmp::Reverse(bounds.bottom - bounds.top)
+        });
+
+        for i in order {
+            let glyph = rasterized[i].as_ref().unwrap();
+            let glyph_width = (glyph.bounds.right - glyph.bounds.left).max(0) as u32;
+            let glyph_height = (glyph.bounds.bottom - glyph.bounds.top).max(0) as u32;
+
+            let (x, y) = if glyph_width == 0 || glyph_height == 0 {
+                (0, 0)
+            } else {
+                match packer.allocate(glyph_width, glyph_height) {
+                    Some(position) => position,
+                    None => continue,
+                }
+            };
+
+            atlas.blit(glyph, x, y, glyph_width, glyph_height);
+            atlas.glyphs[i] = Some(AtlasGlyph {
+                x,
+                y,
+                width: glyph_width,
+                height: glyph_height,
+                left: glyph.bounds.left,
+                top: glyph.bounds.top,
+                uv: (x as f32 / width as f32,
+                     y as f32 / height as f32,
+                     (x + glyph_width) as f32 / width as f32,
+                     (y + glyph_height) as f32 / height as f32),
+            });
+        }
+
+        atlas
+    }
+
+    fn blit(&mut self, glyph: &RasterizedGlyph, x: u32, y: u32, width: u32, height: u32) {
+        let source_bpp = if glyph.texture_type == DWRITE_TEXTURE_ALIASED_1x1 { 1 } else { 3 };
+        let dest_bpp = self.format.bytes_per_pixel();
+
+        for row in 0..height as usize {
+            for col in 0..width as usize {
+                let source = &glyph.data[(row * width as usize + col) * source_bpp..][..source_bpp];
+                let (r, g, b) = if source_bpp == 1 {
+                    (source[0], source[0], source[0])
+                } else {
+                    (source[0], source[1], source[2])
+                };
+                let alpha = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+
+                let dest_offset = ((y as usize + row) * self.width as usize + x as usize + col) * dest_bpp;
+                let dest = &mut self.data[dest_offset..][..dest_bpp];
+                match self.format {
+                    AtlasFormat::Alpha => dest[0] = alpha,
+                    AtlasFormat::Rgba => dest.copy_from_slice(&[r, g, b, alpha]),
+                }
+            }
+        }
+    }
+}
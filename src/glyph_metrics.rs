@@ -0,0 +1,29 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use winapi::um::dwrite::DWRITE_GLYPH_METRICS;
+
+// Conversions for the design-unit glyph metrics returned by
+// FontFace::get_design_glyph_metrics.
+pub trait GlyphMetricsExt {
+    // The glyph's ink rectangle as (left, top, right, bottom) in pixels,
+    // relative to the glyph origin on the baseline with y pointing down,
+    // the same convention as RasterizedGlyph::bounds; top is negative for
+    // ink above the baseline.
+    fn ink_bounds_px(&self, units_per_em: u16, em_size: f32) -> (f32, f32, f32, f32);
+}
+
+impl GlyphMetricsExt for DWRITE_GLYPH_METRICS {
+    fn ink_bounds_px(&self, units_per_em: u16, em_size: f32) -> (f32, f32, f32, f32) {
+        let scale = em_size / units_per_em as f32;
+        // The side bearings are measured inward from the advance box, and
+        // verticalOriginY is the top side bearing plus the height of the
+        // ink above the baseline.
+        let left = self.leftSideBearing;
+        let right = self.advanceWidth as i32 - self.rightSideBearing;
+        let top = self.topSideBearing - self.verticalOriginY;
+        let bottom = self.advanceHeight as i32 - self.bottomSideBearing - self.verticalOriginY;
+        (left as f32 * scale, top as f32 * scale, right as f32 * scale, bottom as f32 * scale)
+    }
+}
@@ -0,0 +1,73 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use winapi::um::dwrite::DWRITE_GLYPH_METRICS;
+use super::FontFace;
+
+// Memoizes get_design_glyph_metrics for one face, for layout code that
+// asks about the same glyphs over and over.  A face's simulations and
+// variation axis values are fixed when it is created, so borrowing the
+// face is enough to keep the cached metrics valid; a face with different
+// simulations or variations is a different FontFace and needs its own
+// cache.
+pub struct GlyphMetricsCache<'a> {
+    face: &'a FontFace,
+    is_sideways: bool,
+    metrics: RefCell<HashMap<u16, DWRITE_GLYPH_METRICS>>,
+}
+
+impl<'a> GlyphMetricsCache<'a> {
+    pub fn new(face: &'a FontFace, is_sideways: bool) -> GlyphMetricsCache<'a> {
+        GlyphMetricsCache {
+            face,
+            is_sideways,
+            metrics: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn face(&self) -> &'a FontFace {
+        self.face
+    }
+
+    pub fn get(&self, glyph: u16) -> DWRITE_GLYPH_METRICS {
+        if let Some(metrics) = self.metrics.borrow().get(&glyph) {
+            return *metrics;
+        }
+        let metrics = self.face.get_design_glyph_metrics(&[glyph], self.is_sideways)[0];
+        self.metrics.borrow_mut().insert(glyph, metrics);
+        metrics
+    }
+
+    // Looks up a run of glyphs, fetching all the ones that aren't cached
+    // yet from DirectWrite in a single call.
+    pub fn get_many(&self, glyph_indices: &[u16]) -> Vec<DWRITE_GLYPH_METRICS> {
+        let mut cache = self.metrics.borrow_mut();
+        let mut missing: Vec<u16> = glyph_indices.iter()
+            .cloned()
+            .filter(|glyph| !cache.contains_key(glyph))
+            .collect();
+        if !missing.is_empty() {
+            missing.sort();
+            missing.dedup();
+            let fetched = self.face.get_design_glyph_metrics(&missing, self.is_sideways);
+            cache.extend(missing.into_iter().zip(fetched));
+        }
+        glyph_indices.iter().map(|glyph| cache[glyph]).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.metrics.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metrics.borrow().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.metrics.borrow_mut().clear();
+    }
+}
@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::mem::zeroed;
+
+use winapi::um::dwrite::DWRITE_GLYPH_RUN;
+use super::{FontFace, GlyphOffset};
+
+// A run of glyphs in one face, laid out from a baseline origin in one
+// reading direction, and ready to hand to GlyphRunAnalysis or
+// BitmapRenderTarget.  Glyphs are in logical order.  For right-to-left
+// runs the baseline origin is the run's right end and each glyph sits to
+// the left of the one before it, as DirectWrite expects for odd bidi
+// levels.
+pub struct GlyphRun<'a> {
+    pub font_face: &'a FontFace,
+    pub em_size: f32,
+    pub glyph_indices: Vec<u16>,
+    pub glyph_advances: Vec<f32>,
+    // Positive advance offsets move glyphs in the reading direction, so
+    // leftwards in right-to-left runs.
+    pub glyph_offsets: Vec<GlyphOffset>,
+    pub baseline_origin: (f32, f32),
+    pub is_right_to_left: bool,
+}
+
+impl<'a> GlyphRun<'a> {
+    // Each glyph's origin, the point on the baseline at its left edge
+    // where a glyph bitmap or outline for it would be placed, in the same
+    // order as glyph_indices.  Y grows downwards.
+    pub fn glyph_positions(&self) -> Vec<(f32, f32)> {
+        let (mut pen, y) = self.baseline_origin;
+        self.glyph_advances.iter().zip(&self.glyph_offsets).map(|(&advance, offset)| {
+            if self.is_right_to_left {
+                pen -= advance;
+                (pen - offset.advanceOffset, y - offset.ascenderOffset)
+            } else {
+                let position = (pen + offset.advanceOffset, y - offset.ascenderOffset);
+                pen += advance;
+                position
+            }
+        }).collect()
+    }
+
+    // The run's total advance.
+    pub fn width(&self) -> f32 {
+        self.glyph_advances.iter().sum()
+    }
+
+    // The run as DirectWrite takes it.  It points into this run, so it
+    // must not outlive it.
+    pub fn as_dwrite_glyph_run(&self) -> DWRITE_GLYPH_RUN {
+        unsafe {
+            let mut glyph_run: DWRITE_GLYPH_RUN = zeroed();
+            glyph_run.fontFace = self.font_face.as_ptr();
+            glyph_run.fontEmSize = self.em_size;
+            glyph_run.glyphCount = self.glyph_indices.len() as u32;
+            glyph_run.glyphIndices = self.glyph_indices.as_ptr();
+            glyph_run.glyphAdvances = self.glyph_advances.as_ptr();
+            glyph_run.glyphOffsets = self.glyph_offsets.as_ptr();
+            glyph_run.isSideways = 0;
+            glyph_run.bidiLevel = if self.is_right_to_left { 1 } else { 0 };
+            glyph_run
+        }
+    }
+}
+
+// Builds a GlyphRun with no glyph offsets.  `origin` is where the run
+// starts in reading order: its left end for left-to-right text, its right
+// end for right-to-left text.
+pub fn layout_glyph_run<'a>(font_face: &'a FontFace,
+                            em_size: f32,
+                            glyph_indices: &[u16],
+                            glyph_advances: &[f32],
+                            origin: (f32, f32),
+                            is_right_to_left: bool)
+                            -> GlyphRun<'a> {
+    assert!(glyph_indices.len() == glyph_advances.len());
+    GlyphRun {
+        font_face,
+        em_size,
+        glyph_indices: glyph_indices.to_vec(),
+        glyph_advances: glyph_advances.to_vec(),
+        glyph_offsets: vec![GlyphOffset { advanceOffset: 0., ascenderOffset: 0. }; glyph_indices.len()],
+        baseline_origin: origin,
+        is_right_to_left,
+    }
+}
@@ -6,14 +6,50 @@ use std::ptr;
 use std::cell::UnsafeCell;
 
 use comptr::ComPtr;
-use winapi::um::dcommon::DWRITE_MEASURING_MODE;
+use winapi::um::dcommon::{DWRITE_MEASURING_MODE, DWRITE_MEASURING_MODE_NATURAL};
 use winapi::um::dwrite::{DWRITE_RENDERING_MODE, DWRITE_MATRIX};
+use winapi::um::dwrite::{DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC, DWRITE_RENDERING_MODE_OUTLINE};
 use winapi::um::dwrite::{DWRITE_GLYPH_RUN, DWRITE_TEXTURE_ALIASED_1x1, DWRITE_TEXTURE_TYPE};
 use winapi::um::dwrite::DWRITE_TEXTURE_CLEARTYPE_3x1;
 use winapi::shared::windef::RECT;
 use winapi::um::dwrite::IDWriteGlyphRunAnalysis;
+use winapi::um::dwrite_1::DWRITE_TEXT_ANTIALIAS_MODE_CLEARTYPE;
+use winapi::um::dwrite_2::{IDWriteFactory2, DWRITE_GRID_FIT_MODE};
+use winapi::um::dwrite_2::{DWRITE_GRID_FIT_MODE_DEFAULT, DWRITE_GRID_FIT_MODE_DISABLED};
+use winapi::shared::winerror::{E_INVALIDARG, E_NOINTERFACE, HRESULT};
+use winapi::Interface;
 use std::mem;
-use super::DWriteFactory;
+use super::{DWriteFactory, RenderingParams};
+
+// The values a renderer should use when blending this analysis's alpha
+// texture, as adjusted by DirectWrite for the run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlphaBlendParams {
+    pub gamma: f32,
+    pub enhanced_contrast: f32,
+    pub cleartype_level: f32,
+}
+
+// Rounds a baseline position in DIPs to the nearest device pixel the way
+// DirectWrite and Direct2D do when pixel snapping: halves round up, even
+// for negative positions, so text laid out and rasterized separately ends
+// up on the same pixel row.
+pub fn snap_baseline(y: f32, pixels_per_dip: f32) -> f32 {
+    (y * pixels_per_dip + 0.5).floor() / pixels_per_dip
+}
+
+// Glyph run analysis rasterizes, so it rejects DWRITE_RENDERING_MODE_OUTLINE,
+// which DirectWrite recommends for text too big to be worth rasterizing
+// and expects to be drawn as geometry.  Anything that resolves a mode and
+// then rasterizes with it goes through here, which substitutes the best
+// rasterized mode for it.
+pub(crate) fn rasterizable_rendering_mode(rendering_mode: DWRITE_RENDERING_MODE) -> DWRITE_RENDERING_MODE {
+    if rendering_mode == DWRITE_RENDERING_MODE_OUTLINE {
+        DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC
+    } else {
+        rendering_mode
+    }
+}
 
 pub struct GlyphRunAnalysis {
     native: UnsafeCell<ComPtr<IDWriteGlyphRunAnalysis>>,
@@ -27,6 +63,20 @@ impl GlyphRunAnalysis {
                   measuring_mode: DWRITE_MEASURING_MODE,
                   baseline_x: f32,
                   baseline_y: f32) -> GlyphRunAnalysis
+    {
+        GlyphRunAnalysis::try_create(glyph_run, pixels_per_dip, transform, rendering_mode, measuring_mode,
+                                     baseline_x, baseline_y).unwrap()
+    }
+
+    // Like create, but returning DirectWrite's error (E_INVALIDARG for an
+    // unsupported rendering mode, for example) instead of panicking.
+    pub fn try_create(glyph_run: &DWRITE_GLYPH_RUN,
+                      pixels_per_dip: f32,
+                      transform: Option<DWRITE_MATRIX>,
+                      rendering_mode: DWRITE_RENDERING_MODE,
+                      measuring_mode: DWRITE_MEASURING_MODE,
+                      baseline_x: f32,
+                      baseline_y: f32) -> Result<GlyphRunAnalysis, HRESULT>
     {
         unsafe {
             let mut native: ComPtr<IDWriteGlyphRunAnalysis> = ComPtr::new();
@@ -36,8 +86,85 @@ impl GlyphRunAnalysis {
                                                                rendering_mode, measuring_mode,
                                                                baseline_x, baseline_y,
                                                                native.getter_addrefs());
-            assert!(hr == 0);
-            GlyphRunAnalysis::take(native)
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(GlyphRunAnalysis::take(native))
+        }
+    }
+
+    // Like create, but with should_pixel_snap the baseline's y is first
+    // rounded with snap_baseline, so the analysis lines up with
+    // pixel-snapped layout.  Snapping happens before the transform, so it
+    // is only exact for transforms without rotation or vertical scaling.
+    pub fn create_at_baseline(glyph_run: &DWRITE_GLYPH_RUN,
+                              pixels_per_dip: f32,
+                              transform: Option<DWRITE_MATRIX>,
+                              rendering_mode: DWRITE_RENDERING_MODE,
+                              measuring_mode: DWRITE_MEASURING_MODE,
+                              (baseline_x, baseline_y): (f32, f32),
+                              should_pixel_snap: bool)
+                              -> GlyphRunAnalysis {
+        let baseline_y = if should_pixel_snap {
+            snap_baseline(baseline_y, pixels_per_dip)
+        } else {
+            baseline_y
+        };
+        GlyphRunAnalysis::create(glyph_run, pixels_per_dip, transform, rendering_mode, measuring_mode,
+                                 baseline_x, baseline_y)
+    }
+
+    // Like create, but with an explicit grid fit mode, which needs
+    // IDWriteFactory2 (E_NOINTERFACE without it).  Turning grid fitting
+    // off keeps subpixel-positioned or animated text from jittering as it
+    // moves.  GDI measuring modes are grid fitted by definition, so
+    // DWRITE_GRID_FIT_MODE_DISABLED is only accepted with
+    // DWRITE_MEASURING_MODE_NATURAL and is otherwise E_INVALIDARG.
+    // DWRITE_GRID_FIT_MODE_DEFAULT is the same as calling try_create.
+    pub fn create_with_grid_fit(glyph_run: &DWRITE_GLYPH_RUN,
+                                pixels_per_dip: f32,
+                                transform: Option<DWRITE_MATRIX>,
+                                rendering_mode: DWRITE_RENDERING_MODE,
+                                measuring_mode: DWRITE_MEASURING_MODE,
+                                grid_fit_mode: DWRITE_GRID_FIT_MODE,
+                                (baseline_x, baseline_y): (f32, f32))
+                                -> Result<GlyphRunAnalysis, HRESULT> {
+        if grid_fit_mode == DWRITE_GRID_FIT_MODE_DEFAULT {
+            return GlyphRunAnalysis::try_create(glyph_run, pixels_per_dip, transform, rendering_mode,
+                                                measuring_mode, baseline_x, baseline_y);
+        }
+        if grid_fit_mode == DWRITE_GRID_FIT_MODE_DISABLED &&
+           measuring_mode != DWRITE_MEASURING_MODE_NATURAL {
+            return Err(E_INVALIDARG);
+        }
+
+        unsafe {
+            let factory = match ComPtr::from_ptr(DWriteFactory())
+                .query_interface::<IDWriteFactory2>(&IDWriteFactory2::uuidof()) {
+                Some(factory) => factory,
+                None => return Err(E_NOINTERFACE),
+            };
+
+            // This version of the call has no pixels_per_dip, so the
+            // scale is folded into the transform instead.
+            let mut matrix = transform.unwrap_or(DWRITE_MATRIX {
+                m11: 1., m12: 0., m21: 0., m22: 1., dx: 0., dy: 0.,
+            });
+            matrix.m11 *= pixels_per_dip;
+            matrix.m12 *= pixels_per_dip;
+            matrix.m21 *= pixels_per_dip;
+            matrix.m22 *= pixels_per_dip;
+            matrix.dx *= pixels_per_dip;
+            matrix.dy *= pixels_per_dip;
+
+            let mut native: ComPtr<IDWriteGlyphRunAnalysis> = ComPtr::new();
+            let hr = factory.CreateGlyphRunAnalysis(glyph_run, &matrix, rendering_mode, measuring_mode,
+                                                    grid_fit_mode, DWRITE_TEXT_ANTIALIAS_MODE_CLEARTYPE,
+                                                    baseline_x, baseline_y, native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(GlyphRunAnalysis::take(native))
         }
     }
 
@@ -47,6 +174,13 @@ impl GlyphRunAnalysis {
         }
     }
 
+    // The analysis does its work once, when it's created; this and
+    // create_alpha_texture only read from it, so they can be called any
+    // number of times, for either texture type, on the same analysis.
+    // Only the texture type matching the rendering mode has any coverage,
+    // though: DWRITE_TEXTURE_ALIASED_1x1 for DWRITE_RENDERING_MODE_ALIASED
+    // and DWRITE_TEXTURE_CLEARTYPE_3x1 for everything else.  Asking for
+    // the other type gives empty bounds.
     pub fn get_alpha_texture_bounds(&self, texture_type: DWRITE_TEXTURE_TYPE) -> RECT {
         unsafe {
             let mut rect: RECT = mem::zeroed();
@@ -73,4 +207,16 @@ impl GlyphRunAnalysis {
             out_bytes
         }
     }
+
+    pub fn get_alpha_blend_params(&self, rendering_params: &RenderingParams) -> AlphaBlendParams {
+        unsafe {
+            let mut params = AlphaBlendParams { gamma: 0., enhanced_contrast: 0., cleartype_level: 0. };
+            let hr = (*self.native.get()).GetAlphaBlendParams(rendering_params.as_ptr(),
+                                                              &mut params.gamma,
+                                                              &mut params.enhanced_contrast,
+                                                              &mut params.cleartype_level);
+            assert!(hr == 0);
+            params
+        }
+    }
 }
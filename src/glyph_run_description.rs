@@ -0,0 +1,76 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::ops::Range;
+use std::slice;
+
+use winapi::um::dwrite::DWRITE_GLYPH_RUN_DESCRIPTION;
+
+// The source text behind a glyph run, as DirectWrite hands it to text
+// renderer callbacks alongside the run itself.  Everything borrows from
+// the layout that is being drawn, so it only lives as long as the
+// callback.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphRunDescription<'a> {
+    // UTF-16, without the terminating null.
+    pub locale_name: &'a [u16],
+    // The UTF-16 text the run was shaped from.
+    pub text: &'a [u16],
+    // For each UTF-16 code unit of text, the index of the first glyph of
+    // its cluster.
+    pub cluster_map: &'a [u16],
+    // Where text starts within the whole layout's text.
+    pub text_position: u32,
+}
+
+impl<'a> GlyphRunDescription<'a> {
+    // The pointers in `description` must be valid for 'a, as they are
+    // during a text renderer callback.
+    pub unsafe fn from_raw(description: &'a DWRITE_GLYPH_RUN_DESCRIPTION) -> GlyphRunDescription<'a> {
+        let locale_name = if description.localeName.is_null() {
+            &[][..]
+        } else {
+            let mut length = 0;
+            while *description.localeName.offset(length) != 0 {
+                length += 1;
+            }
+            slice::from_raw_parts(description.localeName, length as usize)
+        };
+        let length = description.stringLength as usize;
+        let (text, cluster_map) = if length == 0 {
+            (&[][..], &[][..])
+        } else {
+            (slice::from_raw_parts(description.string, length),
+             slice::from_raw_parts(description.clusterMap, length))
+        };
+
+        GlyphRunDescription {
+            locale_name,
+            text,
+            cluster_map,
+            text_position: description.textPosition,
+        }
+    }
+
+    pub fn locale(&self) -> String {
+        String::from_utf16_lossy(self.locale_name)
+    }
+
+    pub fn text_string(&self) -> String {
+        String::from_utf16_lossy(self.text)
+    }
+
+    // The range of text, in UTF-16 code units relative to the start of
+    // the run, that produced the cluster containing `glyph`.  Returns
+    // None if no text maps to that glyph.
+    pub fn text_range_for_glyph(&self, glyph: u16) -> Option<Range<usize>> {
+        let cluster_start = self.cluster_map.iter().rev().find(|&&first| first <= glyph)?;
+        let start = self.cluster_map.iter().position(|first| first == cluster_start)?;
+        let end = self.cluster_map[start..].iter()
+            .position(|first| first != cluster_start)
+            .map(|offset| start + offset)
+            .unwrap_or(self.cluster_map.len());
+        Some(start..end)
+    }
+}
@@ -7,7 +7,7 @@ use winapi::ctypes::wchar_t;
 use comptr::ComPtr;
 use winapi::shared::winerror::S_OK;
 use winapi::shared::minwindef::{BOOL, FALSE};
-use winapi::um::winnls::GetUserDefaultLocaleName;
+use winapi::um::winnls::{GetUserDefaultLocaleName, LocaleNameToLCID};
 use std::ffi::{OsStr};
 use std::os::windows::ffi::{OsStrExt};
 
@@ -21,15 +21,50 @@ lazy_static! {
         }
     };
     static ref EN_US_LOCALE: Vec<wchar_t> = {
-        OsStr::new("en-us").encode_wide().collect()
+        OsStr::new("en-us").to_wide_null()
     };
 }
 
+// The user's default locale name (e.g. "en-US"), as reported by
+// GetUserDefaultLocaleName.  Name lookups try this locale first, then
+// "en-us", then whatever the first available locale is.
+pub fn system_locale_name() -> String {
+    let len = SYSTEM_LOCALE.iter().position(|&c| c == 0).unwrap_or(SYSTEM_LOCALE.len());
+    String::from_utf16_lossy(&SYSTEM_LOCALE[..len])
+}
+
+// The Windows language ID for a locale name such as "en-US", which is what
+// the Windows platform records of a 'name' table are keyed by.
+pub fn locale_name_to_language_id(locale: &str) -> Option<u16> {
+    let lcid = unsafe { LocaleNameToLCID(locale.to_wide_null().as_ptr(), 0) };
+    if lcid == 0 {
+        None
+    } else {
+        Some((lcid & 0xffff) as u16)
+    }
+}
+
 pub fn get_locale_string(strings: &mut ComPtr<IDWriteLocalizedStrings>) -> String {
+    get_locale_string_for(strings, None)
+}
+
+// Like get_locale_string, but tries `locale` (if given) before falling
+// back to the system locale, en-us and the first available string.
+pub fn get_locale_string_for(strings: &mut ComPtr<IDWriteLocalizedStrings>, locale: Option<&str>) -> String {
     unsafe {
         let mut index: u32 = 0;
         let mut exists: BOOL = FALSE;
-        let hr = strings.FindLocaleName((*SYSTEM_LOCALE).as_ptr(), &mut index, &mut exists);
+        if let Some(locale) = locale {
+            let hr = strings.FindLocaleName(locale.to_wide_null().as_ptr(), &mut index, &mut exists);
+            if hr != S_OK {
+                exists = FALSE;
+            }
+        }
+        let hr = if exists == FALSE {
+            strings.FindLocaleName((*SYSTEM_LOCALE).as_ptr(), &mut index, &mut exists)
+        } else {
+            S_OK
+        };
         if hr != S_OK || exists == FALSE {
             let hr = strings.FindLocaleName((*EN_US_LOCALE).as_ptr(), &mut index, &mut exists);
             if hr != S_OK || exists == FALSE {
@@ -0,0 +1,36 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use winapi::ctypes::c_void;
+use winapi::um::dwrite::{DWRITE_BREAK_CONDITION, DWRITE_BREAK_CONDITION_NEUTRAL};
+use winapi::um::dwrite::{DWRITE_INLINE_OBJECT_METRICS, DWRITE_OVERHANG_METRICS};
+use winapi::um::unknwnbase::IUnknown;
+
+// Non-text content, such as an image or a widget, embedded in a
+// TextLayout with TextLayout::set_inline_object.
+pub trait InlineObject {
+    // Draws the object with its top-left corner at the origin.  The
+    // drawing context and effect are whatever the caller passed to the
+    // layout's Draw and attached to the range, respectively.
+    fn draw(&self,
+            client_drawing_context: *mut c_void,
+            origin_x: f32,
+            origin_y: f32,
+            is_sideways: bool,
+            is_right_to_left: bool,
+            drawing_effect: *mut IUnknown);
+
+    // The object's size and where its baseline is, in DIPs.
+    fn get_metrics(&self) -> DWRITE_INLINE_OBJECT_METRICS;
+
+    // How far the object draws outside the box given by get_metrics.
+    fn get_overhang_metrics(&self) -> DWRITE_OVERHANG_METRICS {
+        DWRITE_OVERHANG_METRICS { left: 0., top: 0., right: 0., bottom: 0. }
+    }
+
+    // Whether lines may break before and after the object.
+    fn get_break_conditions(&self) -> (DWRITE_BREAK_CONDITION, DWRITE_BREAK_CONDITION) {
+        (DWRITE_BREAK_CONDITION_NEUTRAL, DWRITE_BREAK_CONDITION_NEUTRAL)
+    }
+}
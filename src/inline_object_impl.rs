@@ -0,0 +1,116 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// An IDWriteInlineObject that forwards to an InlineObject implementation.
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+use std::mem;
+use std::sync::atomic;
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::REFIID;
+use winapi::shared::minwindef::{BOOL, ULONG};
+use winapi::shared::winerror::{E_INVALIDARG, S_OK};
+use winapi::um::dwrite::{IDWriteInlineObject, IDWriteInlineObjectVtbl, IDWriteTextRenderer};
+use winapi::um::dwrite::{DWRITE_BREAK_CONDITION, DWRITE_INLINE_OBJECT_METRICS, DWRITE_OVERHANG_METRICS};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::HRESULT;
+
+use comptr::ComPtr;
+use com_helpers::*;
+use super::InlineObject;
+
+DEFINE_GUID!{UuidOfIDWriteInlineObject, 0x8339fde3, 0x106f, 0x47ab, 0x83, 0x73, 0x1c, 0x62, 0x95, 0xeb, 0x10, 0xb3}
+
+pub struct CustomInlineObject {
+    refcount: atomic::AtomicUsize,
+    object: Box<dyn InlineObject>,
+}
+
+const CustomInlineObjectVtbl: &IDWriteInlineObjectVtbl = &IDWriteInlineObjectVtbl {
+    parent: implement_iunknown!(IDWriteInlineObject, UuidOfIDWriteInlineObject, CustomInlineObject),
+    Draw: {
+        unsafe extern "system" fn Draw(
+            This: *mut IDWriteInlineObject,
+            clientDrawingContext: *mut c_void,
+            _renderer: *mut IDWriteTextRenderer,
+            originX: f32,
+            originY: f32,
+            isSideways: BOOL,
+            isRightToLeft: BOOL,
+            clientDrawingEffect: *mut IUnknown) -> HRESULT
+        {
+            let this = CustomInlineObject::from_interface(This);
+            this.object.draw(clientDrawingContext, originX, originY, isSideways != 0, isRightToLeft != 0,
+                             clientDrawingEffect);
+            S_OK
+        }
+        Draw
+    },
+    GetMetrics: {
+        unsafe extern "system" fn GetMetrics(
+            This: *mut IDWriteInlineObject,
+            metrics: *mut DWRITE_INLINE_OBJECT_METRICS) -> HRESULT
+        {
+            if metrics.is_null() {
+                return E_INVALIDARG
+            }
+            let this = CustomInlineObject::from_interface(This);
+            *metrics = this.object.get_metrics();
+            S_OK
+        }
+        GetMetrics
+    },
+    GetOverhangMetrics: {
+        unsafe extern "system" fn GetOverhangMetrics(
+            This: *mut IDWriteInlineObject,
+            overhangs: *mut DWRITE_OVERHANG_METRICS) -> HRESULT
+        {
+            if overhangs.is_null() {
+                return E_INVALIDARG
+            }
+            let this = CustomInlineObject::from_interface(This);
+            *overhangs = this.object.get_overhang_metrics();
+            S_OK
+        }
+        GetOverhangMetrics
+    },
+    GetBreakConditions: {
+        unsafe extern "system" fn GetBreakConditions(
+            This: *mut IDWriteInlineObject,
+            breakConditionBefore: *mut DWRITE_BREAK_CONDITION,
+            breakConditionAfter: *mut DWRITE_BREAK_CONDITION) -> HRESULT
+        {
+            if breakConditionBefore.is_null() || breakConditionAfter.is_null() {
+                return E_INVALIDARG
+            }
+            let this = CustomInlineObject::from_interface(This);
+            let (before, after) = this.object.get_break_conditions();
+            *breakConditionBefore = before;
+            *breakConditionAfter = after;
+            S_OK
+        }
+        GetBreakConditions
+    },
+};
+
+impl CustomInlineObject {
+    pub fn create<T>(object: T) -> ComPtr<IDWriteInlineObject> where T: InlineObject + 'static {
+        let native = CustomInlineObject {
+            refcount: atomic::AtomicUsize::new(0),
+            object: Box::new(object),
+        };
+        ComPtr::from_ptr(native.into_interface())
+    }
+}
+
+impl Com<IDWriteInlineObject> for CustomInlineObject {
+    type Vtbl = IDWriteInlineObjectVtbl;
+    fn vtbl() -> &'static IDWriteInlineObjectVtbl { CustomInlineObjectVtbl }
+}
+
+impl Com<IUnknown> for CustomInlineObject {
+    type Vtbl = IUnknownVtbl;
+    fn vtbl() -> &'static IUnknownVtbl { &CustomInlineObjectVtbl.parent }
+}
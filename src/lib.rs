@@ -9,7 +9,7 @@ extern crate serde_derive;
 
 #[macro_use]
 extern crate lazy_static;
-#[macro_use(DEFINE_GUID)]
+#[macro_use(DEFINE_GUID, RIDL)]
 extern crate winapi;
 extern crate libc;
 extern crate serde;
@@ -20,7 +20,11 @@ use winapi::um::dwrite::DWRITE_FACTORY_TYPE_SHARED;
 use winapi::um::dwrite::IDWriteFactory;
 use winapi::um::dwrite::IDWriteRenderingParams;
 use winapi::um::winnt::{HRESULT, LPCSTR};
-use winapi::shared::guiddef::REFIID;
+use winapi::shared::guiddef::{GUID, REFIID};
+use winapi::um::dwrite_1::IDWriteFactory1;
+use winapi::um::dwrite_2::IDWriteFactory2;
+use winapi::um::dwrite_3::IDWriteFactory3;
+use winapi::Interface;
 use winapi::um::unknwnbase::IUnknown;
 use winapi::um::dwrite::DWRITE_FACTORY_TYPE;
 use std::ffi::CString;
@@ -31,6 +35,7 @@ use winapi::shared::winerror::S_OK;
 mod comptr;
 mod helpers;
 use helpers::ToWide;
+pub use helpers::system_locale_name;
 use std::os::raw::c_void;
 
 #[cfg(test)]
@@ -40,7 +45,8 @@ mod test;
 // here
 pub use winapi::um::dwrite::DWRITE_FONT_METRICS as FontMetrics;
 pub use winapi::um::dwrite::DWRITE_GLYPH_OFFSET as GlyphOffset;
-pub use winapi::um::dwrite::{DWRITE_MATRIX, DWRITE_GLYPH_RUN};
+pub use winapi::um::dwrite::DWRITE_GLYPH_METRICS as GlyphMetrics;
+pub use winapi::um::dwrite::{DWRITE_MATRIX, DWRITE_GLYPH_RUN, DWRITE_GLYPH_RUN_DESCRIPTION};
 pub use winapi::um::dwrite::{DWRITE_RENDERING_MODE_DEFAULT,
                  DWRITE_RENDERING_MODE_ALIASED,
                  DWRITE_RENDERING_MODE_GDI_CLASSIC,
@@ -52,6 +58,11 @@ pub use winapi::um::dwrite::{DWRITE_RENDERING_MODE_DEFAULT,
                  DWRITE_RENDERING_MODE_CLEARTYPE_GDI_NATURAL,
                  DWRITE_RENDERING_MODE_CLEARTYPE_NATURAL,
                  DWRITE_RENDERING_MODE_CLEARTYPE_NATURAL_SYMMETRIC};
+pub use winapi::um::dwrite_1::DWRITE_JUSTIFICATION_OPPORTUNITY;
+pub use winapi::um::dwrite_2::{DWRITE_GRID_FIT_MODE,
+                 DWRITE_GRID_FIT_MODE_DEFAULT,
+                 DWRITE_GRID_FIT_MODE_DISABLED,
+                 DWRITE_GRID_FIT_MODE_ENABLED};
 pub use winapi::um::dcommon::{DWRITE_MEASURING_MODE_NATURAL,
                  DWRITE_MEASURING_MODE_GDI_CLASSIC,
                  DWRITE_MEASURING_MODE_GDI_NATURAL};
@@ -60,29 +71,139 @@ pub use winapi::um::dwrite::{DWRITE_FONT_SIMULATIONS_NONE,
                  DWRITE_FONT_SIMULATIONS_OBLIQUE};
 pub use winapi::um::dwrite::{DWRITE_TEXTURE_ALIASED_1x1, DWRITE_TEXTURE_CLEARTYPE_3x1};
 pub use winapi::um::dwrite::{DWRITE_FONT_SIMULATIONS};
+pub use winapi::um::dwrite::{DWRITE_INFORMATIONAL_STRING_ID,
+                 DWRITE_INFORMATIONAL_STRING_COPYRIGHT_NOTICE,
+                 DWRITE_INFORMATIONAL_STRING_VERSION_STRINGS,
+                 DWRITE_INFORMATIONAL_STRING_TRADEMARK,
+                 DWRITE_INFORMATIONAL_STRING_MANUFACTURER,
+                 DWRITE_INFORMATIONAL_STRING_DESIGNER,
+                 DWRITE_INFORMATIONAL_STRING_DESCRIPTION,
+                 DWRITE_INFORMATIONAL_STRING_LICENSE_DESCRIPTION,
+                 DWRITE_INFORMATIONAL_STRING_WIN32_FAMILY_NAMES,
+                 DWRITE_INFORMATIONAL_STRING_WIN32_SUBFAMILY_NAMES,
+                 DWRITE_INFORMATIONAL_STRING_PREFERRED_FAMILY_NAMES,
+                 DWRITE_INFORMATIONAL_STRING_PREFERRED_SUBFAMILY_NAMES,
+                 DWRITE_INFORMATIONAL_STRING_SAMPLE_TEXT,
+                 DWRITE_INFORMATIONAL_STRING_FULL_NAME,
+                 DWRITE_INFORMATIONAL_STRING_POSTSCRIPT_NAME};
 pub use winapi::um::dwrite::{DWRITE_RENDERING_MODE};
 pub use winapi::um::dwrite::{DWRITE_TEXTURE_TYPE};
+pub use winapi::um::dwrite::{DWRITE_INLINE_OBJECT_METRICS, DWRITE_OVERHANG_METRICS, DWRITE_TEXT_METRICS};
+pub use winapi::um::dwrite_3::{DWRITE_FONT_PROPERTY_ID,
+                 DWRITE_FONT_PROPERTY_ID_NONE,
+                 DWRITE_FONT_PROPERTY_ID_FAMILY_NAME,
+                 DWRITE_FONT_PROPERTY_ID_PREFERRED_FAMILY_NAME,
+                 DWRITE_FONT_PROPERTY_ID_FACE_NAME,
+                 DWRITE_FONT_PROPERTY_ID_FULL_NAME,
+                 DWRITE_FONT_PROPERTY_ID_WIN32_FAMILY_NAME,
+                 DWRITE_FONT_PROPERTY_ID_POSTSCRIPT_NAME,
+                 DWRITE_FONT_PROPERTY_ID_DESIGN_SCRIPT_LANGUAGE_TAG,
+                 DWRITE_FONT_PROPERTY_ID_SUPPORTED_SCRIPT_LANGUAGE_TAG,
+                 DWRITE_FONT_PROPERTY_ID_SEMANTIC_TAG,
+                 DWRITE_FONT_PROPERTY_ID_WEIGHT,
+                 DWRITE_FONT_PROPERTY_ID_STRETCH,
+                 DWRITE_FONT_PROPERTY_ID_STYLE};
+pub use winapi::um::dcommon::{DWRITE_GLYPH_IMAGE_FORMATS,
+                 DWRITE_GLYPH_IMAGE_FORMATS_PNG,
+                 DWRITE_GLYPH_IMAGE_FORMATS_JPEG,
+                 DWRITE_GLYPH_IMAGE_FORMATS_TIFF,
+                 DWRITE_GLYPH_IMAGE_FORMATS_SVG,
+                 DWRITE_GLYPH_IMAGE_FORMATS_PREMULTIPLIED_B8G8R8A8};
+pub use winapi::um::dwrite::{DWRITE_READING_DIRECTION,
+                 DWRITE_READING_DIRECTION_LEFT_TO_RIGHT,
+                 DWRITE_READING_DIRECTION_RIGHT_TO_LEFT};
+pub use winapi::um::dwrite::{DWRITE_LINE_SPACING_METHOD,
+                 DWRITE_LINE_SPACING_METHOD_DEFAULT,
+                 DWRITE_LINE_SPACING_METHOD_UNIFORM,
+                 DWRITE_LINE_SPACING_METHOD_PROPORTIONAL};
+pub use winapi::um::dwrite::{DWRITE_TRIMMING_GRANULARITY,
+                 DWRITE_TRIMMING_GRANULARITY_NONE,
+                 DWRITE_TRIMMING_GRANULARITY_CHARACTER,
+                 DWRITE_TRIMMING_GRANULARITY_WORD};
+pub use winapi::um::dwrite::{DWRITE_WORD_WRAPPING,
+                 DWRITE_WORD_WRAPPING_WRAP,
+                 DWRITE_WORD_WRAPPING_NO_WRAP,
+                 DWRITE_WORD_WRAPPING_EMERGENCY_BREAK,
+                 DWRITE_WORD_WRAPPING_WHOLE_WORD,
+                 DWRITE_WORD_WRAPPING_CHARACTER};
+pub use winapi::um::dwrite::{DWRITE_BREAK_CONDITION,
+                 DWRITE_BREAK_CONDITION_NEUTRAL,
+                 DWRITE_BREAK_CONDITION_CAN_BREAK,
+                 DWRITE_BREAK_CONDITION_MAY_NOT_BREAK,
+                 DWRITE_BREAK_CONDITION_MUST_BREAK};
+pub use winapi::um::dwrite::{DWRITE_NUMBER_SUBSTITUTION_METHOD,
+                 DWRITE_NUMBER_SUBSTITUTION_METHOD_FROM_CULTURE,
+                 DWRITE_NUMBER_SUBSTITUTION_METHOD_CONTEXTUAL,
+                 DWRITE_NUMBER_SUBSTITUTION_METHOD_NONE,
+                 DWRITE_NUMBER_SUBSTITUTION_METHOD_NATIONAL,
+                 DWRITE_NUMBER_SUBSTITUTION_METHOD_TRADITIONAL};
+pub use winapi::um::dwrite::{DWRITE_SCRIPT_ANALYSIS, DWRITE_SCRIPT_SHAPES_DEFAULT, DWRITE_SCRIPT_SHAPES_NO_VISUAL};
+pub use winapi::um::dwrite::{DWRITE_PIXEL_GEOMETRY,
+                 DWRITE_PIXEL_GEOMETRY_FLAT,
+                 DWRITE_PIXEL_GEOMETRY_RGB,
+                 DWRITE_PIXEL_GEOMETRY_BGR};
 pub use winapi::um::dcommon::{DWRITE_MEASURING_MODE};
 use winapi::um::libloaderapi::{GetProcAddress, LoadLibraryW};
 
 #[macro_use] mod com_helpers;
 
 mod bitmap_render_target; pub use bitmap_render_target::BitmapRenderTarget;
+mod drawing_effect; pub use drawing_effect::ColorDrawingEffect;
+mod com_init; pub use com_init::{ComThreadGuard, init_thread};
+mod feature_settings; pub use feature_settings::FeatureSettings;
 mod font; pub use font::Font;
 mod font_collection; pub use font_collection::FontCollection;
-mod font_face; pub use font_face::FontFace;
+mod font_face; pub use font_face::{FontFace, LineMetrics, RasterizedGlyph, RecommendedRendering};
+pub use font_face::{ValidationError, ValidationProblem, gdi_compatible_ppem, svg_document_is_compressed};
+pub use font_face::{BitmapGlyph, ColorGlyphLayer, StylisticSet, TransformedGlyphMetrics};
 mod font_family; pub use font_family::FontFamily;
 mod font_file; pub use font_file::FontFile;
+mod font_download_queue; pub use font_download_queue::FontDownloadQueue;
+mod font_fallback; pub use font_fallback::{FallbackRun, FontFallback, measure_string_with_fallback};
+mod font_set; pub use font_set::{FontPropertyOverride, FontSet, FontSetBuilder};
+mod font_list; pub use font_list::FontList;
 mod gdi_interop; pub use gdi_interop::GdiInterop;
-mod rendering_params; pub use rendering_params::RenderingParams;
-mod glyph_run_analysis; pub use glyph_run_analysis::GlyphRunAnalysis;
+mod paint_reader; pub use paint_reader::{PaintNode, PaintReader, DWRITE_PAINT_COLOR};
+pub use paint_reader::DWRITE_GLYPH_IMAGE_FORMATS_COLR_PAINT_TREE;
+mod number_substitution; pub use number_substitution::NumberSubstitution;
+mod rendering_params; pub use rendering_params::{RenderingParams, RenderingParamsBuilder};
+mod glyph_run; pub use glyph_run::{GlyphRun, layout_glyph_run};
+mod glyph_run_analysis; pub use glyph_run_analysis::{AlphaBlendParams, GlyphRunAnalysis, snap_baseline};
+mod glyph_run_description; pub use glyph_run_description::GlyphRunDescription;
+mod inline_object; pub use inline_object::InlineObject;
+mod glyph_atlas; pub use glyph_atlas::{AtlasFormat, AtlasGlyph, AtlasPacker, GlyphAtlas, ShelfPacker};
+mod glyph_metrics; pub use glyph_metrics::GlyphMetricsExt;
+mod glyph_metrics_cache; pub use glyph_metrics_cache::GlyphMetricsCache;
+mod shaped_run; pub use shaped_run::ShapedRun;
+mod text_format; pub use text_format::{TextFormat, TrimmingSign};
+mod text_layout; pub use text_layout::{ClusterMetrics, TextLayout};
+mod transform; pub use transform::Transform;
+mod typography; pub use typography::Typography;
+mod text_analysis_source; pub use text_analysis_source::Utf16AnalysisSource;
+mod text_analyzer; pub use text_analyzer::{JustifiedAdvances, JustifiedRun, ShapingOptions, TextAnalyzer};
+#[cfg(feature = "ffi")] pub mod ffi;
+mod tables; pub use tables::{HeadTable, NameRecord, Os2Metrics, VerticalGlyphMetrics, make_opentype_tag};
+pub use tables::{CPAL_PALETTE_USABLE_WITH_DARK_BACKGROUND, CPAL_PALETTE_USABLE_WITH_LIGHT_BACKGROUND};
+pub use tables::{HEAD_MAC_STYLE_BOLD, HEAD_MAC_STYLE_ITALIC};
+pub use tables::{FvarAxis, FvarInstance, FVAR_AXIS_HIDDEN};
+pub use tables::{StatAxis, StatAxisValue, STAT_ELIDABLE_AXIS_VALUE_NAME, STAT_OLDER_SIBLING_FONT_ATTRIBUTE};
 
 // This is an internal implementation of FontFileLoader, for our utility
 // functions.  We don't wrap the DWriteFontFileLoader interface and
 // related things.
 mod font_file_loader_impl;
+mod inline_object_impl;
+mod drawing_effect_impl;
+mod text_analysis_source_impl;
+mod font_download_listener_impl;
+mod geometry_sink_impl;
 
 DEFINE_GUID!{UuidOfIDWriteFactory, 0xb859ee5a, 0xd838, 0x4b5b, 0xa2, 0xe8, 0x1a, 0xdc, 0x7d, 0x93, 0xdb, 0x48}
+// Newer than winapi knows about; only ever queried for, never called.
+DEFINE_GUID!{UuidOfIDWriteFactory4, 0x4b0b5bd3, 0x0797, 0x4549, 0x8a, 0xc5, 0xfe, 0x91, 0x5c, 0xc5, 0x38, 0x56}
+DEFINE_GUID!{UuidOfIDWriteFactory5, 0x958db99a, 0xbe2a, 0x4f09, 0xaf, 0x7d, 0x65, 0x18, 0x98, 0x03, 0xd1, 0xd3}
+DEFINE_GUID!{UuidOfIDWriteFactory6, 0xf3744d80, 0x21f7, 0x42eb, 0xb3, 0x5d, 0x99, 0x5b, 0xc7, 0x2f, 0xc2, 0x23}
+DEFINE_GUID!{UuidOfIDWriteFactory7, 0x35d0e0b3, 0x9076, 0x4d2e, 0xa0, 0x16, 0xa9, 0x1b, 0x56, 0x8a, 0x06, 0xb4}
 
 unsafe impl Sync for ComPtr<IDWriteFactory> { }
 unsafe impl Sync for ComPtr<IDWriteRenderingParams> {}
@@ -122,6 +243,21 @@ lazy_static! {
     }
   };
 
+  static ref MAX_SUPPORTED_FACTORY_VERSION: u32 = {
+    let factory = ComPtr::from_ptr(DWriteFactory());
+    let iids: [&GUID; 7] = [&UuidOfIDWriteFactory7,
+                            &UuidOfIDWriteFactory6,
+                            &UuidOfIDWriteFactory5,
+                            &UuidOfIDWriteFactory4,
+                            &IDWriteFactory3::uuidof(),
+                            &IDWriteFactory2::uuidof(),
+                            &IDWriteFactory1::uuidof()];
+    iids.iter()
+        .position(|iid| factory.query_interface::<IUnknown>(*iid).is_some())
+        .map(|index| (iids.len() - index) as u32)
+        .unwrap_or(0)
+  };
+
 } // end lazy static
 
 // FIXME vlad would be nice to return, say, FactoryPtr<IDWriteFactory>
@@ -133,6 +269,15 @@ fn DWriteFactory() -> *mut IDWriteFactory {
     (*DWRITE_FACTORY_RAW_PTR) as *mut IDWriteFactory
 }
 
+// The newest IDWriteFactoryN interface the shared factory supports: 0 for
+// plain IDWriteFactory, 1 for IDWriteFactory1 and so on, up to 7.  A cheap
+// way, checked once per process, to tell in advance which of the APIs that
+// need newer interfaces (color fonts from 2, font sets from 3, variable
+// fonts from 5, ...) will work on the running system.
+pub fn max_supported_factory_version() -> u32 {
+    *MAX_SUPPORTED_FACTORY_VERSION
+}
+
 #[allow(non_snake_case)]
 fn DefaultDWriteRenderParams() -> *mut IDWriteRenderingParams {
   (*DEFAULT_DWRITE_RENDERING_PARAMS_RAW_PTR) as *mut IDWriteRenderingParams
@@ -0,0 +1,47 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+
+use comptr::ComPtr;
+use helpers::ToWide;
+use winapi::shared::minwindef::BOOL;
+use winapi::um::dwrite::{IDWriteNumberSubstitution, DWRITE_NUMBER_SUBSTITUTION_METHOD};
+use super::DWriteFactory;
+
+// How ASCII digits should be replaced with a locale's native digits (e.g.
+// Arabic-Indic or Devanagari) when shaping.  Pass it to
+// TextAnalyzer::get_glyphs through ShapingOptions.
+pub struct NumberSubstitution {
+    native: UnsafeCell<ComPtr<IDWriteNumberSubstitution>>,
+}
+
+impl NumberSubstitution {
+    // `locale` picks the digits for the NATIONAL and TRADITIONAL methods
+    // and the culture for FROM_CULTURE.  Unless `ignore_user_override` is
+    // set, the user's regional settings take precedence for the user's
+    // own locale.
+    pub fn new(method: DWRITE_NUMBER_SUBSTITUTION_METHOD, locale: &str, ignore_user_override: bool)
+               -> NumberSubstitution {
+        unsafe {
+            let mut native: ComPtr<IDWriteNumberSubstitution> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateNumberSubstitution(method,
+                                                                 locale.to_wide_null().as_ptr(),
+                                                                 ignore_user_override as BOOL,
+                                                                 native.getter_addrefs());
+            assert!(hr == 0);
+            NumberSubstitution::take(native)
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteNumberSubstitution>) -> NumberSubstitution {
+        NumberSubstitution {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteNumberSubstitution {
+        (*self.native.get()).as_ptr()
+    }
+}
@@ -0,0 +1,406 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// COLRv1 paint graphs, through IDWritePaintReader (Windows 11).  winapi
+// stops at IDWriteFontFace5, so the interfaces and structs involved are
+// declared here, following dwrite_3.h.
+
+#![allow(non_snake_case)]
+
+use std::cell::UnsafeCell;
+use std::mem;
+use std::ptr;
+
+use comptr::ComPtr;
+use font_face::FontFace;
+use winapi::shared::winerror::{E_NOINTERFACE, HRESULT};
+use winapi::um::d2d1::{D2D1_GRADIENT_STOP, D2D1_RECT_F};
+use winapi::um::dcommon::DWRITE_GLYPH_IMAGE_FORMATS;
+use winapi::um::dwrite::{DWRITE_MATRIX, IDWriteLocalizedStrings};
+use winapi::um::dwrite_2::DWRITE_COLOR_F;
+use winapi::um::dwrite_3::{IDWriteFontFace5, IDWriteFontFace5Vtbl};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::Interface;
+
+pub const DWRITE_GLYPH_IMAGE_FORMATS_COLR_PAINT_TREE: DWRITE_GLYPH_IMAGE_FORMATS = 0x00000100;
+
+const DWRITE_PAINT_TYPE_NONE: u32 = 0;
+const DWRITE_PAINT_TYPE_LAYERS: u32 = 1;
+const DWRITE_PAINT_TYPE_SOLID_GLYPH: u32 = 2;
+const DWRITE_PAINT_TYPE_SOLID: u32 = 3;
+const DWRITE_PAINT_TYPE_LINEAR_GRADIENT: u32 = 4;
+const DWRITE_PAINT_TYPE_RADIAL_GRADIENT: u32 = 5;
+const DWRITE_PAINT_TYPE_SWEEP_GRADIENT: u32 = 6;
+const DWRITE_PAINT_TYPE_GLYPH: u32 = 7;
+const DWRITE_PAINT_TYPE_COLOR_GLYPH: u32 = 8;
+const DWRITE_PAINT_TYPE_TRANSFORM: u32 = 9;
+const DWRITE_PAINT_TYPE_COMPOSITE: u32 = 10;
+
+// A color in a paint graph, already resolved against the reader's palette
+// and text color; the other fields say where it came from.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DWRITE_PAINT_COLOR {
+    pub value: DWRITE_COLOR_F,
+    pub paletteEntryIndex: u16,
+    pub alphaMultiplier: f32,
+    pub colorAttributes: u32,
+}
+
+// The paint field is a union in dwrite_3.h; its largest members (the
+// solid glyph and the linear gradient) are eight 32-bit words.
+#[repr(C)]
+struct DWRITE_PAINT_ELEMENT {
+    paintType: u32,
+    paint: [u32; 8],
+}
+
+#[repr(C)]
+struct PaintSolidGlyph {
+    glyphIndex: u32,
+    color: DWRITE_PAINT_COLOR,
+}
+
+#[repr(C)]
+struct PaintLinearGradient {
+    extendMode: u32,
+    gradientStopCount: u32,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+}
+
+#[repr(C)]
+struct PaintRadialGradient {
+    extendMode: u32,
+    gradientStopCount: u32,
+    x0: f32,
+    y0: f32,
+    radius0: f32,
+    x1: f32,
+    y1: f32,
+    radius1: f32,
+}
+
+#[repr(C)]
+struct PaintSweepGradient {
+    extendMode: u32,
+    gradientStopCount: u32,
+    centerX: f32,
+    centerY: f32,
+    startAngle: f32,
+    endAngle: f32,
+}
+
+#[repr(C)]
+struct PaintColorGlyph {
+    glyphIndex: u32,
+    clipBox: D2D1_RECT_F,
+}
+
+RIDL!{#[uuid(0xc4b1fe1b, 0x6e84, 0x47d5, 0xb5, 0x4c, 0xa5, 0x97, 0x98, 0x1b, 0x06, 0xad)]
+interface IDWriteFontFace6(IDWriteFontFace6Vtbl): IDWriteFontFace5(IDWriteFontFace5Vtbl) {
+    fn GetFamilyNames(
+        fontFamilyModel: u32,
+        names: *mut *mut IDWriteLocalizedStrings,
+    ) -> HRESULT,
+    fn GetFaceNames(
+        fontFamilyModel: u32,
+        names: *mut *mut IDWriteLocalizedStrings,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x3945b85b, 0xbc95, 0x40f7, 0xb7, 0x2c, 0x8b, 0x73, 0xbf, 0xc7, 0xe1, 0x3b)]
+interface IDWriteFontFace7(IDWriteFontFace7Vtbl): IDWriteFontFace6(IDWriteFontFace6Vtbl) {
+    fn GetPaintFeatureLevel(
+        glyphImageFormat: DWRITE_GLYPH_IMAGE_FORMATS,
+    ) -> u32,
+    fn CreatePaintReader(
+        glyphImageFormat: DWRITE_GLYPH_IMAGE_FORMATS,
+        paintFeatureLevel: u32,
+        paintReader: *mut *mut IDWritePaintReader,
+    ) -> HRESULT,
+}}
+
+RIDL!{#[uuid(0x8128e912, 0x3b97, 0x42a5, 0xab, 0x6c, 0x24, 0xaa, 0xd3, 0xa8, 0x6e, 0x54)]
+interface IDWritePaintReader(IDWritePaintReaderVtbl): IUnknown(IUnknownVtbl) {
+    fn SetCurrentGlyph(
+        glyphIndex: u32,
+        paintElement: *mut DWRITE_PAINT_ELEMENT,
+        structSize: u32,
+        clipBox: *mut D2D1_RECT_F,
+        glyphAttributes: *mut u32,
+    ) -> HRESULT,
+    fn SetTextColor(
+        textColor: *const DWRITE_COLOR_F,
+    ) -> HRESULT,
+    fn SetColorPaletteIndex(
+        colorPaletteIndex: u32,
+    ) -> HRESULT,
+    fn SetCustomColorPalette(
+        paletteEntries: *const DWRITE_COLOR_F,
+        paletteEntryCount: u32,
+    ) -> HRESULT,
+    fn MoveToFirstChild(
+        paintElement: *mut DWRITE_PAINT_ELEMENT,
+        structSize: u32,
+    ) -> HRESULT,
+    fn MoveToNextSibling(
+        paintElement: *mut DWRITE_PAINT_ELEMENT,
+        structSize: u32,
+    ) -> HRESULT,
+    fn MoveToParent() -> HRESULT,
+    fn GetGradientStops(
+        firstGradientStopIndex: u32,
+        gradientStopCount: u32,
+        gradientStops: *mut D2D1_GRADIENT_STOP,
+    ) -> HRESULT,
+    fn GetGradientStopColors(
+        firstGradientStopIndex: u32,
+        gradientStopCount: u32,
+        gradientStopColors: *mut DWRITE_PAINT_COLOR,
+    ) -> HRESULT,
+}}
+
+// One node of a glyph's paint graph.  Coordinates are in design units
+// with y pointing down, like the glyph's outline, and extend and
+// composite modes use the COLRv1 numbering (pad, repeat, reflect; clear,
+// src, dest, ...).
+#[derive(Clone)]
+pub enum PaintNode {
+    // Paints drawn one over the other, bottom first.
+    Layers(Vec<PaintNode>),
+    // A glyph's outline filled with a single color.
+    SolidGlyph { glyph_index: u32, color: DWRITE_PAINT_COLOR },
+    Solid(DWRITE_PAINT_COLOR),
+    LinearGradient {
+        extend_mode: u32,
+        stops: Vec<D2D1_GRADIENT_STOP>,
+        p0: (f32, f32),
+        p1: (f32, f32),
+        // Sets the direction of the gradient's color lines.
+        p2: (f32, f32),
+    },
+    RadialGradient {
+        extend_mode: u32,
+        stops: Vec<D2D1_GRADIENT_STOP>,
+        center0: (f32, f32),
+        radius0: f32,
+        center1: (f32, f32),
+        radius1: f32,
+    },
+    // Angles are in degrees, counter-clockwise.
+    SweepGradient {
+        extend_mode: u32,
+        stops: Vec<D2D1_GRADIENT_STOP>,
+        center: (f32, f32),
+        start_angle: f32,
+        end_angle: f32,
+    },
+    // `paint` clipped to a glyph's outline.
+    Glyph { glyph_index: u32, paint: Box<PaintNode> },
+    // Another color glyph's paint graph, as a reusable component.
+    ColorGlyph { glyph_index: u32, clip_box: D2D1_RECT_F, paint: Box<PaintNode> },
+    Transform { transform: DWRITE_MATRIX, paint: Box<PaintNode> },
+    Composite { mode: u32, source: Box<PaintNode>, backdrop: Box<PaintNode> },
+    // A paint this version of DirectWrite couldn't describe.
+    None,
+}
+
+// Reads the COLRv1 paint graphs of one font face.  Needs IDWriteFontFace7
+// (Windows 11); new returns E_NOINTERFACE on older systems.
+pub struct PaintReader {
+    native: UnsafeCell<ComPtr<IDWritePaintReader>>,
+}
+
+impl PaintReader {
+    pub fn new(face: &FontFace) -> Result<PaintReader, HRESULT> {
+        unsafe {
+            let face = ComPtr::from_ptr(face.as_ptr());
+            let face7 = match face.query_interface::<IDWriteFontFace7>(&IDWriteFontFace7::uuidof()) {
+                Some(face7) => face7,
+                None => return Err(E_NOINTERFACE),
+            };
+            let level = face7.GetPaintFeatureLevel(DWRITE_GLYPH_IMAGE_FORMATS_COLR_PAINT_TREE);
+            let mut native: ComPtr<IDWritePaintReader> = ComPtr::new();
+            let hr = face7.CreatePaintReader(DWRITE_GLYPH_IMAGE_FORMATS_COLR_PAINT_TREE,
+                                             level,
+                                             native.getter_addrefs());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(PaintReader::take(native))
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWritePaintReader>) -> PaintReader {
+        PaintReader {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWritePaintReader {
+        (*self.native.get()).as_ptr()
+    }
+
+    // The palette that palette-indexed colors are resolved against; 0,
+    // the font's default, unless changed.
+    pub fn set_color_palette_index(&self, index: u32) -> Result<(), HRESULT> {
+        unsafe {
+            let hr = (*self.native.get()).SetColorPaletteIndex(index);
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(())
+        }
+    }
+
+    // The color used for paints that use the text's foreground color.
+    pub fn set_text_color(&self, color: DWRITE_COLOR_F) {
+        unsafe {
+            let hr = (*self.native.get()).SetTextColor(&color);
+            assert!(hr == 0);
+        }
+    }
+
+    // The paint graph of `glyph`, starting from its root, which for a
+    // COLRv1 glyph is a ColorGlyph node carrying the glyph's clip box.
+    // None if the glyph has no color paint.
+    pub fn get_paint_tree(&self, glyph: u16) -> Option<PaintNode> {
+        unsafe {
+            let mut element: DWRITE_PAINT_ELEMENT = mem::zeroed();
+            let mut clip_box: D2D1_RECT_F = mem::zeroed();
+            let hr = (*self.native.get()).SetCurrentGlyph(glyph as u32,
+                                                          &mut element,
+                                                          mem::size_of::<DWRITE_PAINT_ELEMENT>() as u32,
+                                                          &mut clip_box,
+                                                          ptr::null_mut());
+            if hr != 0 || element.paintType == DWRITE_PAINT_TYPE_NONE {
+                return None;
+            }
+            self.read_element(&element).ok()
+        }
+    }
+
+    // Builds the node for `element`, which must be the reader's current
+    // element, along with everything under it.
+    unsafe fn read_element(&self, element: &DWRITE_PAINT_ELEMENT) -> Result<PaintNode, HRESULT> {
+        let paint = element.paint.as_ptr();
+        Ok(match element.paintType {
+            DWRITE_PAINT_TYPE_LAYERS => {
+                PaintNode::Layers(self.read_children(*paint as usize)?)
+            }
+            DWRITE_PAINT_TYPE_SOLID_GLYPH => {
+                let solid_glyph = &*(paint as *const PaintSolidGlyph);
+                PaintNode::SolidGlyph { glyph_index: solid_glyph.glyphIndex, color: solid_glyph.color }
+            }
+            DWRITE_PAINT_TYPE_SOLID => {
+                PaintNode::Solid(*(paint as *const DWRITE_PAINT_COLOR))
+            }
+            DWRITE_PAINT_TYPE_LINEAR_GRADIENT => {
+                let gradient = &*(paint as *const PaintLinearGradient);
+                PaintNode::LinearGradient {
+                    extend_mode: gradient.extendMode,
+                    stops: self.read_gradient_stops(gradient.gradientStopCount)?,
+                    p0: (gradient.x0, gradient.y0),
+                    p1: (gradient.x1, gradient.y1),
+                    p2: (gradient.x2, gradient.y2),
+                }
+            }
+            DWRITE_PAINT_TYPE_RADIAL_GRADIENT => {
+                let gradient = &*(paint as *const PaintRadialGradient);
+                PaintNode::RadialGradient {
+                    extend_mode: gradient.extendMode,
+                    stops: self.read_gradient_stops(gradient.gradientStopCount)?,
+                    center0: (gradient.x0, gradient.y0),
+                    radius0: gradient.radius0,
+                    center1: (gradient.x1, gradient.y1),
+                    radius1: gradient.radius1,
+                }
+            }
+            DWRITE_PAINT_TYPE_SWEEP_GRADIENT => {
+                let gradient = &*(paint as *const PaintSweepGradient);
+                PaintNode::SweepGradient {
+                    extend_mode: gradient.extendMode,
+                    stops: self.read_gradient_stops(gradient.gradientStopCount)?,
+                    center: (gradient.centerX, gradient.centerY),
+                    start_angle: gradient.startAngle,
+                    end_angle: gradient.endAngle,
+                }
+            }
+            DWRITE_PAINT_TYPE_GLYPH => {
+                PaintNode::Glyph { glyph_index: *paint, paint: Box::new(self.read_only_child()?) }
+            }
+            DWRITE_PAINT_TYPE_COLOR_GLYPH => {
+                let color_glyph = &*(paint as *const PaintColorGlyph);
+                PaintNode::ColorGlyph {
+                    glyph_index: color_glyph.glyphIndex,
+                    clip_box: color_glyph.clipBox,
+                    paint: Box::new(self.read_only_child()?),
+                }
+            }
+            DWRITE_PAINT_TYPE_TRANSFORM => {
+                PaintNode::Transform {
+                    transform: *(paint as *const DWRITE_MATRIX),
+                    paint: Box::new(self.read_only_child()?),
+                }
+            }
+            DWRITE_PAINT_TYPE_COMPOSITE => {
+                let mut children = self.read_children(2)?.into_iter();
+                let source = children.next().unwrap_or(PaintNode::None);
+                let backdrop = children.next().unwrap_or(PaintNode::None);
+                PaintNode::Composite { mode: *paint, source: Box::new(source), backdrop: Box::new(backdrop) }
+            }
+            _ => PaintNode::None,
+        })
+    }
+
+    unsafe fn read_only_child(&self) -> Result<PaintNode, HRESULT> {
+        Ok(self.read_children(1)?.pop().unwrap_or(PaintNode::None))
+    }
+
+    // Reads the first `count` children of the current element, leaving it
+    // current again afterwards.
+    unsafe fn read_children(&self, count: usize) -> Result<Vec<PaintNode>, HRESULT> {
+        let mut children = Vec::with_capacity(count);
+        if count == 0 {
+            return Ok(children);
+        }
+        let size = mem::size_of::<DWRITE_PAINT_ELEMENT>() as u32;
+        let mut element: DWRITE_PAINT_ELEMENT = mem::zeroed();
+        let hr = (*self.native.get()).MoveToFirstChild(&mut element, size);
+        if hr != 0 {
+            return Err(hr);
+        }
+        let children = (|| {
+            children.push(self.read_element(&element)?);
+            while children.len() < count {
+                let hr = (*self.native.get()).MoveToNextSibling(&mut element, size);
+                if hr != 0 {
+                    return Err(hr);
+                }
+                children.push(self.read_element(&element)?);
+            }
+            Ok(children)
+        })();
+        let hr = (*self.native.get()).MoveToParent();
+        if hr != 0 {
+            return Err(hr);
+        }
+        children
+    }
+
+    unsafe fn read_gradient_stops(&self, count: u32) -> Result<Vec<D2D1_GRADIENT_STOP>, HRESULT> {
+        let mut stops = vec![mem::zeroed(); count as usize];
+        let hr = (*self.native.get()).GetGradientStops(0, count, stops.as_mut_ptr());
+        if hr != 0 {
+            return Err(hr);
+        }
+        Ok(stops)
+    }
+}
+
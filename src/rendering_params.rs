@@ -5,8 +5,11 @@
 use std::cell::UnsafeCell;
 
 use comptr::ComPtr;
-use winapi::um::dwrite::IDWriteRenderingParams;
-use super::DWriteFactory;
+use winapi::um::dwrite::{IDWriteRenderingParams, DWRITE_PIXEL_GEOMETRY, DWRITE_PIXEL_GEOMETRY_RGB};
+use winapi::um::dwrite::DWRITE_RENDERING_MODE;
+use winapi::shared::windef::HMONITOR;
+use winapi::shared::winerror::HRESULT;
+use super::{DefaultDWriteRenderParams, DWriteFactory};
 
 pub struct RenderingParams {
     native: UnsafeCell<ComPtr<IDWriteRenderingParams>>,
@@ -22,6 +25,26 @@ impl RenderingParams {
         }
     }
 
+    // The crate's shared default params, as created for the primary
+    // monitor when first used.  Unlike create_for_primary_monitor this
+    // doesn't create a new object, so it's cheap to call per glyph, but it
+    // won't pick up ClearType setting changes made after that first use.
+    pub fn default_params() -> RenderingParams {
+        RenderingParams::take(ComPtr::from_ptr(DefaultDWriteRenderParams()))
+    }
+
+    // The ClearType settings the user tuned for a particular monitor, for
+    // rasterizing text that will be shown there.  hmonitor must be a
+    // monitor handle, as from MonitorFromWindow.
+    pub unsafe fn for_monitor(hmonitor: HMONITOR) -> Result<RenderingParams, HRESULT> {
+        let mut native: ComPtr<IDWriteRenderingParams> = ComPtr::new();
+        let hr = (*DWriteFactory()).CreateMonitorRenderingParams(hmonitor, native.getter_addrefs());
+        if hr != 0 {
+            return Err(hr);
+        }
+        Ok(RenderingParams::take(native))
+    }
+
     pub fn take(native: ComPtr<IDWriteRenderingParams>) -> RenderingParams {
         RenderingParams {
             native: UnsafeCell::new(native),
@@ -31,4 +54,115 @@ impl RenderingParams {
     pub unsafe fn as_ptr(&self) -> *mut IDWriteRenderingParams {
         (*self.native.get()).as_ptr()
     }
+
+    pub fn get_gamma(&self) -> f32 {
+        unsafe {
+            (*self.native.get()).GetGamma()
+        }
+    }
+
+    pub fn get_enhanced_contrast(&self) -> f32 {
+        unsafe {
+            (*self.native.get()).GetEnhancedContrast()
+        }
+    }
+
+    pub fn get_cleartype_level(&self) -> f32 {
+        unsafe {
+            (*self.native.get()).GetClearTypeLevel()
+        }
+    }
+
+    pub fn get_pixel_geometry(&self) -> DWRITE_PIXEL_GEOMETRY {
+        unsafe {
+            (*self.native.get()).GetPixelGeometry()
+        }
+    }
+
+    pub fn get_rendering_mode(&self) -> DWRITE_RENDERING_MODE {
+        unsafe {
+            (*self.native.get()).GetRenderingMode()
+        }
+    }
+}
+
+// Builds custom rendering params.  Everything not set explicitly comes
+// from the primary monitor's settings, except the pixel geometry, which
+// defaults to RGB.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderingParamsBuilder {
+    gamma: f32,
+    enhanced_contrast: f32,
+    cleartype_level: f32,
+    pixel_geometry: DWRITE_PIXEL_GEOMETRY,
+    rendering_mode: DWRITE_RENDERING_MODE,
+}
+
+impl RenderingParamsBuilder {
+    pub fn new() -> RenderingParamsBuilder {
+        let monitor = RenderingParams::create_for_primary_monitor();
+        unsafe {
+            let native = &*monitor.native.get();
+            RenderingParamsBuilder {
+                gamma: native.GetGamma(),
+                enhanced_contrast: native.GetEnhancedContrast(),
+                cleartype_level: native.GetClearTypeLevel(),
+                pixel_geometry: DWRITE_PIXEL_GEOMETRY_RGB,
+                rendering_mode: native.GetRenderingMode(),
+            }
+        }
+    }
+
+    // Gamma used when blending text, typically between 1.0 and 2.2.  Higher
+    // values make text look darker on light backgrounds.
+    pub fn gamma(mut self, gamma: f32) -> RenderingParamsBuilder {
+        self.gamma = gamma;
+        self
+    }
+
+    // Extra contrast applied to coverage before blending, typically between
+    // 0.0 (none) and 1.0.
+    pub fn enhanced_contrast(mut self, enhanced_contrast: f32) -> RenderingParamsBuilder {
+        self.enhanced_contrast = enhanced_contrast;
+        self
+    }
+
+    // How much of the ClearType coverage to keep, from 0.0 (grayscale) to
+    // 1.0 (full ClearType).
+    pub fn cleartype_level(mut self, cleartype_level: f32) -> RenderingParamsBuilder {
+        self.cleartype_level = cleartype_level;
+        self
+    }
+
+    // The order of the subpixels on the panel.  Getting this wrong makes
+    // ClearType fringes appear on the opposite side of each stem.
+    pub fn pixel_geometry(mut self, pixel_geometry: DWRITE_PIXEL_GEOMETRY) -> RenderingParamsBuilder {
+        self.pixel_geometry = pixel_geometry;
+        self
+    }
+
+    pub fn rendering_mode(mut self, rendering_mode: DWRITE_RENDERING_MODE) -> RenderingParamsBuilder {
+        self.rendering_mode = rendering_mode;
+        self
+    }
+
+    pub fn build(&self) -> RenderingParams {
+        unsafe {
+            let mut native: ComPtr<IDWriteRenderingParams> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateCustomRenderingParams(self.gamma,
+                                                                   self.enhanced_contrast,
+                                                                   self.cleartype_level,
+                                                                   self.pixel_geometry,
+                                                                   self.rendering_mode,
+                                                                   native.getter_addrefs());
+            assert!(hr == 0);
+            RenderingParams::take(native)
+        }
+    }
+}
+
+impl Default for RenderingParamsBuilder {
+    fn default() -> RenderingParamsBuilder {
+        RenderingParamsBuilder::new()
+    }
 }
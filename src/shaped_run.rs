@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::ops::Range;
+
+use winapi::um::dwrite::{DWRITE_SHAPING_GLYPH_PROPERTIES, DWRITE_SHAPING_TEXT_PROPERTIES};
+
+// The result of shaping a run of text with TextAnalyzer::get_glyphs.
+// Glyphs are in logical order, even for right-to-left runs.  The fields
+// are public, but TextAnalyzer expects them to stay the lengths shaping
+// left them (glyph_props one per glyph index, text_props one per entry
+// of cluster_map) and panics otherwise.
+#[derive(Clone)]
+pub struct ShapedRun {
+    pub glyph_indices: Vec<u16>,
+    // For each UTF-16 code unit of the text, the index of the first glyph
+    // of the cluster it belongs to.  Never decreases.
+    pub cluster_map: Vec<u16>,
+    // One per UTF-16 code unit; needed again to place the glyphs.
+    pub text_props: Vec<DWRITE_SHAPING_TEXT_PROPERTIES>,
+    pub glyph_props: Vec<DWRITE_SHAPING_GLYPH_PROPERTIES>,
+}
+
+impl ShapedRun {
+    pub fn glyph_count(&self) -> usize {
+        self.glyph_indices.len()
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.cluster_map.len()
+    }
+
+    // The glyphs making up the cluster that the UTF-16 code unit at
+    // `text_index` belongs to.
+    pub fn glyph_for_cluster(&self, text_index: usize) -> Range<usize> {
+        let start = self.cluster_map[text_index] as usize;
+        let end = self.cluster_map[text_index..].iter()
+            .map(|&glyph| glyph as usize)
+            .find(|&glyph| glyph > start)
+            .unwrap_or_else(|| self.glyph_count());
+        start..end
+    }
+
+    // The UTF-16 code units that produced the cluster containing `glyph`.
+    pub fn text_for_glyph(&self, glyph: usize) -> Range<usize> {
+        let cluster_start = self.cluster_map.iter()
+            .rev()
+            .map(|&first| first as usize)
+            .find(|&first| first <= glyph)
+            .unwrap_or(0);
+        let start = self.cluster_map.iter().position(|&first| first as usize == cluster_start).unwrap_or(0);
+        let end = self.cluster_map[start..].iter()
+            .position(|&first| first as usize != cluster_start)
+            .map(|offset| start + offset)
+            .unwrap_or_else(|| self.text_len());
+        start..end
+    }
+
+    pub fn is_cluster_start(&self, glyph: usize) -> bool {
+        self.glyph_props[glyph].isClusterStart() != 0
+    }
+
+    // Whether the glyph is a combining mark that attaches to the glyph
+    // before it.
+    pub fn is_diacritic(&self, glyph: usize) -> bool {
+        self.glyph_props[glyph].isDiacritic() != 0
+    }
+
+    pub fn is_zero_width_space(&self, glyph: usize) -> bool {
+        self.glyph_props[glyph].isZeroWidthSpace() != 0
+    }
+
+    // The glyph's DWRITE_SCRIPT_JUSTIFY class, saying how it may be
+    // stretched or where kashidas may go when justifying.
+    pub fn justification(&self, glyph: usize) -> u16 {
+        self.glyph_props[glyph].justification()
+    }
+
+    // Whether the run can be split before `glyph` without breaking up a
+    // cluster or separating a mark from its base.  This only protects
+    // clusters; whether a line break is allowed there is a separate
+    // question answered by line-break analysis.
+    pub fn can_break_before(&self, glyph: usize) -> bool {
+        glyph == 0 || glyph >= self.glyph_count() ||
+            (self.is_cluster_start(glyph) && !self.is_diacritic(glyph))
+    }
+}
@@ -0,0 +1,989 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Parsers for OpenType tables whose contents DirectWrite doesn't expose.
+// The raw table bytes come from FontFace::get_font_table.  Everything in
+// an OpenType table is big-endian, and a malformed or truncated table
+// makes the parser return None rather than panic.
+
+use std::char;
+use std::collections::HashMap;
+
+// Equivalent of DWRITE_MAKE_OPENTYPE_TAG, for use with
+// FontFace::get_font_table.
+pub fn make_opentype_tag(tag: &[u8; 4]) -> u32 {
+    (tag[0] as u32) | ((tag[1] as u32) << 8) | ((tag[2] as u32) << 16) | ((tag[3] as u32) << 24)
+}
+
+#[derive(Clone, Copy)]
+struct TableReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TableReader<'a> {
+    fn new(data: &'a [u8]) -> TableReader<'a> {
+        TableReader { data }
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        let end = offset.checked_add(len)?;
+        if end > self.data.len() {
+            return None;
+        }
+        Some(&self.data[offset..end])
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        self.bytes(offset, 2).map(|b| ((b[0] as u16) << 8) | b[1] as u16)
+    }
+
+    fn i16(&self, offset: usize) -> Option<i16> {
+        self.u16(offset).map(|v| v as i16)
+    }
+
+    fn tag(&self, offset: usize) -> Option<[u8; 4]> {
+        self.bytes(offset, 4).map(|b| [b[0], b[1], b[2], b[3]])
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        self.bytes(offset, 4).map(|b| {
+            ((b[0] as u32) << 24) | ((b[1] as u32) << 16) | ((b[2] as u32) << 8) | b[3] as u32
+        })
+    }
+
+    // A 16.16 fixed-point number.
+    fn fixed(&self, offset: usize) -> Option<f32> {
+        self.u32(offset).map(|v| v as i32 as f32 / 65536.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameRecord {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub language_id: u16,
+    pub name_id: u16,
+    // None if the record uses an encoding we can't decode.
+    pub name: Option<String>,
+}
+
+const PLATFORM_UNICODE: u16 = 0;
+const PLATFORM_MACINTOSH: u16 = 1;
+const PLATFORM_WINDOWS: u16 = 3;
+
+pub fn parse_name_table(table: &[u8]) -> Option<Vec<NameRecord>> {
+    let reader = TableReader::new(table);
+    let count = reader.u16(2)? as usize;
+    let storage_offset = reader.u16(4)? as usize;
+
+    let mut records = Vec::with_capacity(count);
+    for i in 0..count {
+        let record = 6 + i * 12;
+        let platform_id = reader.u16(record)?;
+        let encoding_id = reader.u16(record + 2)?;
+        let length = reader.u16(record + 8)? as usize;
+        let offset = reader.u16(record + 10)? as usize;
+
+        let name = reader.bytes(storage_offset + offset, length)
+                         .and_then(|bytes| decode_name(platform_id, encoding_id, bytes));
+        records.push(NameRecord {
+            platform_id,
+            encoding_id,
+            language_id: reader.u16(record + 4)?,
+            name_id: reader.u16(record + 6)?,
+            name,
+        });
+    }
+    Some(records)
+}
+
+pub const EN_US_LANGUAGE_ID: u16 = 0x0409;
+
+// Finds the string for a name ID, preferring Windows records in each of
+// the given language IDs in turn, then any Unicode record, then anything
+// we could decode.
+pub fn find_name(records: &[NameRecord], name_id: u16, language_ids: &[u16]) -> Option<String> {
+    let candidates = || records.iter().filter(move |r| r.name_id == name_id && r.name.is_some());
+    language_ids.iter()
+                .filter_map(|&language_id| {
+                    candidates().find(|r| r.platform_id == PLATFORM_WINDOWS && r.language_id == language_id)
+                })
+                .next()
+                .or_else(|| {
+                    candidates().find(|r| r.platform_id == PLATFORM_WINDOWS || r.platform_id == PLATFORM_UNICODE)
+                })
+                .or_else(|| candidates().next())
+                .and_then(|r| r.name.clone())
+}
+
+fn decode_name(platform_id: u16, encoding_id: u16, bytes: &[u8]) -> Option<String> {
+    match (platform_id, encoding_id) {
+        // Symbol, Unicode BMP and Unicode full repertoire on Windows, and
+        // everything on the Unicode platform, are UTF-16BE.
+        (PLATFORM_UNICODE, _) | (PLATFORM_WINDOWS, 0) | (PLATFORM_WINDOWS, 1) | (PLATFORM_WINDOWS, 10) => {
+            let units: Vec<u16> = bytes.chunks(2)
+                                       .filter(|c| c.len() == 2)
+                                       .map(|c| ((c[0] as u16) << 8) | c[1] as u16)
+                                       .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        (PLATFORM_MACINTOSH, 0) => Some(decode_mac_roman(bytes)),
+        _ => None,
+    }
+}
+
+fn decode_mac_roman(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| {
+        if b < 0x80 {
+            b as char
+        } else {
+            char::from_u32(MAC_ROMAN_HIGH[(b - 0x80) as usize] as u32).unwrap_or('\u{fffd}')
+        }
+    }).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerticalGlyphMetrics {
+    pub advance_height: u16,
+    pub top_side_bearing: i16,
+}
+
+// Looks up each glyph in 'vmtx', using 'vhea' for the number of full
+// metric records.  Glyphs past the last full record share its advance
+// and only have a top side bearing of their own.
+pub fn parse_vertical_metrics(vhea: &[u8], vmtx: &[u8], glyph_indices: &[u16])
+                              -> Option<Vec<VerticalGlyphMetrics>> {
+    let num_long_metrics = TableReader::new(vhea).u16(34)? as usize;
+    if num_long_metrics == 0 {
+        return None;
+    }
+
+    let vmtx = TableReader::new(vmtx);
+    let last_advance = vmtx.u16((num_long_metrics - 1) * 4)?;
+    glyph_indices.iter().map(|&glyph| {
+        let glyph = glyph as usize;
+        if glyph < num_long_metrics {
+            Some(VerticalGlyphMetrics {
+                advance_height: vmtx.u16(glyph * 4)?,
+                top_side_bearing: vmtx.i16(glyph * 4 + 2)?,
+            })
+        } else {
+            Some(VerticalGlyphMetrics {
+                advance_height: last_advance,
+                top_side_bearing: vmtx.i16(num_long_metrics * 4 + (glyph - num_long_metrics) * 2)?,
+            })
+        }
+    }).collect()
+}
+
+// The glyph count from 'maxp'.
+pub fn parse_maxp_glyph_count(maxp: &[u8]) -> Option<u16> {
+    TableReader::new(maxp).u16(4)
+}
+
+// The glyph count of a 'CFF ' table, which it keeps apart from 'maxp': the
+// number of entries in the CharStrings INDEX named by the first font's
+// Top DICT.
+pub fn parse_cff_glyph_count(cff: &[u8]) -> Option<u16> {
+    let reader = TableReader::new(cff);
+    let header_size = reader.bytes(2, 1)?[0] as usize;
+    let top_dict_index = cff_index_end(reader, header_size)?;
+    let count = reader.u16(top_dict_index)? as usize;
+    if count == 0 {
+        return None;
+    }
+    let off_size = reader.bytes(top_dict_index + 2, 1)?[0] as usize;
+    let offsets = top_dict_index + 3;
+    let data = offsets + (count + 1) * off_size;
+    let start = cff_offset(reader, offsets, off_size)?;
+    let end = cff_offset(reader, offsets + off_size, off_size)?;
+    let top_dict = reader.bytes(data + start, end.checked_sub(start)?)?;
+
+    const CHAR_STRINGS: u8 = 17;
+    let char_strings = cff_dict_operand(top_dict, CHAR_STRINGS)?;
+    if char_strings <= 0 {
+        return None;
+    }
+    reader.u16(char_strings as usize)
+}
+
+// An INDEX offset; offsets are 1-based and 1 to 4 bytes long.
+fn cff_offset(reader: TableReader, position: usize, off_size: usize) -> Option<usize> {
+    if off_size == 0 || off_size > 4 {
+        return None;
+    }
+    let offset = reader.bytes(position, off_size)?.iter().fold(0, |offset, &b| offset << 8 | b as usize);
+    offset.checked_sub(1)
+}
+
+// Where the INDEX starting at `position` ends.
+fn cff_index_end(reader: TableReader, position: usize) -> Option<usize> {
+    let count = reader.u16(position)? as usize;
+    if count == 0 {
+        return Some(position + 2);
+    }
+    let off_size = reader.bytes(position + 2, 1)?[0] as usize;
+    let offsets = position + 3;
+    let data = offsets + (count + 1) * off_size;
+    Some(data + cff_offset(reader, offsets + count * off_size, off_size)?)
+}
+
+// The last operand before the first use of a one-byte `operator` in a
+// DICT, if it's an integer.
+fn cff_dict_operand(dict: &[u8], operator: u8) -> Option<i32> {
+    let reader = TableReader::new(dict);
+    let mut operand = None;
+    let mut position = 0;
+    while position < dict.len() {
+        let b0 = dict[position];
+        match b0 {
+            12 => {
+                operand = None;
+                position += 2;
+            }
+            0..=21 => {
+                if b0 == operator {
+                    return operand;
+                }
+                operand = None;
+                position += 1;
+            }
+            28 => {
+                operand = Some(reader.i16(position + 1)? as i32);
+                position += 3;
+            }
+            29 => {
+                operand = Some(reader.u32(position + 1)? as i32);
+                position += 5;
+            }
+            // A real number, in nibbles up to one of 0xf.
+            30 => {
+                operand = None;
+                position += 1;
+                loop {
+                    let b = reader.bytes(position, 1)?[0];
+                    position += 1;
+                    if b >> 4 == 0xf || b & 0xf == 0xf {
+                        break;
+                    }
+                }
+            }
+            32..=246 => {
+                operand = Some(b0 as i32 - 139);
+                position += 1;
+            }
+            247..=250 => {
+                operand = Some((b0 as i32 - 247) * 256 + reader.bytes(position + 1, 1)?[0] as i32 + 108);
+                position += 2;
+            }
+            251..=254 => {
+                operand = Some(-(b0 as i32 - 251) * 256 - reader.bytes(position + 1, 1)?[0] as i32 - 108);
+                position += 2;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+// The number of full (advance, side bearing) records in 'hmtx', from
+// 'hhea'.
+pub fn parse_hhea_long_metrics_count(hhea: &[u8]) -> Option<u16> {
+    TableReader::new(hhea).u16(34)
+}
+
+// The 'hhea' ascender, descender (negative below the baseline) and line
+// gap, in design units.
+pub fn parse_hhea_line_metrics(hhea: &[u8]) -> Option<(i16, i16, i16)> {
+    let reader = TableReader::new(hhea);
+    Some((reader.i16(4)?, reader.i16(6)?, reader.i16(8)?))
+}
+
+// The advance width of every glyph from 'hmtx', using 'hhea' for the
+// number of full metric records.  Glyphs past the last full record
+// (typically a run of monospaced glyphs at the end of the font) share its
+// advance.
+pub fn parse_horizontal_advances(hhea: &[u8], hmtx: &[u8], glyph_count: u16) -> Option<Vec<u16>> {
+    let num_long_metrics = parse_hhea_long_metrics_count(hhea)? as usize;
+    if num_long_metrics == 0 {
+        return None;
+    }
+
+    let hmtx = TableReader::new(hmtx);
+    let num_long_metrics = num_long_metrics.min(glyph_count as usize);
+    let mut advances = (0..num_long_metrics).map(|glyph| hmtx.u16(glyph * 4))
+                                            .collect::<Option<Vec<u16>>>()?;
+    let last_advance = advances.last().cloned().unwrap_or(0);
+    advances.resize(glyph_count as usize, last_advance);
+    Some(advances)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Os2Metrics {
+    pub version: u16,
+    pub weight_class: u16,
+    pub width_class: u16,
+    pub fs_selection: u16,
+    pub unicode_range: [u32; 4],
+    // The foundry's four-character vendor tag, e.g. *b"MS  ".
+    pub vendor_id: [u8; 4],
+    pub typo_ascender: i16,
+    pub typo_descender: i16,
+    pub typo_line_gap: i16,
+    pub win_ascent: u16,
+    pub win_descent: u16,
+    // Only present in version 1 and later.
+    pub code_page_range: Option<[u32; 2]>,
+    // Only present in version 2 and later.
+    pub x_height: Option<i16>,
+    pub cap_height: Option<i16>,
+}
+
+// fsSelection bit 7: line spacing should come from the typo metrics
+// rather than the win metrics.
+const FS_SELECTION_USE_TYPO_METRICS: u16 = 1 << 7;
+
+impl Os2Metrics {
+    pub fn use_typo_metrics(&self) -> bool {
+        self.fs_selection & FS_SELECTION_USE_TYPO_METRICS != 0
+    }
+}
+
+// The OS/2 table has grown with each version; version 0 is 78 bytes,
+// version 1 is 86, versions 2-4 are 96 and version 5 is 100.  Fields from
+// later versions are only read if both the version and the table length
+// say they're there.
+pub fn parse_os2_table(table: &[u8]) -> Option<Os2Metrics> {
+    let reader = TableReader::new(table);
+    let version = reader.u16(0)?;
+
+    let code_page_range = if version >= 1 {
+        reader.u32(78).and_then(|first| reader.u32(82).map(|second| [first, second]))
+    } else {
+        None
+    };
+    let (x_height, cap_height) = if version >= 2 {
+        (reader.i16(86), reader.i16(88))
+    } else {
+        (None, None)
+    };
+
+    Some(Os2Metrics {
+        version,
+        weight_class: reader.u16(4)?,
+        width_class: reader.u16(6)?,
+        fs_selection: reader.u16(62)?,
+        unicode_range: [reader.u32(42)?, reader.u32(46)?, reader.u32(50)?, reader.u32(54)?],
+        vendor_id: reader.tag(58)?,
+        typo_ascender: reader.i16(68)?,
+        typo_descender: reader.i16(70)?,
+        typo_line_gap: reader.i16(72)?,
+        win_ascent: reader.u16(74)?,
+        win_descent: reader.u16(76)?,
+        code_page_range,
+        x_height,
+        cap_height,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadTable {
+    // 16.16 fixed point, as written in the font.
+    pub font_revision: f32,
+    pub flags: u16,
+    pub units_per_em: u16,
+    // The union of all glyph bounding boxes, in font design units.
+    pub x_min: i16,
+    pub y_min: i16,
+    pub x_max: i16,
+    pub y_max: i16,
+    pub mac_style: u16,
+    pub lowest_rec_ppem: u16,
+    // 0 if 'loca' has 16-bit offsets, 1 if it has 32-bit ones.
+    pub index_to_loc_format: i16,
+}
+
+const HEAD_MAGIC_NUMBER: u32 = 0x5f0f3cf5;
+
+// macStyle bits.
+pub const HEAD_MAC_STYLE_BOLD: u16 = 1 << 0;
+pub const HEAD_MAC_STYLE_ITALIC: u16 = 1 << 1;
+
+impl HeadTable {
+    pub fn is_bold(&self) -> bool {
+        self.mac_style & HEAD_MAC_STYLE_BOLD != 0
+    }
+
+    pub fn is_italic(&self) -> bool {
+        self.mac_style & HEAD_MAC_STYLE_ITALIC != 0
+    }
+}
+
+// Parses a 'head' table, or a 'bhed' table from a bitmap-only font, which
+// has the same layout.  The magic number is checked to reject garbage.
+pub fn parse_head_table(table: &[u8]) -> Option<HeadTable> {
+    let reader = TableReader::new(table);
+    if reader.u16(0)? != 1 || reader.u32(12)? != HEAD_MAGIC_NUMBER {
+        return None;
+    }
+
+    Some(HeadTable {
+        font_revision: reader.fixed(4)?,
+        flags: reader.u16(16)?,
+        units_per_em: reader.u16(18)?,
+        x_min: reader.i16(36)?,
+        y_min: reader.i16(38)?,
+        x_max: reader.i16(40)?,
+        y_max: reader.i16(42)?,
+        mac_style: reader.u16(44)?,
+        lowest_rec_ppem: reader.u16(46)?,
+        index_to_loc_format: reader.i16(50)?,
+    })
+}
+
+// The script tags in a GSUB or GPOS ScriptList, sorted and deduplicated.
+pub fn parse_layout_script_tags(table: &[u8]) -> Option<Vec<[u8; 4]>> {
+    let reader = TableReader::new(table);
+    let script_list = reader.u16(4)? as usize;
+    let count = reader.u16(script_list)? as usize;
+
+    let mut tags = Vec::with_capacity(count);
+    for i in 0..count {
+        tags.push(reader.tag(script_list + 2 + i * 6)?);
+    }
+    tags.sort();
+    tags.dedup();
+    Some(tags)
+}
+
+// The tags of a GSUB or GPOS FeatureList, sorted and deduplicated (the
+// list has one record per feature per script/language system, so tags
+// repeat).
+pub fn parse_layout_feature_tags(table: &[u8]) -> Option<Vec<[u8; 4]>> {
+    let reader = TableReader::new(table);
+    let feature_list = reader.u16(6)? as usize;
+    let count = reader.u16(feature_list)? as usize;
+
+    let mut tags = Vec::with_capacity(count);
+    for i in 0..count {
+        tags.push(reader.tag(feature_list + 2 + i * 6)?);
+    }
+    tags.sort();
+    tags.dedup();
+    Some(tags)
+}
+
+// The stylistic sets ('ss01' to 'ss20') in a GSUB FeatureList, as (set
+// number, UI name ID) pairs sorted by set number.  The name ID comes from
+// the feature's FeatureParams and is None if the font doesn't give one.
+pub fn parse_stylistic_sets(gsub: &[u8]) -> Option<Vec<(u8, Option<u16>)>> {
+    let reader = TableReader::new(gsub);
+    let feature_list = reader.u16(6)? as usize;
+    let count = reader.u16(feature_list)? as usize;
+
+    let mut sets: Vec<(u8, Option<u16>)> = vec![];
+    for i in 0..count {
+        let record = feature_list + 2 + i * 6;
+        let tag = reader.tag(record)?;
+        if &tag[..2] != b"ss" || !tag[2].is_ascii_digit() || !tag[3].is_ascii_digit() {
+            continue;
+        }
+        let number = (tag[2] - b'0') * 10 + (tag[3] - b'0');
+        if !(1..=20).contains(&number) {
+            continue;
+        }
+
+        // FeatureParams for stylistic sets are a version (0) followed by
+        // the UI name ID.
+        let feature = feature_list + reader.u16(record + 4)? as usize;
+        let params_offset = reader.u16(feature)? as usize;
+        let name_id = if params_offset != 0 {
+            reader.u16(feature + params_offset + 2)
+        } else {
+            None
+        };
+
+        // The same feature tag appears once per script/language system; keep
+        // whichever record has a name.
+        match sets.iter_mut().find(|set| set.0 == number) {
+            Some(set) => set.1 = set.1.or(name_id),
+            None => sets.push((number, name_id)),
+        }
+    }
+    sets.sort();
+    Some(sets)
+}
+
+// fvar axis flags: the axis shouldn't be shown in a user interface.
+pub const FVAR_AXIS_HIDDEN: u16 = 0x0001;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FvarAxis {
+    pub tag: [u8; 4],
+    pub min_value: f32,
+    pub default_value: f32,
+    pub max_value: f32,
+    pub flags: u16,
+    pub name_id: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FvarInstance {
+    pub subfamily_name_id: u16,
+    pub postscript_name_id: Option<u16>,
+    // One value per axis, in the same order as the axes.
+    pub coordinates: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FvarTable {
+    pub axes: Vec<FvarAxis>,
+    pub instances: Vec<FvarInstance>,
+}
+
+pub fn parse_fvar_table(table: &[u8]) -> Option<FvarTable> {
+    let reader = TableReader::new(table);
+    let axes_offset = reader.u16(4)? as usize;
+    let axis_count = reader.u16(8)? as usize;
+    let axis_size = reader.u16(10)? as usize;
+    let instance_count = reader.u16(12)? as usize;
+    let instance_size = reader.u16(14)? as usize;
+
+    let mut axes = Vec::with_capacity(axis_count);
+    for i in 0..axis_count {
+        let axis = axes_offset + i * axis_size;
+        axes.push(FvarAxis {
+            tag: reader.tag(axis)?,
+            min_value: reader.fixed(axis + 4)?,
+            default_value: reader.fixed(axis + 8)?,
+            max_value: reader.fixed(axis + 12)?,
+            flags: reader.u16(axis + 16)?,
+            name_id: reader.u16(axis + 18)?,
+        });
+    }
+
+    // Instances follow the axes.  The PostScript name ID is optional, and
+    // present only if the record size leaves room for it.
+    let instances_offset = axes_offset + axis_count * axis_size;
+    let has_postscript_name = instance_size >= axis_count * 4 + 6;
+    let mut instances = Vec::with_capacity(instance_count);
+    for i in 0..instance_count {
+        let instance = instances_offset + i * instance_size;
+        let mut coordinates = Vec::with_capacity(axis_count);
+        for axis in 0..axis_count {
+            coordinates.push(reader.fixed(instance + 4 + axis * 4)?);
+        }
+        instances.push(FvarInstance {
+            subfamily_name_id: reader.u16(instance)?,
+            postscript_name_id: if has_postscript_name {
+                Some(reader.u16(instance + 4 + axis_count * 4)?)
+            } else {
+                None
+            },
+            coordinates,
+        });
+    }
+
+    Some(FvarTable { axes, instances })
+}
+
+// STAT axis value flags.
+pub const STAT_OLDER_SIBLING_FONT_ATTRIBUTE: u16 = 0x0001;
+pub const STAT_ELIDABLE_AXIS_VALUE_NAME: u16 = 0x0002;
+
+// A named value, or range of values, on a STAT design axis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatAxisValue {
+    pub name_id: u16,
+    // STAT_* flags.
+    pub flags: u16,
+    pub value: f32,
+    // The (min, max) range the name applies to, for format 2 records.
+    pub range: Option<(f32, f32)>,
+    // The bold or regular counterpart of this value, for format 3
+    // records, e.g. 700 for a weight of 400.
+    pub linked_value: Option<f32>,
+}
+
+impl StatAxisValue {
+    // Whether the name can be left out when building a face name, as
+    // "Regular" usually is.
+    pub fn is_elidable(&self) -> bool {
+        self.flags & STAT_ELIDABLE_AXIS_VALUE_NAME != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatAxis {
+    pub tag: [u8; 4],
+    pub name_id: u16,
+    // Where the axis's value names go when building a face name, lowest
+    // first.
+    pub ordering: u16,
+    pub values: Vec<StatAxisValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatTable {
+    pub axes: Vec<StatAxis>,
+    // The name to use when every axis value name is elided; only in
+    // version 1.1 and later.
+    pub elided_fallback_name_id: Option<u16>,
+}
+
+// Format 4 axis values, which name a combination of values on several
+// axes at once, are skipped.
+pub fn parse_stat_table(table: &[u8]) -> Option<StatTable> {
+    let reader = TableReader::new(table);
+    let minor_version = reader.u16(2)?;
+    let axis_size = reader.u16(4)? as usize;
+    let axis_count = reader.u16(6)? as usize;
+    let axes_offset = reader.u32(8)? as usize;
+    let value_count = reader.u16(12)? as usize;
+    let values_offset = reader.u32(14)? as usize;
+    let elided_fallback_name_id = if minor_version >= 1 {
+        Some(reader.u16(18)?)
+    } else {
+        None
+    };
+
+    let mut axes = Vec::with_capacity(axis_count);
+    for i in 0..axis_count {
+        let axis = axes_offset + i * axis_size;
+        axes.push(StatAxis {
+            tag: reader.tag(axis)?,
+            name_id: reader.u16(axis + 4)?,
+            ordering: reader.u16(axis + 6)?,
+            values: vec![],
+        });
+    }
+
+    for i in 0..value_count {
+        let value = values_offset + reader.u16(values_offset + i * 2)? as usize;
+        let format = reader.u16(value)?;
+        if !(1..=3).contains(&format) {
+            continue;
+        }
+        let axis_index = reader.u16(value + 2)? as usize;
+        let mut record = StatAxisValue {
+            flags: reader.u16(value + 4)?,
+            name_id: reader.u16(value + 6)?,
+            value: reader.fixed(value + 8)?,
+            range: None,
+            linked_value: None,
+        };
+        match format {
+            2 => record.range = Some((reader.fixed(value + 12)?, reader.fixed(value + 16)?)),
+            3 => record.linked_value = Some(reader.fixed(value + 12)?),
+            _ => {}
+        }
+        if let Some(axis) = axes.get_mut(axis_index) {
+            axis.values.push(record);
+        }
+    }
+
+    Some(StatTable { axes, elided_fallback_name_id })
+}
+
+// CPAL v1 palette type flags.
+pub const CPAL_PALETTE_USABLE_WITH_LIGHT_BACKGROUND: u32 = 1 << 0;
+pub const CPAL_PALETTE_USABLE_WITH_DARK_BACKGROUND: u32 = 1 << 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorPalettes {
+    // One entry per palette, each with the same number of (r, g, b, a)
+    // colors.  Palette 0 is the default.
+    pub palettes: Vec<Vec<(u8, u8, u8, u8)>>,
+    // CPAL_PALETTE_* flags for each palette, if the table is version 1 and
+    // has a palette types array; empty otherwise.
+    pub palette_types: Vec<u32>,
+    // 'name' table IDs of each palette's label, None for palettes without
+    // one, if the table is version 1 and has a palette labels array; empty
+    // otherwise.
+    pub palette_labels: Vec<Option<u16>>,
+}
+
+pub fn parse_cpal_table(table: &[u8]) -> Option<ColorPalettes> {
+    let reader = TableReader::new(table);
+    let version = reader.u16(0)?;
+    let entry_count = reader.u16(2)? as usize;
+    let palette_count = reader.u16(4)? as usize;
+    let color_records = reader.u32(8)? as usize;
+
+    // Palettes are windows into a shared array of BGRA color records, and
+    // may overlap.
+    let mut palettes = Vec::with_capacity(palette_count);
+    for i in 0..palette_count {
+        let first_record = reader.u16(12 + i * 2)? as usize;
+        let mut palette = Vec::with_capacity(entry_count);
+        for entry in 0..entry_count {
+            let bgra = reader.bytes(color_records + (first_record + entry) * 4, 4)?;
+            palette.push((bgra[2], bgra[1], bgra[0], bgra[3]));
+        }
+        palettes.push(palette);
+    }
+
+    let mut palette_types = vec![];
+    let mut palette_labels = vec![];
+    if version >= 1 {
+        let palette_types_offset = reader.u32(12 + palette_count * 2)? as usize;
+        if palette_types_offset != 0 {
+            for i in 0..palette_count {
+                palette_types.push(reader.u32(palette_types_offset + i * 4)?);
+            }
+        }
+        let palette_labels_offset = reader.u32(16 + palette_count * 2)? as usize;
+        if palette_labels_offset != 0 {
+            for i in 0..palette_count {
+                let name_id = reader.u16(palette_labels_offset + i * 2)?;
+                palette_labels.push(if name_id == 0xffff { None } else { Some(name_id) });
+            }
+        }
+    }
+
+    Some(ColorPalettes { palettes, palette_types, palette_labels })
+}
+
+// Unicode values for Mac Roman bytes 0x80-0xff.
+const MAC_ROMAN_HIGH: [u16; 128] = [
+    0x00C4, 0x00C5, 0x00C7, 0x00C9, 0x00D1, 0x00D6, 0x00DC, 0x00E1,
+    0x00E0, 0x00E2, 0x00E4, 0x00E3, 0x00E5, 0x00E7, 0x00E9, 0x00E8,
+    0x00EA, 0x00EB, 0x00ED, 0x00EC, 0x00EE, 0x00EF, 0x00F1, 0x00F3,
+    0x00F2, 0x00F4, 0x00F6, 0x00F5, 0x00FA, 0x00F9, 0x00FB, 0x00FC,
+    0x2020, 0x00B0, 0x00A2, 0x00A3, 0x00A7, 0x2022, 0x00B6, 0x00DF,
+    0x00AE, 0x00A9, 0x2122, 0x00B4, 0x00A8, 0x2260, 0x00C6, 0x00D8,
+    0x221E, 0x00B1, 0x2264, 0x2265, 0x00A5, 0x00B5, 0x2202, 0x2211,
+    0x220F, 0x03C0, 0x222B, 0x00AA, 0x00BA, 0x03A9, 0x00E6, 0x00F8,
+    0x00BF, 0x00A1, 0x00AC, 0x221A, 0x0192, 0x2248, 0x2206, 0x00AB,
+    0x00BB, 0x2026, 0x00A0, 0x00C0, 0x00C3, 0x00D5, 0x0152, 0x0153,
+    0x2013, 0x2014, 0x201C, 0x201D, 0x2018, 0x2019, 0x00F7, 0x25CA,
+    0x00FF, 0x0178, 0x2044, 0x20AC, 0x2039, 0x203A, 0xFB01, 0xFB02,
+    0x2021, 0x00B7, 0x201A, 0x201E, 0x2030, 0x00C2, 0x00CA, 0x00C1,
+    0x00CB, 0x00C8, 0x00CD, 0x00CE, 0x00CF, 0x00CC, 0x00D3, 0x00D4,
+    0xF8FF, 0x00D2, 0x00DA, 0x00DB, 0x00D9, 0x0131, 0x02C6, 0x02DC,
+    0x00AF, 0x02D8, 0x02D9, 0x02DA, 0x00B8, 0x02DD, 0x02DB, 0x02C7,
+];
+
+// cmap subtables in order of preference: full-repertoire Unicode (format
+// 12) first, then BMP-only Unicode (format 4), then the Windows symbol
+// encoding, whose codes live in U+F000-U+F0FF.
+const CMAP_ENCODINGS: [(u16, u16, u16); 6] = [
+    (3, 10, 12),
+    (0, 4, 12),
+    (0, 6, 12),
+    (3, 1, 4),
+    (0, 3, 4),
+    (3, 0, 4),
+];
+
+// Reverses the best Unicode 'cmap' subtable: for each glyph reachable
+// from some code point, every code point that maps to it, in ascending
+// order.  .notdef (glyph 0) is left out.
+pub fn parse_cmap_glyph_to_unicode(table: &[u8]) -> Option<HashMap<u16, Vec<u32>>> {
+    let reader = TableReader::new(table);
+    let num_tables = reader.u16(2)? as usize;
+    let mut subtables = vec![];
+    for i in 0..num_tables {
+        let record = 4 + i * 8;
+        let platform_id = reader.u16(record)?;
+        let encoding_id = reader.u16(record + 2)?;
+        let offset = reader.u32(record + 4)? as usize;
+        let format = reader.u16(offset)?;
+        if let Some(rank) = CMAP_ENCODINGS.iter().position(|&encoding| encoding == (platform_id, encoding_id, format)) {
+            subtables.push((rank, offset, format));
+        }
+    }
+    subtables.sort();
+
+    // A truncated subtable is skipped as a whole in favor of the next best
+    // one, rather than contributing part of its mappings.
+    let mappings = subtables.iter().filter_map(|&(_, offset, format)| {
+        let subtable = TableReader::new(&table[offset..]);
+        if format == 12 {
+            parse_cmap_format_12(subtable)
+        } else {
+            parse_cmap_format_4(subtable)
+        }
+    }).next().unwrap_or_default();
+
+    let mut map: HashMap<u16, Vec<u32>> = HashMap::new();
+    for (code_point, glyph) in mappings {
+        if glyph != 0 {
+            map.entry(glyph).or_default().push(code_point);
+        }
+    }
+    for code_points in map.values_mut() {
+        code_points.sort();
+        code_points.dedup();
+    }
+    Some(map)
+}
+
+// A subtable can map each code point at most once, so one that expands to
+// more mappings than there are code points in its range is treated as
+// malformed rather than allowed to allocate without bound.
+const CMAP_FORMAT_4_MAX_MAPPINGS: usize = 0x10000;
+const CMAP_FORMAT_12_MAX_MAPPINGS: usize = 0x110000;
+
+// (code point, glyph) pairs.
+fn parse_cmap_format_4(subtable: TableReader) -> Option<Vec<(u32, u16)>> {
+    let seg_count = subtable.u16(6)? as usize / 2;
+    let end_codes = 14;
+    let start_codes = end_codes + seg_count * 2 + 2;
+    let id_deltas = start_codes + seg_count * 2;
+    let id_range_offsets = id_deltas + seg_count * 2;
+
+    let mut mappings = vec![];
+    for segment in 0..seg_count {
+        let end = subtable.u16(end_codes + segment * 2)?;
+        let start = subtable.u16(start_codes + segment * 2)?;
+        let delta = subtable.u16(id_deltas + segment * 2)?;
+        let range_offset_position = id_range_offsets + segment * 2;
+        let range_offset = subtable.u16(range_offset_position)? as usize;
+        if start > end {
+            continue;
+        }
+        if mappings.len() + (end - start) as usize >= CMAP_FORMAT_4_MAX_MAPPINGS {
+            return None;
+        }
+        for code in start..=end {
+            if code == 0xffff {
+                break;
+            }
+            let glyph = if range_offset == 0 {
+                code.wrapping_add(delta)
+            } else {
+                let position = range_offset_position + range_offset + (code - start) as usize * 2;
+                match subtable.u16(position)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                }
+            };
+            mappings.push((code as u32, glyph));
+        }
+    }
+    Some(mappings)
+}
+
+fn parse_cmap_format_12(subtable: TableReader) -> Option<Vec<(u32, u16)>> {
+    let num_groups = subtable.u32(12)? as usize;
+    let mut mappings = vec![];
+    for group in 0..num_groups {
+        let record = 16 + group * 12;
+        let start = subtable.u32(record)?;
+        let end = subtable.u32(record + 4)?.min(0x10ffff);
+        let start_glyph = subtable.u32(record + 8)?;
+        if start > end {
+            continue;
+        }
+        if mappings.len() + (end - start) as usize >= CMAP_FORMAT_12_MAX_MAPPINGS {
+            return None;
+        }
+        for code_point in start..=end {
+            let glyph = start_glyph as u64 + (code_point - start) as u64;
+            if glyph > 0xffff {
+                break;
+            }
+            mappings.push((code_point, glyph as u16));
+        }
+    }
+    Some(mappings)
+}
+
+// The ppemY of each strike in an 'EBLC' or 'CBLC' table.  Both share the
+// same header and 48-byte BitmapSize records.
+pub fn parse_bitmap_location_sizes(table: &[u8]) -> Option<Vec<u32>> {
+    let reader = TableReader::new(table);
+    let num_sizes = reader.u32(4)? as usize;
+    let mut sizes = Vec::with_capacity(num_sizes.min(table.len() / 48));
+    for i in 0..num_sizes {
+        let ppem_y = reader.bytes(8 + i * 48 + 45, 1)?[0];
+        sizes.push(ppem_y as u32);
+    }
+    Some(sizes)
+}
+
+// The ppem of each strike in an 'sbix' table.
+pub fn parse_sbix_sizes(table: &[u8]) -> Option<Vec<u32>> {
+    let reader = TableReader::new(table);
+    let num_strikes = reader.u32(4)? as usize;
+    let mut sizes = Vec::with_capacity(num_strikes.min(table.len() / 4));
+    for i in 0..num_strikes {
+        let strike = reader.u32(8 + i * 4)? as usize;
+        sizes.push(reader.u16(strike)? as u32);
+    }
+    Some(sizes)
+}
+
+// The standard Macintosh glyph order, which 'post' formats 1 and 2 refer
+// to by index.
+const MAC_GLYPH_NAMES: [&str; 258] = [
+    ".notdef", ".null", "nonmarkingreturn", "space", "exclam", "quotedbl", "numbersign", "dollar",
+    "percent", "ampersand", "quotesingle", "parenleft", "parenright", "asterisk", "plus", "comma",
+    "hyphen", "period", "slash", "zero", "one", "two", "three", "four", "five", "six", "seven",
+    "eight", "nine", "colon", "semicolon", "less", "equal", "greater", "question", "at", "A", "B",
+    "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U",
+    "V", "W", "X", "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum",
+    "underscore", "grave", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n",
+    "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "braceleft", "bar", "braceright",
+    "asciitilde", "Adieresis", "Aring", "Ccedilla", "Eacute", "Ntilde", "Odieresis", "Udieresis",
+    "aacute", "agrave", "acircumflex", "adieresis", "atilde", "aring", "ccedilla", "eacute",
+    "egrave", "ecircumflex", "edieresis", "iacute", "igrave", "icircumflex", "idieresis", "ntilde",
+    "oacute", "ograve", "ocircumflex", "odieresis", "otilde", "uacute", "ugrave", "ucircumflex",
+    "udieresis", "dagger", "degree", "cent", "sterling", "section", "bullet", "paragraph",
+    "germandbls", "registered", "copyright", "trademark", "acute", "dieresis", "notequal", "AE",
+    "Oslash", "infinity", "plusminus", "lessequal", "greaterequal", "yen", "mu", "partialdiff",
+    "summation", "product", "pi", "integral", "ordfeminine", "ordmasculine", "Omega", "ae",
+    "oslash", "questiondown", "exclamdown", "logicalnot", "radical", "florin", "approxequal",
+    "Delta", "guillemotleft", "guillemotright", "ellipsis", "nonbreakingspace", "Agrave", "Atilde",
+    "Otilde", "OE", "oe", "endash", "emdash", "quotedblleft", "quotedblright", "quoteleft",
+    "quoteright", "divide", "lozenge", "ydieresis", "Ydieresis", "fraction", "currency",
+    "guilsinglleft", "guilsinglright", "fi", "fl", "daggerdbl", "periodcentered", "quotesinglbase",
+    "quotedblbase", "perthousand", "Acircumflex", "Ecircumflex", "Aacute", "Edieresis", "Egrave",
+    "Iacute", "Icircumflex", "Idieresis", "Igrave", "Oacute", "Ocircumflex", "apple", "Ograve",
+    "Uacute", "Ucircumflex", "Ugrave", "dotlessi", "circumflex", "tilde", "macron", "breve",
+    "dotaccent", "ring", "cedilla", "hungarumlaut", "ogonek", "caron", "Lslash", "lslash", "Scaron",
+    "scaron", "Zcaron", "zcaron", "brokenbar", "Eth", "eth", "Yacute", "yacute", "Thorn", "thorn",
+    "minus", "multiply", "onesuperior", "twosuperior", "threesuperior", "onehalf", "onequarter",
+    "threequarters", "franc", "Gbreve", "gbreve", "Idotaccent", "Scedilla", "scedilla", "Cacute",
+    "cacute", "Ccaron", "ccaron", "dcroat",
+];
+
+// The name of every glyph from a format 1 or 2 'post' table, indexed by
+// glyph ID.  None for format 3, which has no names, and for the
+// deprecated format 2.5.
+pub fn parse_post_glyph_names(post: &[u8], glyph_count: u16) -> Option<Vec<String>> {
+    let reader = TableReader::new(post);
+    match reader.u32(0)? {
+        0x00010000 => {
+            Some(MAC_GLYPH_NAMES.iter().take(glyph_count as usize).map(|&name| name.to_owned()).collect())
+        }
+        0x00020000 => {
+            let num_glyphs = reader.u16(32)? as usize;
+            let indices = (0..num_glyphs).map(|glyph| reader.u16(34 + glyph * 2))
+                                         .collect::<Option<Vec<u16>>>()?;
+
+            // Names past the standard set are Pascal strings, in order.
+            let mut custom_names = vec![];
+            let mut offset = 34 + num_glyphs * 2;
+            let custom_count = indices.iter().map(|&index| index.saturating_sub(257)).max().unwrap_or(0) as usize;
+            while custom_names.len() < custom_count {
+                let length = reader.bytes(offset, 1)?[0] as usize;
+                let name = reader.bytes(offset + 1, length)?;
+                custom_names.push(String::from_utf8_lossy(name).into_owned());
+                offset += 1 + length;
+            }
+
+            indices.iter().map(|&index| {
+                let index = index as usize;
+                if index < 258 {
+                    Some(MAC_GLYPH_NAMES[index].to_owned())
+                } else {
+                    custom_names.get(index - 258).cloned()
+                }
+            }).collect()
+        }
+        _ => None,
+    }
+}
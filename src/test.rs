@@ -12,6 +12,28 @@ fn test_system_family_iter() {
     assert!(system_fc.families_iter().find(|f| f.name() == "Arial").is_some());
 }
 
+#[test]
+fn test_system_locale_name() {
+    let locale = system_locale_name();
+    assert!(!locale.is_empty());
+    assert!(!locale.contains('\0'));
+}
+
+#[test]
+fn test_max_supported_factory_version() {
+    let version = max_supported_factory_version();
+    assert!(version <= 7);
+    assert_eq!(version >= 2, FontFallback::system().is_some());
+    assert_eq!(version >= 3, FontSet::system().is_ok());
+}
+
+#[test]
+fn test_system_font_set_generation_is_stable() {
+    let first = FontCollection::system_font_set_generation();
+    let second = FontCollection::system_font_set_generation();
+    assert_eq!(first, second);
+}
+
 #[test]
 fn test_descriptor_round_trip() {
     let system_fc = FontCollection::system();
@@ -118,3 +140,1970 @@ fn test_glyph_image() {
     let bytes = rt.get_opaque_values_as_mask();
     println!("bytes length: {}", bytes.len());
 }
+
+#[test]
+fn test_blit_to_hdc() {
+    let system_fc = FontCollection::system();
+    let face = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal)
+        .create_font_face();
+    let glyph = face.get_glyph_indices(&['A' as u32])[0];
+
+    // Another target's memory DC stands in for an app's GDI surface.
+    let gdi_interop = GdiInterop::create();
+    let surface = gdi_interop.create_bitmap_render_target(64, 64);
+    let text = unsafe { gdi_interop.create_bitmap_render_target_for_hdc(surface.get_memory_dc(), 32, 32) };
+    assert_eq!(text.get_size(), (32, 32));
+
+    let rp = RenderingParams::create_for_primary_monitor();
+    unsafe { text.copy_from_hdc(surface.get_memory_dc(), 16, 16).unwrap() };
+    text.draw_glyph_run(4., 24., DWRITE_MEASURING_MODE_NATURAL, &face, 20., &[glyph], &[0.],
+                        &[GlyphOffset { advanceOffset: 0., ascenderOffset: 0. }], &rp, &(1., 1., 1.));
+    assert!(surface.get_opaque_values_as_mask().iter().skip(3).step_by(4).all(|&alpha| alpha == 0));
+    unsafe { text.blit_to_hdc(surface.get_memory_dc(), 16, 16).unwrap() };
+    assert!(surface.get_opaque_values_as_mask().iter().skip(3).step_by(4).any(|&alpha| alpha != 0));
+}
+
+#[test]
+fn test_name_records() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let records = face.get_name_records();
+    assert!(records.iter().any(|r| {
+        r.platform_id == 3 && r.name_id == 1 && r.name.as_ref().map(|n| &n[..]) == Some("Arial")
+    }));
+}
+
+#[test]
+fn test_os2_metrics() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let os2 = face.get_os2_metrics().unwrap();
+    assert_eq!(os2.weight_class, 400);
+    assert_eq!(os2.width_class, 5);
+    assert!(os2.typo_ascender > 0);
+    assert!(os2.typo_descender < 0);
+    assert!(os2.code_page_range.is_some());
+}
+
+#[test]
+fn test_head_table() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Bold,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let head = face.get_head().unwrap();
+    assert_eq!(head.units_per_em, face.metrics().designUnitsPerEm);
+    assert!(head.x_min < head.x_max && head.y_min < 0 && head.y_max > 0);
+    assert!(head.is_bold() && !head.is_italic());
+    assert!(head.index_to_loc_format == 0 || head.index_to_loc_format == 1);
+}
+
+#[test]
+fn test_map_codepoints() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    // U+E000 is in the private use area, which Arial doesn't cover.
+    let mapped = face.map_codepoints(&['A' as u32, 0xE000]);
+    assert_eq!(mapped[0], Some(face.get_glyph_indices(&['A' as u32])[0]));
+    assert_eq!(mapped[1], None);
+}
+
+#[test]
+fn test_system_async() {
+    let receiver = FontCollection::system_async(false);
+    let system_fc = receiver.recv().unwrap();
+    assert!(system_fc.get_font_family_by_name("Arial").is_some());
+}
+
+#[test]
+fn test_from_directory() {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    let dir = env::temp_dir().join("dwrote-test-from-directory");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let fonts_dir = env::var_os("WINDIR").map(|windir| Path::new(&windir).join("Fonts")).unwrap();
+    fs::copy(fonts_dir.join("arial.ttf"), dir.join("arial.ttf")).unwrap();
+    fs::write(dir.join("broken.ttf"), b"not a font").unwrap();
+    fs::write(dir.join("readme.txt"), b"not a font either").unwrap();
+
+    let (collection, skipped) = FontCollection::from_directory(&dir).unwrap();
+    assert_eq!(skipped, 1);
+    assert_eq!(collection.get_font_family_count(), 1);
+    assert!(collection.get_font_family_by_name("Arial").is_some());
+
+    drop(collection);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_has_feature() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    assert!(face.has_feature(*b"kern"));
+    assert!(!face.has_feature(*b"zzzz"));
+}
+
+#[test]
+fn test_recommended_rendering_mode_fallbacks() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let params = RenderingParams::create_for_primary_monitor();
+
+    for max_version in 0..4 {
+        let recommended = face.recommended_rendering_mode_up_to(max_version, 16., 1.,
+                                                                DWRITE_MEASURING_MODE_NATURAL,
+                                                                &params);
+        assert!(recommended.mode != DWRITE_RENDERING_MODE_DEFAULT);
+        if max_version < 2 {
+            assert_eq!(recommended.grid_fit, DWRITE_GRID_FIT_MODE_DEFAULT);
+        }
+    }
+}
+
+#[test]
+fn test_measure_string_advance() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let a = face.get_glyph_indices(&['A' as u32])[0];
+    let width = face.measure_string_advance("AA", 16.);
+    assert_eq!(width, face.glyph_advance_px(a, 16.) * 2.);
+    assert_eq!(face.measure_string_advance("", 16.), 0.);
+}
+
+#[test]
+fn test_init_thread() {
+    ::std::thread::spawn(|| {
+        let _guard = init_thread().unwrap();
+        let system_fc = FontCollection::system();
+        assert!(system_fc.get_font_family_count() > 0);
+    }).join().unwrap();
+}
+
+#[test]
+fn test_shelf_packer() {
+    let mut packer = ShelfPacker::new(20, 20);
+    assert_eq!(packer.allocate(9, 5), Some((0, 0)));
+    assert_eq!(packer.allocate(9, 3), Some((10, 0)));
+    // Doesn't fit across the first shelf, so starts a second one below it.
+    assert_eq!(packer.allocate(4, 4), Some((0, 6)));
+    assert_eq!(packer.allocate(30, 1), None);
+    assert_eq!(packer.allocate(4, 20), None);
+}
+
+#[test]
+fn test_glyph_atlas() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyphs = face.get_glyph_indices(&['A' as u32, 'g' as u32, ' ' as u32]);
+
+    let requests: Vec<_> = glyphs.iter().map(|&glyph| (&face, glyph, 24.)).collect();
+    let params = RenderingParams::create_for_primary_monitor();
+    let atlas = GlyphAtlas::build(&requests, 64, 64, AtlasFormat::Alpha, &params);
+    assert_eq!(atlas.data.len(), 64 * 64);
+
+    let a = atlas.glyphs[0].unwrap();
+    let g = atlas.glyphs[1].unwrap();
+    assert!(a.width > 0 && a.height > 0);
+    assert!(g.x >= a.x + a.width || g.y >= a.y + a.height ||
+            a.x >= g.x + g.width || a.y >= g.y + g.height);
+    assert_eq!(atlas.glyphs[2].unwrap().width, 0);
+    assert!(atlas.data.iter().any(|&coverage| coverage > 0));
+
+    // Large enough that the recommended mode is outline; the glyph still
+    // rasterizes, it just doesn't fit.
+    let atlas = GlyphAtlas::build(&[(&face, glyphs[0], 400.)], 64, 64, AtlasFormat::Alpha, &params);
+    assert!(atlas.glyphs[0].is_none());
+}
+
+#[test]
+fn test_pixel_geometry() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['W' as u32])[0];
+
+    let rgb = RenderingParamsBuilder::new().build();
+    assert_eq!(rgb.get_pixel_geometry(), DWRITE_PIXEL_GEOMETRY_RGB);
+    let bgr = RenderingParamsBuilder::new().pixel_geometry(DWRITE_PIXEL_GEOMETRY_BGR).build();
+    assert_eq!(bgr.get_pixel_geometry(), DWRITE_PIXEL_GEOMETRY_BGR);
+
+    let rasterize = |params: &RenderingParams| {
+        face.rasterize_glyph(glyph, 24., 1., DWRITE_RENDERING_MODE_NATURAL,
+                             DWRITE_MEASURING_MODE_NATURAL, params)
+    };
+    let rgb_glyph = rasterize(&rgb);
+    let bgr_glyph = rasterize(&bgr);
+    assert_eq!(rgb_glyph.texture_type, DWRITE_TEXTURE_CLEARTYPE_3x1);
+    assert_eq!(rgb_glyph.data.len(), bgr_glyph.data.len());
+    for (rgb_pixel, bgr_pixel) in rgb_glyph.data.chunks(3).zip(bgr_glyph.data.chunks(3)) {
+        assert_eq!(rgb_pixel, &[bgr_pixel[2], bgr_pixel[1], bgr_pixel[0]]);
+    }
+}
+
+#[test]
+fn test_alpha_blend_params() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['W' as u32])[0];
+
+    let params = RenderingParamsBuilder::new().gamma(1.8).enhanced_contrast(0.5).build();
+    assert!((params.get_gamma() - 1.8).abs() < 1e-6);
+    assert!((params.get_enhanced_contrast() - 0.5).abs() < 1e-6);
+
+    let rasterized = face.rasterize_glyph(glyph, 24., 1., DWRITE_RENDERING_MODE_NATURAL,
+                                          DWRITE_MEASURING_MODE_NATURAL, &params);
+    assert!(rasterized.alpha_blend.gamma > 0.);
+    assert!(rasterized.alpha_blend.enhanced_contrast >= 0.);
+}
+
+#[test]
+fn test_rasterize_glyph_with_grid_fit() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['W' as u32])[0];
+    let params = RenderingParamsBuilder::new().build();
+
+    let disabled = RecommendedRendering {
+        mode: DWRITE_RENDERING_MODE_NATURAL,
+        grid_fit: DWRITE_GRID_FIT_MODE_DISABLED,
+    };
+    let rasterized = face.rasterize_glyph_with_grid_fit(glyph, 24., 1., disabled,
+                                                        DWRITE_MEASURING_MODE_NATURAL, &params).unwrap();
+    assert!(rasterized.bounds.right > rasterized.bounds.left);
+    assert!(!rasterized.data.is_empty());
+
+    assert_eq!(face.rasterize_glyph_with_grid_fit(glyph, 24., 1., disabled,
+                                                  DWRITE_MEASURING_MODE_GDI_CLASSIC, &params).err(),
+               Some(winapi::shared::winerror::E_INVALIDARG));
+
+    let outline = RecommendedRendering {
+        mode: DWRITE_RENDERING_MODE_OUTLINE,
+        grid_fit: DWRITE_GRID_FIT_MODE_DEFAULT,
+    };
+    assert!(face.rasterize_glyph_with_grid_fit(glyph, 24., 1., outline,
+                                               DWRITE_MEASURING_MODE_NATURAL, &params).is_ok());
+
+    // Whatever is recommended, including at sizes big enough for
+    // downsampled or outline rendering, must be accepted.
+    for &em_size in &[9., 24., 150., 400.] {
+        for &pixels_per_dip in &[1., 2.] {
+            let recommended = face.recommended_rendering_mode(em_size, pixels_per_dip,
+                                                              DWRITE_MEASURING_MODE_NATURAL, &params);
+            assert!(face.rasterize_glyph_with_grid_fit(glyph, em_size, pixels_per_dip, recommended,
+                                                       DWRITE_MEASURING_MODE_NATURAL, &params).is_ok());
+        }
+    }
+}
+
+#[test]
+fn test_line_metrics() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let line = face.line_metrics(16.);
+    let scale = 16. / face.metrics().designUnitsPerEm as f32;
+    assert_eq!(line.ascent, face.metrics().ascent as f32 * scale);
+    assert!(line.descent > 0.);
+    assert!((line.line_height - (line.ascent + line.descent + line.line_gap)).abs() < 1e-3);
+}
+
+#[test]
+fn test_transformed_design_glyph_metrics() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let a = face.get_glyph_indices(&['A' as u32])[0];
+    let gm = face.get_design_glyph_metrics(&[a], false)[0];
+
+    let identity = DWRITE_MATRIX { m11: 1., m12: 0., m21: 0., m22: 1., dx: 0., dy: 0. };
+    let plain = face.get_transformed_design_glyph_metrics(&[a], false, &identity)[0];
+    assert_eq!(plain.advance, (gm.advanceWidth as f32, 0.));
+    assert_eq!(plain.ink_bounds.0, gm.leftSideBearing as f32);
+    assert_eq!(plain.ink_bounds.3, 0.);
+
+    // A synthetic-oblique style skew pushes the top of the glyph right.
+    let skew = DWRITE_MATRIX { m11: 1., m12: 0., m21: -0.25, m22: 1., dx: 0., dy: 0. };
+    let skewed = face.get_transformed_design_glyph_metrics(&[a], false, &skew)[0];
+    assert_eq!(skewed.advance, plain.advance);
+    assert_eq!(skewed.ink_bounds.1, plain.ink_bounds.1);
+    assert!(skewed.ink_bounds.2 > plain.ink_bounds.2);
+}
+
+#[test]
+fn test_font_list() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let fonts = arial_family.get_matching_fonts(FontWeight::Bold,
+                                                FontStretch::Normal,
+                                                FontStyle::Normal);
+    assert!(!fonts.is_empty());
+    assert_eq!(fonts.iter().count(), fonts.len());
+    assert_eq!(fonts.get_font(0).weight(), FontWeight::Bold);
+
+    let italic = fonts.find(FontWeight::Regular, FontStretch::Normal, FontStyle::Italic).unwrap();
+    assert_eq!(italic.style(), FontStyle::Italic);
+}
+
+#[test]
+fn test_glyph_run_description() {
+    let locale: Vec<u16> = "en-us\0".encode_utf16().collect();
+    let text: Vec<u16> = "affix".encode_utf16().collect();
+    // "ffi" shaped as a single ligature glyph.
+    let cluster_map = [0u16, 1, 1, 1, 2];
+    let raw = DWRITE_GLYPH_RUN_DESCRIPTION {
+        localeName: locale.as_ptr(),
+        string: text.as_ptr(),
+        stringLength: text.len() as u32,
+        clusterMap: cluster_map.as_ptr(),
+        textPosition: 7,
+    };
+
+    let description = unsafe { GlyphRunDescription::from_raw(&raw) };
+    assert_eq!(description.locale(), "en-us");
+    assert_eq!(description.text_string(), "affix");
+    assert_eq!(description.text_position, 7);
+    assert_eq!(description.text_range_for_glyph(0), Some(0..1));
+    assert_eq!(description.text_range_for_glyph(1), Some(1..4));
+    assert_eq!(description.text_range_for_glyph(2), Some(4..5));
+}
+
+#[test]
+fn test_family_names() {
+    let system_fc = FontCollection::system();
+    let names = system_fc.family_names(None);
+    assert!(names.iter().any(|name| name == "Arial"));
+    assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(system_fc.family_names(Some("en-us")).iter().any(|name| name == "Arial"));
+}
+
+#[test]
+fn test_typographic_families() {
+    let system_fc = FontCollection::system();
+    let families = system_fc.typographic_families(None);
+    assert!(families.iter().any(|name| name == "Arial"));
+    assert!(families.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_metrics_cache() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyphs = face.get_glyph_indices(&['h' as u32, 'e' as u32, 'l' as u32, 'l' as u32, 'o' as u32]);
+
+    let cache = face.metrics_cache();
+    assert!(cache.is_empty());
+    let cached = cache.get_many(&glyphs);
+    assert_eq!(cache.len(), 4);
+    let uncached = face.get_design_glyph_metrics(&glyphs, false);
+    for (cached, uncached) in cached.iter().zip(uncached.iter()) {
+        assert_eq!(cached.advanceWidth, uncached.advanceWidth);
+        assert_eq!(cached.leftSideBearing, uncached.leftSideBearing);
+    }
+    assert_eq!(cache.get(glyphs[0]).advanceWidth, uncached[0].advanceWidth);
+    assert_eq!(cache.len(), 4);
+}
+
+#[test]
+fn test_color_palettes() {
+    let system_fc = FontCollection::system();
+    let emoji_family = system_fc.get_font_family_by_name("Segoe UI Emoji").unwrap();
+    let emoji_font = emoji_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = emoji_font.create_font_face();
+
+    let palettes = face.get_color_palettes();
+    assert!(!palettes.is_empty());
+    assert_eq!(face.get_color_palette_count(), palettes.len());
+    assert!(!palettes[0].is_empty());
+    assert!(palettes.iter().all(|palette| palette.len() == palettes[0].len()));
+    assert_eq!(face.color_palette_labels(Some("en-us")).len(), palettes.len());
+
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert!(arial_font.create_font_face().get_color_palettes().is_empty());
+    assert!(arial_font.create_font_face().color_palette_labels(None).is_empty());
+}
+
+#[test]
+fn test_glyph_indices_for_str() {
+    let system_fc = FontCollection::system();
+    let emoji_family = system_fc.get_font_family_by_name("Segoe UI Emoji").unwrap();
+    let emoji_font = emoji_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = emoji_font.create_font_face();
+
+    let glyphs = face.get_glyph_indices_for_str("a\u{1F600}b");
+    assert_eq!(glyphs.len(), 3);
+    assert_eq!(glyphs[1], face.get_glyph_indices(&[0x1F600])[0]);
+    assert!(glyphs[1] != 0);
+}
+
+#[test]
+fn test_astral_characters_in_str_helpers() {
+    let system_fc = FontCollection::system();
+    // U+20000 is the first CJK Extension B ideograph.
+    let family = match system_fc.get_font_family_by_name("SimSun-ExtB") {
+        Some(family) => family,
+        None => return,
+    };
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let face = font.create_font_face();
+
+    let glyphs = face.get_glyph_indices_for_str("\u{20000}x\u{1F600}");
+    assert_eq!(glyphs.len(), 3);
+    assert_eq!(glyphs[0], face.get_glyph_indices(&[0x20000])[0]);
+    assert!(glyphs[0] != 0);
+    assert_eq!(glyphs[2], face.get_glyph_indices(&[0x1F600])[0]);
+
+    let one = face.measure_string_advance("\u{20000}", 16.);
+    assert!(one > 0.);
+    assert_eq!(face.measure_string_advance("\u{20000}\u{20000}", 16.), one * 2.);
+
+    let arial = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    if let Some(fallback) = FontFallback::system() {
+        let text = "a\u{1F600}b\u{20000}c";
+        let runs = fallback.map_characters(text, &arial, Some("en-us")).unwrap();
+        assert_eq!(runs.first().unwrap().text.start, 0);
+        assert_eq!(runs.last().unwrap().text.end, text.len());
+        for run in &runs {
+            assert!(!run.text.is_empty());
+            assert!(text.is_char_boundary(run.text.start) && text.is_char_boundary(run.text.end));
+        }
+        assert!(measure_string_with_fallback(text, &arial, 16.) > 0.);
+    }
+}
+
+#[test]
+fn test_has_glyph() {
+    let system_fc = FontCollection::system();
+    let face = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal)
+        .create_font_face();
+    assert!(face.has_glyph('A' as u32));
+    assert!(face.has_glyph(0xE9));
+    assert!(!face.has_glyph(0x1F600));
+    assert!(!face.has_glyph(0x110000));
+}
+
+#[test]
+fn test_ink_bounds_px() {
+    let metrics = GlyphMetrics {
+        leftSideBearing: 100,
+        advanceWidth: 1200,
+        rightSideBearing: 100,
+        topSideBearing: 200,
+        advanceHeight: 2400,
+        bottomSideBearing: 300,
+        verticalOriginY: 1900,
+    };
+    let (left, top, right, bottom) = metrics.ink_bounds_px(2000, 20.);
+    assert!((left - 1.).abs() < 1e-4);
+    assert!((right - 11.).abs() < 1e-4);
+    assert!((top - -17.).abs() < 1e-4);
+    assert!((bottom - 2.).abs() < 1e-4);
+
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['H' as u32])[0];
+    let metrics = face.get_design_glyph_metrics(&[glyph], false)[0];
+    let (left, top, right, bottom) = metrics.ink_bounds_px(face.metrics().designUnitsPerEm, 32.);
+    assert!(left >= 0. && right > left);
+    assert!(top < 0. && (bottom - 0.).abs() < 0.5);
+}
+
+#[test]
+fn test_shared_text_analyzer() {
+    let first = TextAnalyzer::shared();
+    let second = TextAnalyzer::shared();
+    unsafe {
+        assert_eq!(first.as_ptr(), second.as_ptr());
+    }
+}
+
+#[test]
+fn test_shaped_run() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let text: Vec<u16> = "Hello".encode_utf16().collect();
+    let options = ShapingOptions { locale: Some("en-us"), ..ShapingOptions::default() };
+    let run = TextAnalyzer::shared().get_glyphs(&text, &face, &options);
+    assert_eq!(run.glyph_count(), 5);
+    assert_eq!(run.text_len(), 5);
+    assert_eq!(run.glyph_indices, face.get_glyph_indices_for_str("Hello"));
+    assert_eq!(run.glyph_for_cluster(1), 1..2);
+    assert_eq!(run.text_for_glyph(4), 4..5);
+    assert!(run.is_cluster_start(2));
+    assert!(!run.is_diacritic(2));
+    assert!(run.can_break_before(2));
+}
+
+#[test]
+fn test_feature_settings() {
+    let mut features = FeatureSettings::new();
+    assert!(features.add(*b"liga", 0, 0..usize::MAX).is_ok());
+    assert!(features.add(*b"ss01", 1, 2..4).is_ok());
+    assert!(features.add(*b" abc", 1, 0..1).is_err());
+    assert!(features.add(*b"a bc", 1, 0..1).is_err());
+    assert!(features.add(*b"smcp", 1, 3..3).is_err());
+
+    let system_fc = FontCollection::system();
+    let family = system_fc.get_font_family_by_name("Calibri").unwrap();
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let face = font.create_font_face();
+
+    let text: Vec<u16> = "office".encode_utf16().collect();
+    let options = ShapingOptions { features: Some(&features), ..ShapingOptions::default() };
+    let analyzer = TextAnalyzer::shared();
+    let run = analyzer.get_glyphs(&text, &face, &options);
+    assert_eq!(run.glyph_indices, face.get_glyph_indices_for_str("office"));
+
+    let (advances, offsets) = analyzer.get_glyph_placements(&text, &run, &face, 16., &options);
+    assert_eq!(advances.len(), run.glyph_count());
+    assert_eq!(offsets.len(), run.glyph_count());
+    assert!(advances.iter().all(|&advance| advance > 0.));
+}
+
+#[test]
+fn test_stylistic_sets() {
+    let system_fc = FontCollection::system();
+    let family = system_fc.get_font_family_by_name("Gabriola").unwrap();
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let sets = font.create_font_face().stylistic_sets();
+    assert!(!sets.is_empty());
+    assert!(sets.iter().all(|set| set.number >= 1 && set.number <= 20));
+    assert!(sets.windows(2).all(|pair| pair[0].number < pair[1].number));
+
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert!(arial_font.create_font_face().stylistic_sets().is_empty());
+}
+
+#[test]
+fn test_number_substitution() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let substitution = NumberSubstitution::new(DWRITE_NUMBER_SUBSTITUTION_METHOD_NONE, "en-us", true);
+    let text: Vec<u16> = "123".encode_utf16().collect();
+    let options = ShapingOptions { number_substitution: Some(&substitution), ..ShapingOptions::default() };
+    let run = TextAnalyzer::shared().get_glyphs(&text, &face, &options);
+    assert_eq!(run.glyph_indices, face.get_glyph_indices_for_str("123"));
+
+    let substitution = NumberSubstitution::new(DWRITE_NUMBER_SUBSTITUTION_METHOD_NATIONAL, "ar-EG", true);
+    let options = ShapingOptions { number_substitution: Some(&substitution), ..ShapingOptions::default() };
+    assert_eq!(TextAnalyzer::shared().get_glyphs(&text, &face, &options).glyph_count(), 3);
+}
+
+#[test]
+fn test_justification() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let text: Vec<u16> = "a few words".encode_utf16().collect();
+    let options = ShapingOptions::default();
+    let analyzer = TextAnalyzer::shared();
+    let run = analyzer.get_glyphs(&text, &face, &options);
+    let (advances, offsets) = analyzer.get_glyph_placements(&text, &run, &face, 16., &options);
+    let width: f32 = advances.iter().sum();
+
+    let opportunities = analyzer.get_justification_opportunities(&text, &run, &face, 16., &options).unwrap();
+    assert_eq!(opportunities.len(), run.glyph_count());
+    let justified = analyzer.justify_glyph_advances(width + 20., &opportunities, &advances, &offsets).unwrap();
+    let justified_width: f32 = justified.advances.iter().sum();
+    assert!((justified_width - (width + 20.)).abs() < 0.01);
+
+    let justified_run = analyzer.get_justified_glyphs(&run, &face, 16., &options, &advances, &justified).unwrap();
+    assert_eq!(justified_run.glyph_indices, run.glyph_indices);
+    assert_eq!(justified_run.cluster_map.len(), text.len());
+}
+
+#[test]
+fn test_inline_object() {
+    struct Box40;
+    impl InlineObject for Box40 {
+        fn draw(&self, _: *mut winapi::ctypes::c_void, _: f32, _: f32, _: bool, _: bool, _: *mut winapi::um::unknwnbase::IUnknown) {}
+        fn get_metrics(&self) -> DWRITE_INLINE_OBJECT_METRICS {
+            DWRITE_INLINE_OBJECT_METRICS { width: 40., height: 10., baseline: 10., supportsSideways: 0 }
+        }
+    }
+
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    let plain = TextLayout::new("a\u{FFFC}b", &format, 1000., 1000.);
+    let with_object = TextLayout::new("a\u{FFFC}b", &format, 1000., 1000.);
+    with_object.set_inline_object(Box40, 1..2);
+    assert!(with_object.get_metrics().width > plain.get_metrics().width + 30.);
+}
+
+#[test]
+fn test_text_layout_ranges() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    let plain = TextLayout::new("some text", &format, 1000., 1000.);
+    let formatted = TextLayout::new("some text", &format, 1000., 1000.);
+    formatted.set_font_size(32., 0..4);
+    formatted.set_font_weight(FontWeight::Bold, 0..4);
+    formatted.set_font_style(FontStyle::Italic, 5..9);
+    formatted.set_underline(true, 5..9);
+    formatted.set_strikethrough(true, 0..9);
+    formatted.set_font_family("Times New Roman", 5..9);
+    formatted.set_font_collection(&FontCollection::system(), 0..9);
+    formatted.set_locale_name("de-de", 0..9);
+    assert!(formatted.get_metrics().width > plain.get_metrics().width);
+    assert!(formatted.get_metrics().height > plain.get_metrics().height);
+}
+
+#[test]
+fn test_typography_and_drawing_effect() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    let layout = TextLayout::new("red and plain", &format, 1000., 1000.);
+
+    let typography = Typography::new();
+    typography.add_feature(*b"smcp", 1).unwrap();
+    assert!(typography.add_feature(*b" abc", 1).is_err());
+    assert_eq!(typography.get_features().len(), 1);
+    layout.set_typography(&typography, 0..3);
+
+    let red = ColorDrawingEffect::new(1., 0., 0., 1.);
+    unsafe {
+        layout.set_drawing_effect(red.as_ptr(), 0..3);
+    }
+    let effect = layout.get_drawing_effect(1).unwrap();
+    let color = unsafe { ColorDrawingEffect::from_unknown(effect.as_ptr()) }.unwrap().color();
+    assert_eq!(color, [1., 0., 0., 1.]);
+    assert!(layout.get_drawing_effect(5).is_none());
+}
+
+#[test]
+fn test_text_layout_overhang() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    let layout = TextLayout::new("overhang", &format, 10., 10.);
+    let overhangs = layout.get_overhang_metrics();
+    // The text is much wider than the 10 DIP box.
+    assert!(overhangs.right > 0.);
+}
+
+#[test]
+fn test_cluster_metrics() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    let layout = TextLayout::new("ab cd\ne\u{301}", &format, 1000., 1000.);
+    let clusters = layout.get_cluster_metrics();
+    assert_eq!(clusters.iter().map(|c| c.length).sum::<usize>(), 8);
+    // "e" plus the combining acute accent form a single cluster.
+    assert_eq!(clusters.len(), 7);
+    assert_eq!(clusters[6].length, 2);
+    assert!(clusters[2].is_whitespace && clusters[2].can_wrap_line_after);
+    assert!(clusters[5].is_newline);
+    assert!(!clusters.iter().any(|c| c.is_right_to_left));
+}
+
+#[test]
+fn test_trimming() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    format.set_word_wrapping(DWRITE_WORD_WRAPPING_NO_WRAP);
+    let untrimmed = TextLayout::new("a label that is far too long to fit", &format, 60., 100.);
+    assert!(untrimmed.get_metrics().width > 60.);
+
+    format.set_ellipsis_trimming();
+    let trimmed = TextLayout::new("a label that is far too long to fit", &format, 60., 100.);
+    assert!(trimmed.get_metrics().width <= 60.);
+
+    format.set_trimming(DWRITE_TRIMMING_GRANULARITY_WORD, Some('\\'), 1, None);
+    let path = TextLayout::new("C:\\Users\\someone\\Documents\\file.txt", &format, 60., 100.);
+    assert_eq!(path.get_metrics().lineCount, 1);
+}
+
+#[test]
+fn test_fit_font_size() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    format.set_word_wrapping(DWRITE_WORD_WRAPPING_NO_WRAP);
+    let layout = TextLayout::new("banner", &format, 1000., 1000.);
+    let size = layout.fit_font_size(200., 100., 4., 200.);
+    assert!(size > 4. && size < 200.);
+    let metrics = layout.get_metrics();
+    assert!(metrics.width <= 200. && metrics.height <= 100.);
+
+    // Too small a box for even the minimum size.
+    assert_eq!(layout.fit_font_size(1., 1., 4., 200.), 4.);
+    // Plenty of room for the maximum.
+    assert_eq!(layout.fit_font_size(10000., 10000., 4., 20.), 20.);
+}
+
+#[test]
+fn test_text_layout_font_fallback() {
+    let fallback = match FontFallback::system() {
+        Some(fallback) => fallback,
+        None => return,
+    };
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    let layout = TextLayout::new("a \u{1F600}", &format, 1000., 1000.);
+    layout.set_font_fallback(&fallback).unwrap();
+    assert!(layout.get_metrics().width > 0.);
+}
+
+#[test]
+fn test_line_spacing() {
+    let format = TextFormat::new("Arial", None, FontWeight::Regular, FontStyle::Normal,
+                                 FontStretch::Normal, 16., "en-us");
+    assert_eq!(format.get_line_spacing().0, DWRITE_LINE_SPACING_METHOD_DEFAULT);
+
+    format.set_line_spacing(DWRITE_LINE_SPACING_METHOD_UNIFORM, 40., 30.).unwrap();
+    assert_eq!(format.get_line_spacing(), (DWRITE_LINE_SPACING_METHOD_UNIFORM, 40., 30.));
+    let layout = TextLayout::new("one\ntwo\nthree", &format, 1000., 1000.);
+    assert_eq!(layout.get_metrics().height, 120.);
+}
+
+#[test]
+fn test_snap_baseline() {
+    assert_eq!(snap_baseline(10.4, 1.), 10.);
+    assert_eq!(snap_baseline(10.5, 1.), 11.);
+    assert_eq!(snap_baseline(-0.5, 1.), 0.);
+    assert_eq!(snap_baseline(10.3, 2.), 10.5);
+
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices_for_str("H")[0];
+    let advance = 0f32;
+    let offset = GlyphOffset { advanceOffset: 0., ascenderOffset: 0. };
+    let mut glyph_run: DWRITE_GLYPH_RUN = unsafe { std::mem::zeroed() };
+    glyph_run.fontFace = unsafe { face.as_ptr() };
+    glyph_run.fontEmSize = 16.;
+    glyph_run.glyphCount = 1;
+    glyph_run.glyphIndices = &glyph;
+    glyph_run.glyphAdvances = &advance;
+    glyph_run.glyphOffsets = &offset;
+
+    let snapped = GlyphRunAnalysis::create_at_baseline(&glyph_run, 1., None, DWRITE_RENDERING_MODE_ALIASED,
+                                                       DWRITE_MEASURING_MODE_NATURAL, (0., 20.4), true);
+    let whole = GlyphRunAnalysis::create(&glyph_run, 1., None, DWRITE_RENDERING_MODE_ALIASED,
+                                         DWRITE_MEASURING_MODE_NATURAL, 0., 20.);
+    let snapped_bounds = snapped.get_alpha_texture_bounds(DWRITE_TEXTURE_ALIASED_1x1);
+    let whole_bounds = whole.get_alpha_texture_bounds(DWRITE_TEXTURE_ALIASED_1x1);
+    assert_eq!((snapped_bounds.top, snapped_bounds.bottom), (whole_bounds.top, whole_bounds.bottom));
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn test_ffi() {
+    use ffi::*;
+    use std::ptr;
+
+    let path: Vec<u16> = "C:\\Windows\\Fonts\\arial.ttf\0".encode_utf16().collect();
+    let mut face = ptr::null_mut();
+    unsafe {
+        let mut missing = ptr::null_mut();
+        assert_eq!(dwrote_font_face_create_from_path(path.as_ptr(), 1, &mut missing),
+                   DWROTE_ERROR_CANNOT_LOAD_FONT);
+        assert_eq!(dwrote_font_face_create_from_path(path.as_ptr(), 0, &mut face), DWROTE_OK);
+
+        let mut glyph = 0;
+        assert_eq!(dwrote_font_face_get_glyph_indices(face, &('A' as u32), 1, &mut glyph), DWROTE_OK);
+        assert!(glyph != 0);
+
+        let mut info = DWroteRasterizedGlyph::default();
+        assert_eq!(dwrote_font_face_rasterize_glyph(face, glyph, 16., 0, &mut info, ptr::null_mut(), 0),
+                   DWROTE_ERROR_BUFFER_TOO_SMALL);
+        let mut buffer = vec![0u8; info.data_len];
+        assert_eq!(dwrote_font_face_rasterize_glyph(face, glyph, 16., 0, &mut info, buffer.as_mut_ptr(), buffer.len()),
+                   DWROTE_OK);
+        assert!(buffer.iter().any(|&b| b != 0));
+        assert_eq!(dwrote_font_face_rasterize_glyph(face, glyph, -1., 0, &mut info, ptr::null_mut(), 0),
+                   DWROTE_ERROR_INVALID_ARGUMENT);
+        assert_eq!(dwrote_font_face_rasterize_glyph(face, glyph, 16., 6, &mut info, ptr::null_mut(), 0),
+                   DWROTE_ERROR_INVALID_ARGUMENT);
+
+        assert_eq!(dwrote_font_face_free(face), DWROTE_OK);
+        assert_eq!(dwrote_font_face_free(ptr::null_mut()), DWROTE_ERROR_NULL_POINTER);
+    }
+}
+
+#[test]
+fn test_font_file_faces() {
+    let file = FontFile::new_from_path("C:\\Windows\\Fonts\\cambria.ttc").unwrap();
+    assert!(file.face_count() >= 2);
+    let mut faces = file.faces();
+    let first = faces.next().unwrap().unwrap();
+    assert!(first.get_glyph_count() > 0);
+    assert_eq!(faces.count() as u32, file.face_count() - 1);
+
+    let arial = FontFile::new_from_path("C:\\Windows\\Fonts\\arial.ttf").unwrap();
+    assert_eq!(arial.faces().count(), 1);
+}
+
+#[test]
+fn test_vertical_origins() {
+    let system_fc = FontCollection::system();
+    let family = system_fc.get_font_family_by_name("MS Gothic").unwrap();
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let face = font.create_font_face();
+
+    let glyphs = face.get_glyph_indices_for_str("\u{3042}A");
+    let origins = face.get_vertical_origins(&glyphs, 20.);
+    let metrics = face.get_design_glyph_metrics(&glyphs, false);
+    let scale = 20. / face.metrics().designUnitsPerEm as f32;
+    assert_eq!(origins.len(), 2);
+    for (origin, metrics) in origins.iter().zip(metrics.iter()) {
+        assert_eq!(origin.0, metrics.advanceWidth as f32 / 2. * scale);
+        // The vertical origin is above the baseline.
+        assert!(origin.1 < 0.);
+    }
+}
+
+#[test]
+fn test_font_face_try_methods() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let glyphs = face.try_get_glyph_indices(&['A' as u32, 'B' as u32]).unwrap();
+    assert_eq!(glyphs, face.get_glyph_indices(&['A' as u32, 'B' as u32]));
+    assert_eq!(face.try_get_design_glyph_metrics(&glyphs, false).unwrap().len(), 2);
+    assert_eq!(face.try_get_gdi_compatible_glyph_metrics(16., 1., std::ptr::null(), false, &glyphs, false)
+                   .unwrap().len(), 2);
+    assert!(face.try_get_font_table(make_opentype_tag(b"head")).unwrap().is_some());
+    assert!(face.try_get_font_table(make_opentype_tag(b"zzzz")).unwrap().is_none());
+    assert_eq!(face.try_get_files().unwrap().len(), 1);
+}
+
+#[test]
+fn test_all_glyph_advances() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let advances = face.all_glyph_advances();
+    assert_eq!(advances.len(), face.get_glyph_count() as usize);
+    let glyph_indices: Vec<u16> = (0..face.get_glyph_count()).collect();
+    let metrics = face.get_design_glyph_metrics(&glyph_indices, false);
+    for (advance, metrics) in advances.iter().zip(metrics.iter()) {
+        assert_eq!(*advance as u32, metrics.advanceWidth);
+    }
+}
+
+#[test]
+fn test_glyph_advances_px() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let units_per_em = face.metrics().designUnitsPerEm as f32;
+
+    let glyphs = face.get_glyph_indices(&['W' as u32, 'i' as u32, ' ' as u32]);
+    let design = face.get_design_glyph_metrics(&glyphs, false);
+    for &em_size in &[12., 16.5, 100.] {
+        let advances = face.glyph_advances_px(&glyphs, em_size);
+        assert_eq!(advances.len(), glyphs.len());
+        for ((&glyph, advance), metrics) in glyphs.iter().zip(advances.iter()).zip(design.iter()) {
+            let expected = metrics.advanceWidth as f32 * em_size / units_per_em;
+            assert!((advance - expected).abs() < 1e-4);
+            assert_eq!(face.glyph_advance_px(glyph, em_size), *advance);
+        }
+    }
+}
+
+#[test]
+fn test_vertical_glyph_metrics() {
+    let system_fc = FontCollection::system();
+    let family = match system_fc.get_font_family_by_name("MS Gothic") {
+        Some(family) => family,
+        None => return,
+    };
+    let face = family.get_first_matching_font(FontWeight::Regular,
+                                              FontStretch::Normal,
+                                              FontStyle::Normal).create_font_face();
+    // U+6F22, an ideograph, set upright in vertical text.
+    let glyph = face.get_glyph_indices(&[0x6F22])[0];
+    assert!(glyph != 0);
+
+    let vertical = face.get_vertical_glyph_metrics(&[glyph]);
+    assert_eq!(vertical.len(), 1);
+    // Ideographs advance a full em down the line.
+    assert_eq!(vertical[0].advance_height as u32, face.metrics().designUnitsPerEm as u32);
+
+    // DirectWrite's sideways metrics come from the same 'vmtx' entry, with
+    // the vertical origin top_side_bearing above the top of the glyph.
+    let metrics = face.get_design_glyph_metrics(&[glyph], true)[0];
+    assert_eq!(vertical[0].advance_height as u32, metrics.advanceHeight);
+    assert_eq!(vertical[0].top_side_bearing as i32, metrics.topSideBearing);
+    assert!(metrics.verticalOriginY > metrics.topSideBearing);
+
+    let arial = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal)
+        .create_font_face();
+    assert!(arial.get_vertical_glyph_metrics(&[glyph]).is_empty());
+}
+
+#[test]
+fn test_prefers_typo_metrics() {
+    let system_fc = FontCollection::system();
+    for name in &["Arial", "Segoe UI", "Bahnschrift", "Cambria"] {
+        let family = match system_fc.get_font_family_by_name(name) {
+            Some(family) => family,
+            None => continue,
+        };
+        let face = family.get_first_matching_font(FontWeight::Regular,
+                                                  FontStretch::Normal,
+                                                  FontStyle::Normal).create_font_face();
+        // fsSelection is at offset 62 of 'OS/2'; bit 7 is USE_TYPO_METRICS.
+        let os2 = face.get_font_table(make_opentype_tag(b"OS/2")).unwrap();
+        let fs_selection = u16::from_be_bytes([os2[62], os2[63]]);
+        assert_eq!(face.prefers_typo_metrics(), fs_selection & (1 << 7) != 0);
+    }
+
+    // Arial predates the bit.
+    let arial = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal)
+        .create_font_face();
+    assert!(!arial.prefers_typo_metrics());
+}
+
+#[test]
+fn test_glyph_widths_1000() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    assert_eq!(face.units_per_em(), 2048);
+    assert_eq!(face.units_per_em(), face.metrics().designUnitsPerEm);
+
+    // Arial's space is 569 units of 2048, or 277.83 of 1000; 'm' is 1706,
+    // or 833.01.
+    let glyphs = face.get_glyph_indices_for_str(" m");
+    assert_eq!(face.glyph_widths_1000(&glyphs), vec![278, 833]);
+}
+
+#[test]
+fn test_glyph_to_unicode_map() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let map = face.glyph_to_unicode_map();
+    assert!(!map.contains_key(&0));
+    let glyph_a = face.get_glyph_indices(&['A' as u32])[0];
+    assert_eq!(map[&glyph_a], vec!['A' as u32]);
+    for (&glyph, code_points) in map.iter().take(100) {
+        assert_eq!(face.get_glyph_indices(code_points), vec![glyph; code_points.len()]);
+    }
+}
+
+// A 'cmap' table with a single encoding record pointing at `subtable`.
+fn cmap_table(platform_id: u16, encoding_id: u16, subtable: &[u8]) -> Vec<u8> {
+    let mut table = vec![0, 0, 0, 1];
+    table.extend_from_slice(&platform_id.to_be_bytes());
+    table.extend_from_slice(&encoding_id.to_be_bytes());
+    table.extend_from_slice(&12u32.to_be_bytes());
+    table.extend_from_slice(subtable);
+    table
+}
+
+// A format 12 subtable made of (start, end, start glyph) groups.
+fn cmap_format_12(groups: &[(u32, u32, u32)]) -> Vec<u8> {
+    let mut subtable = vec![0, 12, 0, 0];
+    subtable.extend_from_slice(&(16 + groups.len() as u32 * 12).to_be_bytes());
+    subtable.extend_from_slice(&0u32.to_be_bytes());
+    subtable.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for &(start, end, start_glyph) in groups {
+        subtable.extend_from_slice(&start.to_be_bytes());
+        subtable.extend_from_slice(&end.to_be_bytes());
+        subtable.extend_from_slice(&start_glyph.to_be_bytes());
+    }
+    subtable
+}
+
+#[test]
+fn test_parse_cmap_format_4() {
+    // 'A'-'C' to glyphs 1-3 by delta, then the required 0xFFFF segment.
+    let segments: [(u16, u16, u16); 2] = [(0x41, 0x43, 1u16.wrapping_sub(0x41)), (0xffff, 0xffff, 1)];
+    let mut subtable = vec![0, 4, 0, 0, 0, 0, 0, 4, 0, 4, 0, 1, 0, 0];
+    for &(_, end, _) in &segments {
+        subtable.extend_from_slice(&end.to_be_bytes());
+    }
+    subtable.extend_from_slice(&[0, 0]);
+    for &(start, _, _) in &segments {
+        subtable.extend_from_slice(&start.to_be_bytes());
+    }
+    for &(_, _, delta) in &segments {
+        subtable.extend_from_slice(&delta.to_be_bytes());
+    }
+    subtable.extend_from_slice(&[0; 4]);
+    let table = cmap_table(3, 1, &subtable);
+
+    let map = tables::parse_cmap_glyph_to_unicode(&table).unwrap();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map[&1], vec![0x41]);
+    assert_eq!(map[&3], vec![0x43]);
+
+    // A truncated subtable contributes nothing; a truncated header is
+    // rejected.
+    assert!(tables::parse_cmap_glyph_to_unicode(&table[..table.len() - 2]).unwrap().is_empty());
+    assert!(tables::parse_cmap_glyph_to_unicode(&table[..10]).is_none());
+    assert!(tables::parse_cmap_glyph_to_unicode(&[]).is_none());
+}
+
+#[test]
+fn test_parse_cmap_format_12() {
+    let table = cmap_table(3, 10, &cmap_format_12(&[(0x1f600, 0x1f601, 5), (0x20000, 0x20000, 5)]));
+    let map = tables::parse_cmap_glyph_to_unicode(&table).unwrap();
+    assert_eq!(map[&5], vec![0x1f600, 0x20000]);
+    assert_eq!(map[&6], vec![0x1f601]);
+
+    // Glyph IDs past 0xFFFF are dropped, even when the start glyph is
+    // close to overflowing.
+    let table = cmap_table(3, 10, &cmap_format_12(&[(0x41, 0x42, 0xffff), (0x43, 0x44, u32::MAX)]));
+    let map = tables::parse_cmap_glyph_to_unicode(&table).unwrap();
+    assert_eq!(map.len(), 1);
+    assert_eq!(map[&0xffff], vec![0x41]);
+
+    // Groups that each claim the whole code space must not be expanded
+    // one after another without limit.
+    let hostile = vec![(0, 0x10ffff, 1); 64];
+    let table = cmap_table(3, 10, &cmap_format_12(&hostile));
+    assert!(tables::parse_cmap_glyph_to_unicode(&table).unwrap().is_empty());
+
+    // A group count larger than the table holds.
+    let mut subtable = cmap_format_12(&[(0x41, 0x41, 1)]);
+    subtable[12..16].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(tables::parse_cmap_glyph_to_unicode(&cmap_table(3, 10, &subtable)).unwrap().is_empty());
+}
+
+#[test]
+fn test_is_in_system_collection() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert!(arial_font.is_in_system_collection());
+
+    let face = arial_font.create_font_face();
+    assert!(face.is_in_system_collection());
+
+    let bytes = face.get_files()[0].get_font_file_bytes();
+    let memory_file = FontFile::new_from_data(&bytes).unwrap();
+    let memory_face = memory_file.create_face(0, DWRITE_FONT_SIMULATIONS_NONE);
+    assert!(!memory_face.is_in_system_collection());
+}
+
+#[test]
+fn test_face_and_file_types() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    assert_eq!(face.face_type(), FaceType::TrueType);
+    let file = &face.get_files()[0];
+    assert_eq!(file.file_type(), FontFileType::TrueType);
+    assert_eq!(file.face_type(), FaceType::TrueType);
+
+    let collection = FontFile::new_from_path("C:\\Windows\\Fonts\\cambria.ttc").unwrap();
+    assert_eq!(collection.file_type(), FontFileType::OpenTypeCollection);
+    assert_eq!(collection.faces().next().unwrap().unwrap().face_type(), FaceType::OpenTypeCollection);
+    assert_eq!(FaceType::from_u32(FaceType::RawCff.to_u32()), FaceType::RawCff);
+}
+
+#[test]
+fn test_color_glyph_layers() {
+    let system_fc = FontCollection::system();
+    let family = system_fc.get_font_family_by_name("Segoe UI Emoji").unwrap();
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let face = font.create_font_face();
+
+    let smiley = face.get_glyph_indices(&[0x1F600])[0];
+    let layers = face.get_color_glyph_layers(smiley, 32., 0);
+    assert!(layers.len() > 1);
+    assert!(layers.iter().all(|layer| layer.glyph_indices.len() == 1));
+    assert!(layers.iter().any(|layer| layer.color.is_some()));
+
+    let a = face.get_glyph_indices(&['a' as u32])[0];
+    assert!(face.get_color_glyph_layers(a, 32., 0).is_empty());
+}
+
+#[test]
+fn test_transform_invert() {
+    let rotate = Transform { m11: 0., m12: 1., m21: -1., m22: 0., dx: 10., dy: 20. };
+    assert_eq!(rotate.transform_point(1., 0.), (10., 21.));
+    let inverse = rotate.invert().unwrap();
+    assert_eq!(inverse.transform_point(10., 21.), (1., 0.));
+
+    let skew_scale = Transform::from(DWRITE_MATRIX { m11: 2., m12: 0., m21: -0.5, m22: 3., dx: -4., dy: 7. });
+    let inverse = skew_scale.invert().unwrap();
+    let (x, y) = skew_scale.transform_point(3., -5.);
+    let (x, y) = inverse.transform_point(x, y);
+    assert!((x - 3.).abs() < 1e-5 && (y + 5.).abs() < 1e-5);
+
+    let singular = Transform { m11: 1., m12: 2., m21: 2., m22: 4., dx: 0., dy: 0. };
+    assert!(singular.invert().is_none());
+    assert_eq!(Transform::identity().invert(), Some(Transform::identity()));
+}
+
+#[test]
+fn test_transform_for_rendering() {
+    let close = |(x, y): (f32, f32), (expected_x, expected_y): (f32, f32)| {
+        (x - expected_x).abs() < 1e-5 && (y - expected_y).abs() < 1e-5
+    };
+
+    assert_eq!(Transform::for_rendering(96., 96., 0., 1.), Transform::identity());
+    let scaled = Transform::for_rendering(192., 144., 0., 2.);
+    assert!(close(scaled.transform_point(1., 1.), (4., 3.)));
+
+    // A quarter turn clockwise: the baseline runs down the screen and
+    // ascenders (-y) point right.
+    let rotated = Transform::for_rendering(96., 96., std::f32::consts::FRAC_PI_2, 1.);
+    assert!(close(rotated.transform_point(1., 0.), (0., 1.)));
+    assert!(close(rotated.transform_point(0., -1.), (1., 0.)));
+
+    let rotated_high_dpi = Transform::for_rendering(192., 96., std::f32::consts::FRAC_PI_2, 1.);
+    assert!(close(rotated_high_dpi.transform_point(1., 0.), (0., 1.)));
+    assert!(close(rotated_high_dpi.transform_point(0., -1.), (2., 0.)));
+}
+
+#[test]
+fn test_font_property_values() {
+    let system_fc = FontCollection::system();
+    let arial = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Bold, FontStretch::Normal, FontStyle::Normal);
+    if FontSet::from_font(&arial).is_err() {
+        return;
+    }
+    assert_eq!(arial.get_property_values(DWRITE_FONT_PROPERTY_ID_WIN32_FAMILY_NAME, Some("en-us")),
+               vec!["Arial".to_owned()]);
+    assert_eq!(arial.get_property_values(DWRITE_FONT_PROPERTY_ID_FULL_NAME, Some("en-us")),
+               vec!["Arial Bold".to_owned()]);
+    assert_eq!(arial.get_property_values(DWRITE_FONT_PROPERTY_ID_POSTSCRIPT_NAME, None),
+               vec!["Arial-BoldMT".to_owned()]);
+}
+
+#[test]
+fn test_font_set_from_files() {
+    let files = vec![FontFile::new_from_path("C:\\Windows\\Fonts\\arial.ttf").unwrap(),
+                     FontFile::new_from_path("C:\\Windows\\Fonts\\cambria.ttc").unwrap()];
+    let set = FontSet::from_files(&files).unwrap();
+    assert_eq!(set.get_font_count(), 1 + files[1].face_count());
+
+    let families = set.get_property_values(DWRITE_FONT_PROPERTY_ID_WIN32_FAMILY_NAME, Some("en-us"));
+    assert!(families.iter().any(|name| name == "Arial"));
+    assert!(families.iter().any(|name| name == "Cambria"));
+
+    let arial = set.get_matching_fonts("Arial", FontWeight::Regular, FontStretch::Normal, FontStyle::Normal).unwrap();
+    assert_eq!(arial.get_font_property(0, DWRITE_FONT_PROPERTY_ID_POSTSCRIPT_NAME, None),
+               Some("ArialMT".to_owned()));
+    assert!(arial.create_font_face(0).unwrap().get_glyph_count() > 0);
+
+    let collection = set.create_font_collection().unwrap();
+    assert!(collection.get_font_family_by_name("Cambria").is_some());
+    assert!(collection.get_font_family_by_name("Times New Roman").is_none());
+}
+
+#[test]
+fn test_font_set_builder_overrides() {
+    let arial = FontFile::new_from_path("C:\\Windows\\Fonts\\arial.ttf").unwrap();
+    let builder = FontSetBuilder::new().unwrap();
+    builder.add_with_properties(&arial, 0, &[(DWRITE_FONT_PROPERTY_ID_WIN32_FAMILY_NAME, "Library Sans", "en-us"),
+                                             (DWRITE_FONT_PROPERTY_ID_FAMILY_NAME, "Library Sans", "en-us")])
+           .unwrap();
+    let set = builder.build().unwrap();
+    assert_eq!(set.get_font_count(), 1);
+    assert_eq!(set.get_font_property(0, DWRITE_FONT_PROPERTY_ID_WIN32_FAMILY_NAME, Some("en-us")),
+               Some("Library Sans".to_owned()));
+    // Properties that weren't overridden come from the font.
+    assert_eq!(set.get_font_property(0, DWRITE_FONT_PROPERTY_ID_POSTSCRIPT_NAME, None),
+               Some("ArialMT".to_owned()));
+    assert!(set.get_matching_fonts("Library Sans", FontWeight::Regular, FontStretch::Normal, FontStyle::Normal)
+               .unwrap().get_font_count() == 1);
+}
+
+#[test]
+fn test_glyph_run_pixel_bounds() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let glyphs = face.get_glyph_indices_for_str("Hi");
+    let advances = face.glyph_advances_px(&glyphs, 32.);
+    let bounds = face.glyph_run_pixel_bounds(&glyphs, &advances, 32., 1., None,
+                                             DWRITE_RENDERING_MODE_ALIASED, DWRITE_MEASURING_MODE_NATURAL);
+    let rasterized = face.rasterize_glyph(glyphs[0], 32., 1., DWRITE_RENDERING_MODE_ALIASED,
+                                          DWRITE_MEASURING_MODE_NATURAL,
+                                          &RenderingParams::create_for_primary_monitor());
+    assert_eq!(bounds.left, rasterized.bounds.left);
+    assert!(bounds.right > rasterized.bounds.right);
+    assert!(bounds.top < 0 && bounds.bottom >= 0);
+
+    // At two pixels per DIP the run covers twice as many pixels.
+    let doubled = face.glyph_run_pixel_bounds(&glyphs, &advances, 32., 2., None,
+                                              DWRITE_RENDERING_MODE_ALIASED, DWRITE_MEASURING_MODE_NATURAL);
+    assert!(doubled.right - doubled.left > (bounds.right - bounds.left) * 3 / 2);
+    assert!(doubled.bottom - doubled.top > (bounds.bottom - bounds.top) * 3 / 2);
+
+    let spaces = face.get_glyph_indices_for_str("  ");
+    let advances = face.glyph_advances_px(&spaces, 32.);
+    let bounds = face.glyph_run_pixel_bounds(&spaces, &advances, 32., 1., None,
+                                             DWRITE_RENDERING_MODE_DEFAULT, DWRITE_MEASURING_MODE_NATURAL);
+    assert_eq!((bounds.left, bounds.top, bounds.right, bounds.bottom), (0, 0, 0, 0));
+
+    // Large enough that DEFAULT resolves to outline.
+    let advances = face.glyph_advances_px(&glyphs, 400.);
+    let bounds = face.glyph_run_pixel_bounds(&glyphs, &advances, 400., 1., None,
+                                             DWRITE_RENDERING_MODE_DEFAULT, DWRITE_MEASURING_MODE_NATURAL);
+    assert!(bounds.right > bounds.left && bounds.bottom > bounds.top);
+}
+
+#[test]
+fn test_embedded_bitmaps() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    assert!(!face.has_embedded_bitmaps());
+    assert!(face.preferred_bitmap_sizes().is_empty());
+
+    // SimSun ships bitmap strikes for small CJK sizes.
+    if let Some(family) = system_fc.get_font_family_by_name("SimSun") {
+        let face = family.get_first_matching_font(FontWeight::Regular,
+                                                  FontStretch::Normal,
+                                                  FontStyle::Normal).create_font_face();
+        assert!(face.has_embedded_bitmaps());
+        let sizes = face.preferred_bitmap_sizes();
+        assert!(!sizes.is_empty());
+        assert!(sizes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
+
+#[test]
+fn test_get_embedded_bitmap() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['A' as u32])[0];
+    assert!(face.get_embedded_bitmap(glyph, 16).is_none());
+}
+
+#[test]
+fn test_glyph_outline_bounds() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyphs = face.get_glyph_indices(&['o' as u32, ' ' as u32]);
+
+    let (left, top, right, bottom) = face.glyph_outline_bounds(glyphs[0]).unwrap();
+    assert!(left < right && top < bottom);
+    // The "o" sits on the baseline, with its overshoot just below it.
+    assert!(top < 0. && bottom > 0. && bottom < 0.05);
+
+    // Should agree with the design metrics to within rounding.
+    let metrics = face.get_design_glyph_metrics(&glyphs[..1], false)[0];
+    let units_per_em = face.metrics().designUnitsPerEm as f32;
+    assert!((left - metrics.leftSideBearing as f32 / units_per_em).abs() < 0.01);
+
+    assert!(face.glyph_outline_bounds(glyphs[1]).is_none());
+}
+
+#[test]
+fn test_glyph_run_flattened() {
+    let system_fc = FontCollection::system();
+    let face = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal)
+        .create_font_face();
+    let glyphs = face.get_glyph_indices(&['o' as u32, 'l' as u32]);
+
+    // An "o" is an outer contour and a hole.
+    let coarse = face.glyph_run_flattened(&glyphs[..1], 100., 1.);
+    assert_eq!(coarse.len(), 2);
+    let fine = face.glyph_run_flattened(&glyphs[..1], 100., 0.01);
+    assert_eq!(fine.len(), 2);
+    assert!(fine[0].len() > coarse[0].len());
+
+    // Every point lies within the outline's bounds.
+    let (left, top, right, bottom) = face.glyph_outline_bounds(glyphs[0]).unwrap();
+    for &(x, y) in fine.iter().flat_map(|contour| contour.iter()) {
+        assert!(x >= left * 100. - 0.01 && x <= right * 100. + 0.01);
+        assert!(y >= top * 100. - 0.01 && y <= bottom * 100. + 0.01);
+    }
+
+    // The "l" is a plain rectangle and comes after the "o".
+    let run = face.glyph_run_flattened(&glyphs, 100., 0.1);
+    assert_eq!(run.len(), 3);
+    assert_eq!(run[2].len(), 4);
+    let advance = face.glyph_advance_px(glyphs[0], 100.);
+    assert!(run[2].iter().all(|&(x, _)| x > advance - 1.));
+}
+
+#[test]
+fn test_get_glyph_svg() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['A' as u32])[0];
+    assert!(face.get_glyph_svg(glyph).is_none());
+
+    assert!(svg_document_is_compressed(&[0x1f, 0x8b, 0x08, 0x00]));
+    assert!(!svg_document_is_compressed(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+}
+
+#[test]
+fn test_supports_variations() {
+    let system_fc = FontCollection::system();
+    let arial = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    assert!(!arial.create_font_face().supports_variations());
+
+    if let Some(family) = system_fc.get_font_family_by_name("Bahnschrift") {
+        let face = family.get_first_matching_font(FontWeight::Regular,
+                                                  FontStretch::Normal,
+                                                  FontStyle::Normal).create_font_face();
+        assert_eq!(face.supports_variations(), max_supported_factory_version() >= 5);
+    }
+}
+
+#[test]
+fn test_stat_axes() {
+    let system_fc = FontCollection::system();
+    // Bahnschrift is a variable font with a STAT table, shipped since
+    // Windows 10 1709.
+    let family = match system_fc.get_font_family_by_name("Bahnschrift") {
+        Some(family) => family,
+        None => return,
+    };
+    let face = family.get_first_matching_font(FontWeight::Regular,
+                                              FontStretch::Normal,
+                                              FontStyle::Normal).create_font_face();
+    let axes = face.get_stat_axes();
+    let weight = axes.iter().find(|axis| &axis.tag == b"wght").unwrap();
+    assert!(face.get_name_string(weight.name_id, Some("en-us")).is_some());
+    assert!(!weight.values.is_empty());
+    for value in &weight.values {
+        assert!(face.get_name_string(value.name_id, Some("en-us")).is_some());
+    }
+}
+
+#[test]
+fn test_fvar_axes() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert!(arial_font.create_font_face().get_fvar_axes().is_empty());
+
+    let family = match system_fc.get_font_family_by_name("Bahnschrift") {
+        Some(family) => family,
+        None => return,
+    };
+    let face = family.get_first_matching_font(FontWeight::Regular,
+                                              FontStretch::Normal,
+                                              FontStyle::Normal).create_font_face();
+    let axes = face.get_fvar_axes();
+    let weight = axes.iter().find(|axis| &axis.tag == b"wght").unwrap();
+    assert!(weight.min_value <= weight.default_value);
+    assert!(weight.default_value <= weight.max_value);
+    for instance in face.get_fvar_instances() {
+        assert_eq!(instance.coordinates.len(), axes.len());
+    }
+}
+
+#[test]
+fn test_named_instance_name() {
+    let system_fc = FontCollection::system();
+    let arial = system_fc.get_font_family_by_name("Arial").unwrap()
+        .get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    assert_eq!(arial.create_font_face().named_instance_name(Some("en-us")), None);
+
+    let family = match system_fc.get_font_family_by_name("Bahnschrift") {
+        Some(family) => family,
+        None => return,
+    };
+    let regular = family.get_first_matching_font(FontWeight::Regular,
+                                                 FontStretch::Normal,
+                                                 FontStyle::Normal).create_font_face();
+    assert_eq!(regular.named_instance_name(Some("en-us")).as_ref().map(|name| &name[..]), Some("Regular"));
+
+    // The system collection lists Bahnschrift's named instances as fonts;
+    // telling them apart needs the face's axis values.
+    if !regular.supports_variations() {
+        return;
+    }
+    let bold = family.get_first_matching_font(FontWeight::Bold,
+                                              FontStretch::Normal,
+                                              FontStyle::Normal).create_font_face();
+    assert_eq!(bold.named_instance_name(Some("en-us")).as_ref().map(|name| &name[..]), Some("Bold"));
+}
+
+#[test]
+fn test_measure_string_with_fallback() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let latin = measure_string_with_fallback("Hello", &arial_font, 16.);
+    assert!((latin - face.measure_string_advance("Hello", 16.)).abs() < 0.01);
+
+    let fallback = match FontFallback::system() {
+        Some(fallback) => fallback,
+        None => return,
+    };
+    let text = "Hello \u{4e2d}\u{6587}";
+    let runs = fallback.map_characters(text, &arial_font, Some("zh-cn")).unwrap();
+    assert!(runs.len() >= 2);
+    assert_eq!(runs[0].text.start, 0);
+    assert_eq!(runs.last().unwrap().text.end, text.len());
+    assert_eq!(runs[0].font.as_ref().unwrap().family_name(), "Arial");
+    assert!(runs.last().unwrap().font.as_ref().unwrap().family_name() != "Arial");
+    assert!(measure_string_with_fallback(text, &arial_font, 16.) > face.measure_string_advance("Hello ", 16.));
+}
+
+#[test]
+fn test_css_line_height() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    // Arial doesn't set USE_TYPO_METRICS, so this is its win metrics.
+    let os2 = face.get_os2_metrics().unwrap();
+    assert!(!os2.use_typo_metrics());
+    let win = (os2.win_ascent as f32 + os2.win_descent as f32) * 16. / face.units_per_em() as f32;
+    let height = face.css_line_height(16.);
+    assert!(height >= win - 0.01);
+    assert!((face.css_line_height(32.) - height * 2.).abs() < 0.01);
+}
+
+#[test]
+fn test_supported_scripts() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let scripts = arial_font.create_font_face().supported_scripts();
+    assert!(scripts.contains(b"latn"));
+    assert!(scripts.contains(b"arab"));
+    assert!(scripts.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_default_rendering_params() {
+    let shared = RenderingParams::default_params();
+    let fresh = RenderingParams::create_for_primary_monitor();
+    unsafe {
+        assert_eq!(shared.as_ptr(), RenderingParams::default_params().as_ptr());
+    }
+    assert_eq!(shared.get_pixel_geometry(), fresh.get_pixel_geometry());
+    assert!(shared.get_gamma() > 0.);
+}
+
+#[test]
+fn test_rendering_params_for_monitor() {
+    use winapi::shared::windef::POINT;
+    use winapi::um::winuser::{MonitorFromPoint, MONITOR_DEFAULTTOPRIMARY};
+
+    // The primary monitor always has its top left corner at (0, 0).
+    let params = unsafe {
+        let hmonitor = MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY);
+        assert!(!hmonitor.is_null());
+        RenderingParams::for_monitor(hmonitor).unwrap()
+    };
+    let primary = RenderingParams::create_for_primary_monitor();
+    assert_eq!(params.get_pixel_geometry(), primary.get_pixel_geometry());
+    assert_eq!(params.get_gamma(), primary.get_gamma());
+    assert!(params.get_gamma() > 0.);
+}
+
+#[test]
+fn test_glyph_run_analysis_reuse() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['g' as u32])[0];
+    let advance = 0f32;
+    let offset = GlyphOffset { advanceOffset: 0., ascenderOffset: 0. };
+    let glyph_run = DWRITE_GLYPH_RUN {
+        fontFace: unsafe { face.as_ptr() },
+        fontEmSize: 24.,
+        glyphCount: 1,
+        glyphIndices: &glyph,
+        glyphAdvances: &advance,
+        glyphOffsets: &offset,
+        isSideways: 0,
+        bidiLevel: 0,
+    };
+    let analysis = GlyphRunAnalysis::create(&glyph_run, 1., None, DWRITE_RENDERING_MODE_NATURAL,
+                                            DWRITE_MEASURING_MODE_NATURAL, 0., 0.);
+
+    let aliased = analysis.get_alpha_texture_bounds(DWRITE_TEXTURE_ALIASED_1x1);
+    assert!(aliased.right <= aliased.left || aliased.bottom <= aliased.top);
+    let bounds = analysis.get_alpha_texture_bounds(DWRITE_TEXTURE_CLEARTYPE_3x1);
+    assert!(bounds.right > bounds.left && bounds.bottom > bounds.top);
+
+    let first = analysis.create_alpha_texture(DWRITE_TEXTURE_CLEARTYPE_3x1, bounds);
+    let again = analysis.get_alpha_texture_bounds(DWRITE_TEXTURE_CLEARTYPE_3x1);
+    assert_eq!((again.left, again.top, again.right, again.bottom),
+               (bounds.left, bounds.top, bounds.right, bounds.bottom));
+    assert_eq!(analysis.create_alpha_texture(DWRITE_TEXTURE_CLEARTYPE_3x1, bounds), first);
+}
+
+#[test]
+fn test_decoration_rects() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let (left, top, right, bottom) = face.underline_rect(16., 100.);
+    assert_eq!((left, right), (0., 100.));
+    assert!(top > 0.);
+    assert!(bottom > top);
+
+    let (_, top, _, bottom) = face.strikethrough_rect(16., 100.);
+    assert!(top < 0.);
+    assert!(bottom > top);
+    let thickness = face.metrics().strikethroughThickness as f32 * 16. / face.units_per_em() as f32;
+    assert!((bottom - top - thickness).abs() < 0.001);
+}
+
+#[test]
+fn test_is_hinted() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert!(arial_font.create_font_face().is_hinted());
+}
+
+#[test]
+fn test_list_faces() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let faces = arial_family.list_faces(Some("en-us"));
+    assert_eq!(faces.len() as u32, arial_family.get_font_count());
+    assert!(faces.iter().any(|(name, _)| name == "Regular"));
+    assert!(faces.iter().any(|(name, _)| name == "Bold Italic"));
+    assert!(faces.windows(2).all(|pair| pair[0].1.weight().to_u32() <= pair[1].1.weight().to_u32()));
+}
+
+#[test]
+fn test_utf16_analysis_source() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let text: Vec<u16> = "ab\u{1F600}c".encode_utf16().collect();
+    let source = Utf16AnalysisSource::new(&text, Some("en-us"), DWRITE_READING_DIRECTION_LEFT_TO_RIGHT);
+    assert_eq!(source.len(), 5);
+
+    unsafe {
+        let mut string = std::ptr::null();
+        let mut length = 0;
+        assert_eq!((*source.as_ptr()).GetTextAtPosition(2, &mut string, &mut length), 0);
+        assert_eq!(length, 3);
+        assert_eq!(std::slice::from_raw_parts(string, length as usize), &text[2..]);
+        assert_eq!((*source.as_ptr()).GetTextAtPosition(5, &mut string, &mut length), 0);
+        assert_eq!(length, 0);
+        assert_eq!((*source.as_ptr()).GetTextBeforePosition(2, &mut string, &mut length), 0);
+        assert_eq!(std::slice::from_raw_parts(string, length as usize), &text[..2]);
+        assert_eq!((*source.as_ptr()).GetTextBeforePosition(0, &mut string, &mut length), 0);
+        assert_eq!(length, 0);
+    }
+
+    if let Some(fallback) = FontFallback::system() {
+        let runs = fallback.map_characters_utf16(&source, &arial_font).unwrap();
+        assert_eq!(runs.first().unwrap().text.start, 0);
+        assert_eq!(runs.last().unwrap().text.end, text.len());
+    }
+}
+
+#[test]
+fn test_layout_glyph_run() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyphs = face.get_glyph_indices(&['a' as u32, 'W' as u32, 'i' as u32]);
+    let advances = [5., 12., 3.];
+
+    let ltr = layout_glyph_run(&face, 16., &glyphs, &advances, (10., 20.), false);
+    assert_eq!(ltr.glyph_positions(), vec![(10., 20.), (15., 20.), (27., 20.)]);
+    assert_eq!(ltr.width(), 20.);
+    assert_eq!(ltr.as_dwrite_glyph_run().bidiLevel, 0);
+
+    let rtl = layout_glyph_run(&face, 16., &glyphs, &advances, (30., 20.), true);
+    assert_eq!(rtl.glyph_positions(), vec![(25., 20.), (13., 20.), (10., 20.)]);
+    let run = rtl.as_dwrite_glyph_run();
+    assert_eq!(run.bidiLevel, 1);
+    assert_eq!(run.glyphCount, 3);
+
+    let analysis = GlyphRunAnalysis::create(&run, 1., None, DWRITE_RENDERING_MODE_NATURAL,
+                                            DWRITE_MEASURING_MODE_NATURAL,
+                                            rtl.baseline_origin.0, rtl.baseline_origin.1);
+    let bounds = analysis.get_alpha_texture_bounds(DWRITE_TEXTURE_CLEARTYPE_3x1);
+    assert!(bounds.left >= 5 && bounds.right <= 35);
+}
+
+#[test]
+fn test_wws_family_name() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert_eq!(arial_font.wws_family_name(Some("en-us")), Some("Arial".to_owned()));
+
+    // Segoe UI Semibold is its own RIBBI family but part of the Segoe UI
+    // weight-width-slope family.
+    if let Some(family) = system_fc.get_font_family_by_name("Segoe UI") {
+        let font = family.get_first_matching_font(FontWeight::SemiBold,
+                                                  FontStretch::Normal,
+                                                  FontStyle::Normal);
+        assert_eq!(font.wws_family_name(Some("en-us")), Some("Segoe UI".to_owned()));
+    }
+}
+
+#[test]
+fn test_validate() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    assert_eq!(arial_font.create_font_face().validate(), Ok(()));
+
+    let error = ValidationError {
+        problems: vec![ValidationProblem::MissingTable(*b"hmtx"),
+                       ValidationProblem::GlyphCountMismatch { maxp: 10, cff: 12 }],
+    };
+    assert_eq!(error.to_string(),
+               "invalid font: missing 'hmtx' table, 'maxp' has 10 glyphs but 'CFF ' has 12");
+}
+
+#[test]
+fn test_parse_cff_glyph_count() {
+    let mut cff = vec![1, 0, 4, 1];
+    // Name INDEX with one name.
+    cff.extend_from_slice(&[0, 1, 1, 1, 2, b'A']);
+    // Top DICT INDEX: a real number and an escaped operator, then
+    // CharStrings (17) at offset 23.
+    let top_dict = [30, 0x1f, 12, 7, 28, 0, 23, 17];
+    cff.extend_from_slice(&[0, 1, 1, 1, 1 + top_dict.len() as u8]);
+    cff.extend_from_slice(&top_dict);
+    assert_eq!(cff.len(), 23);
+    // CharStrings INDEX with three glyphs.
+    cff.extend_from_slice(&[0, 3, 1, 1, 2, 3, 4, 14, 14, 14]);
+    assert_eq!(tables::parse_cff_glyph_count(&cff), Some(3));
+
+    assert_eq!(tables::parse_cff_glyph_count(&cff[..23]), None);
+    assert_eq!(tables::parse_cff_glyph_count(&cff[..12]), None);
+    assert_eq!(tables::parse_cff_glyph_count(&[]), None);
+    // An offset size out of range.
+    let mut bad = cff.clone();
+    bad[12] = 5;
+    assert_eq!(tables::parse_cff_glyph_count(&bad), None);
+}
+
+#[test]
+fn test_glyph_name() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyphs = face.get_glyph_indices(&['a' as u32, 'A' as u32, ' ' as u32]);
+    assert_eq!(face.glyph_name(glyphs[0]).as_ref().map(|name| &name[..]), Some("a"));
+    assert_eq!(face.glyph_name(glyphs[1]).as_ref().map(|name| &name[..]), Some("A"));
+    assert_eq!(face.glyph_name(glyphs[2]).as_ref().map(|name| &name[..]), Some("space"));
+    assert_eq!(face.glyph_name(0).as_ref().map(|name| &name[..]), Some(".notdef"));
+    assert_eq!(face.glyph_name(face.get_glyph_count()), None);
+}
+
+#[test]
+fn test_glyph_for_name() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyph = face.get_glyph_indices(&['a' as u32])[0];
+    assert_eq!(face.glyph_for_name("a"), Some(glyph));
+    assert_eq!(face.glyph_for_name(".notdef"), Some(0));
+    assert_eq!(face.glyph_for_name("no.such.glyph"), None);
+    for glyph in 0..face.get_glyph_count().min(50) {
+        if let Some(name) = face.glyph_name(glyph) {
+            assert!(face.glyph_for_name(&name).unwrap() <= glyph);
+        }
+    }
+}
+
+#[test]
+fn test_gdi_compatible_ppem() {
+    assert_eq!(gdi_compatible_ppem(13.3, 1.), 13.);
+    assert_eq!(gdi_compatible_ppem(12.5, 1.), 13.);
+    assert_eq!(gdi_compatible_ppem(12., 1.5), 18.);
+
+    // GDI-compatible metrics at 13.3 are those at a 13 pixel em.
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let glyphs = face.get_glyph_indices(&['a' as u32, 'W' as u32, 'm' as u32]);
+    let upem = face.units_per_em() as f32;
+    let advances = |em_size: f32| -> Vec<f32> {
+        face.get_gdi_compatible_glyph_metrics(em_size, 1., std::ptr::null(), false, &glyphs, false)
+            .iter()
+            .map(|metrics| metrics.advanceWidth as f32 * em_size / upem)
+            .collect()
+    };
+    for (fractional, whole) in advances(13.3).iter().zip(advances(gdi_compatible_ppem(13.3, 1.))) {
+        assert!((fractional - whole).abs() < 0.05);
+    }
+}
+
+#[test]
+fn test_vendor_id() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+    let vendor_id = face.vendor_id().unwrap();
+    assert!(!vendor_id.is_empty() && vendor_id.len() <= 4);
+    assert!(!vendor_id.ends_with(' '));
+    assert!(face.get_os2_metrics().unwrap().vendor_id.starts_with(vendor_id.as_bytes()));
+}
+
+#[test]
+fn test_covered_codepoints() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial_font = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                          FontStretch::Normal,
+                                                          FontStyle::Normal);
+    let face = arial_font.create_font_face();
+
+    let ranges = face.unicode_ranges();
+    assert!(ranges.iter().any(|&(first, last)| first <= 'A' as u32 && 'z' as u32 <= last));
+    assert!(ranges.windows(2).all(|pair| pair[0].1 < pair[1].0));
+
+    let total: u32 = ranges.iter().map(|&(first, last)| last - first + 1).sum();
+    assert_eq!(face.covered_codepoints().count() as u32, total);
+    assert!(face.covered_codepoints().any(|code_point| code_point == 'A' as u32));
+    assert!(!face.covered_codepoints().any(|code_point| code_point == 0x4e2d));
+}
+
+#[test]
+fn test_coverage_difference() {
+    let system_fc = FontCollection::system();
+    let arial_family = system_fc.get_font_family_by_name("Arial").unwrap();
+    let arial = arial_family.get_first_matching_font(FontWeight::Regular,
+                                                     FontStretch::Normal,
+                                                     FontStyle::Normal).create_font_face();
+    assert!(arial.coverage_difference(&arial).is_empty());
+
+    let family = match system_fc.get_font_family_by_name("Segoe UI Symbol") {
+        Some(family) => family,
+        None => return,
+    };
+    let symbol = family.get_first_matching_font(FontWeight::Regular,
+                                                FontStretch::Normal,
+                                                FontStyle::Normal).create_font_face();
+    let difference = symbol.coverage_difference(&arial);
+    assert!(!difference.is_empty());
+    assert!(difference.windows(2).all(|pair| pair[0].1 + 1 < pair[1].0));
+
+    let arial_ranges = arial.unicode_ranges();
+    let symbol_ranges = symbol.unicode_ranges();
+    let covers = |ranges: &[(u32, u32)], code_point: u32| {
+        ranges.iter().any(|&(first, last)| first <= code_point && code_point <= last)
+    };
+    for &(first, last) in &difference {
+        for code_point in &[first, last] {
+            assert!(covers(&symbol_ranges, *code_point));
+            assert!(!covers(&arial_ranges, *code_point));
+        }
+    }
+}
+
+#[test]
+fn test_font_download_queue() {
+    let queue = match FontDownloadQueue::system() {
+        Ok(queue) => queue,
+        Err(_) => return,
+    };
+    let token = queue.add_listener(|_| {});
+    // Nothing here asks for cloud fonts, so there is nothing to download.
+    if queue.is_empty() {
+        let generation = queue.generation_count();
+        assert_eq!(queue.begin_download(), Ok(false));
+        assert_eq!(queue.generation_count(), generation);
+    }
+    queue.remove_listener(token);
+}
+
+#[test]
+fn test_paint_reader() {
+    let system_fc = FontCollection::system();
+    let family = system_fc.get_font_family_by_name("Segoe UI Emoji").unwrap();
+    let font = family.get_first_matching_font(FontWeight::Regular, FontStretch::Normal, FontStyle::Normal);
+    let face = font.create_font_face();
+    // Paint readers need Windows 11.
+    let reader = match PaintReader::new(&face) {
+        Ok(reader) => reader,
+        Err(_) => return,
+    };
+
+    let smiley = face.get_glyph_indices(&[0x1F600])[0];
+    match reader.get_paint_tree(smiley) {
+        Some(PaintNode::ColorGlyph { glyph_index, paint, .. }) => {
+            assert_eq!(glyph_index, smiley as u32);
+            assert!(!matches!(*paint, PaintNode::None));
+        }
+        Some(PaintNode::Layers(layers)) => assert!(!layers.is_empty()),
+        Some(_) => panic!("unexpected paint tree root"),
+        None => panic!("no paint tree for U+1F600"),
+    }
+
+    let a = face.get_glyph_indices(&['a' as u32])[0];
+    assert!(reader.get_paint_tree(a).is_none());
+}
@@ -0,0 +1,48 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::marker::PhantomData;
+
+use comptr::ComPtr;
+use helpers::system_locale_name;
+use text_analysis_source_impl::TextAnalysisSourceImpl;
+use winapi::um::dwrite::{IDWriteTextAnalysisSource, DWRITE_READING_DIRECTION};
+
+// An IDWriteTextAnalysisSource that reads straight from UTF-16 text the
+// caller already has, such as the contents of a Win32 edit control, with
+// no conversion through &str.  The whole text shares one locale and
+// reading direction.
+pub struct Utf16AnalysisSource<'a> {
+    native: ComPtr<IDWriteTextAnalysisSource>,
+    len: usize,
+    text: PhantomData<&'a [u16]>,
+}
+
+impl<'a> Utf16AnalysisSource<'a> {
+    // `locale` is the system locale if None.
+    pub fn new(text: &'a [u16], locale: Option<&str>, reading_direction: DWRITE_READING_DIRECTION)
+               -> Utf16AnalysisSource<'a> {
+        let locale = locale.map(|locale| locale.to_owned()).unwrap_or_else(system_locale_name);
+        Utf16AnalysisSource {
+            native: unsafe { TextAnalysisSourceImpl::create_borrowed(text, &locale, reading_direction) },
+            len: text.len(),
+            text: PhantomData,
+        }
+    }
+
+    // The text's length in UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The source borrows the text, so the pointer must not be kept past
+    // this object's lifetime.
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteTextAnalysisSource {
+        self.native.as_ptr()
+    }
+}
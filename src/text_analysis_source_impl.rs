@@ -0,0 +1,174 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// An IDWriteTextAnalysisSource over a single run of UTF-16 text with one
+// locale and no number substitution, for the analysis and font fallback
+// calls that want text through a callback interface.  The text is either
+// owned or, for Utf16AnalysisSource, borrowed from the caller.
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+use std::mem;
+use std::ptr;
+use std::sync::atomic;
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::REFIID;
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::winerror::{E_INVALIDARG, S_OK};
+use winapi::um::dwrite::{IDWriteNumberSubstitution, IDWriteTextAnalysisSource};
+use winapi::um::dwrite::{IDWriteTextAnalysisSourceVtbl, DWRITE_READING_DIRECTION};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::{HRESULT, WCHAR};
+
+use comptr::ComPtr;
+use com_helpers::*;
+use helpers::ToWide;
+
+DEFINE_GUID!{UuidOfIDWriteTextAnalysisSource, 0x688e1a58, 0x5094, 0x47c8, 0xad, 0xc8, 0xfb, 0xce, 0xa6, 0x0a, 0xe9, 0x2b}
+
+pub struct TextAnalysisSourceImpl {
+    refcount: atomic::AtomicUsize,
+    // Points into owned_text, or at borrowed text that outlives this.
+    text: *const u16,
+    text_length: usize,
+    owned_text: Vec<u16>,
+    locale: Vec<u16>,
+    reading_direction: DWRITE_READING_DIRECTION,
+}
+
+const TextAnalysisSourceImplVtbl: &IDWriteTextAnalysisSourceVtbl = &IDWriteTextAnalysisSourceVtbl {
+    parent: implement_iunknown!(IDWriteTextAnalysisSource, UuidOfIDWriteTextAnalysisSource, TextAnalysisSourceImpl),
+    GetTextAtPosition: {
+        unsafe extern "system" fn GetTextAtPosition(
+            This: *mut IDWriteTextAnalysisSource,
+            textPosition: u32,
+            textString: *mut *const WCHAR,
+            textLength: *mut u32) -> HRESULT
+        {
+            if textString.is_null() || textLength.is_null() {
+                return E_INVALIDARG
+            }
+            let this = TextAnalysisSourceImpl::from_interface(This);
+            let position = textPosition as usize;
+            if position >= this.text_length {
+                *textString = ptr::null();
+                *textLength = 0;
+            } else {
+                *textString = this.text.add(position);
+                *textLength = (this.text_length - position) as u32;
+            }
+            S_OK
+        }
+        GetTextAtPosition
+    },
+    GetTextBeforePosition: {
+        unsafe extern "system" fn GetTextBeforePosition(
+            This: *mut IDWriteTextAnalysisSource,
+            textPosition: u32,
+            textString: *mut *const WCHAR,
+            textLength: *mut u32) -> HRESULT
+        {
+            if textString.is_null() || textLength.is_null() {
+                return E_INVALIDARG
+            }
+            let this = TextAnalysisSourceImpl::from_interface(This);
+            let position = textPosition as usize;
+            if position == 0 || position > this.text_length {
+                *textString = ptr::null();
+                *textLength = 0;
+            } else {
+                *textString = this.text;
+                *textLength = position as u32;
+            }
+            S_OK
+        }
+        GetTextBeforePosition
+    },
+    GetParagraphReadingDirection: {
+        unsafe extern "system" fn GetParagraphReadingDirection(
+            This: *mut IDWriteTextAnalysisSource) -> DWRITE_READING_DIRECTION
+        {
+            TextAnalysisSourceImpl::from_interface(This).reading_direction
+        }
+        GetParagraphReadingDirection
+    },
+    GetLocaleName: {
+        unsafe extern "system" fn GetLocaleName(
+            This: *mut IDWriteTextAnalysisSource,
+            textPosition: u32,
+            textLength: *mut u32,
+            localeName: *mut *const WCHAR) -> HRESULT
+        {
+            if textLength.is_null() || localeName.is_null() {
+                return E_INVALIDARG
+            }
+            let this = TextAnalysisSourceImpl::from_interface(This);
+            *textLength = (this.text_length as u32).saturating_sub(textPosition);
+            *localeName = this.locale.as_ptr();
+            S_OK
+        }
+        GetLocaleName
+    },
+    GetNumberSubstitution: {
+        unsafe extern "system" fn GetNumberSubstitution(
+            This: *mut IDWriteTextAnalysisSource,
+            textPosition: u32,
+            textLength: *mut u32,
+            numberSubstitution: *mut *mut IDWriteNumberSubstitution) -> HRESULT
+        {
+            if textLength.is_null() || numberSubstitution.is_null() {
+                return E_INVALIDARG
+            }
+            let this = TextAnalysisSourceImpl::from_interface(This);
+            *textLength = (this.text_length as u32).saturating_sub(textPosition);
+            *numberSubstitution = ptr::null_mut();
+            S_OK
+        }
+        GetNumberSubstitution
+    },
+};
+
+impl TextAnalysisSourceImpl {
+    pub fn create(text: &str, locale: &str, reading_direction: DWRITE_READING_DIRECTION)
+                  -> ComPtr<IDWriteTextAnalysisSource> {
+        TextAnalysisSourceImpl::create_with(text.to_wide(), ptr::null(), 0, locale, reading_direction)
+    }
+
+    // The source borrows `text`, which must outlive every use of it.
+    pub unsafe fn create_borrowed(text: &[u16], locale: &str, reading_direction: DWRITE_READING_DIRECTION)
+                                  -> ComPtr<IDWriteTextAnalysisSource> {
+        TextAnalysisSourceImpl::create_with(vec![], text.as_ptr(), text.len(), locale, reading_direction)
+    }
+
+    fn create_with(owned_text: Vec<u16>,
+                   text: *const u16,
+                   text_length: usize,
+                   locale: &str,
+                   reading_direction: DWRITE_READING_DIRECTION)
+                   -> ComPtr<IDWriteTextAnalysisSource> {
+        let mut native = TextAnalysisSourceImpl {
+            refcount: atomic::AtomicUsize::new(0),
+            text,
+            text_length,
+            owned_text,
+            locale: locale.to_wide_null(),
+            reading_direction,
+        };
+        if text.is_null() {
+            native.text = native.owned_text.as_ptr();
+            native.text_length = native.owned_text.len();
+        }
+        ComPtr::from_ptr(native.into_interface())
+    }
+}
+
+impl Com<IDWriteTextAnalysisSource> for TextAnalysisSourceImpl {
+    type Vtbl = IDWriteTextAnalysisSourceVtbl;
+    fn vtbl() -> &'static IDWriteTextAnalysisSourceVtbl { TextAnalysisSourceImplVtbl }
+}
+
+impl Com<IUnknown> for TextAnalysisSourceImpl {
+    type Vtbl = IUnknownVtbl;
+    fn vtbl() -> &'static IUnknownVtbl { &TextAnalysisSourceImplVtbl.parent }
+}
@@ -0,0 +1,343 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+use std::mem::zeroed;
+use std::ptr;
+
+use comptr::ComPtr;
+use helpers::{system_locale_name, ToWide};
+use winapi::shared::minwindef::BOOL;
+use winapi::shared::winerror::{ERROR_INSUFFICIENT_BUFFER, HRESULT_FROM_WIN32};
+use winapi::um::dwrite::{IDWriteTextAnalyzer, DWRITE_SCRIPT_ANALYSIS};
+use winapi::um::dwrite::{DWRITE_SHAPING_GLYPH_PROPERTIES, DWRITE_SHAPING_TEXT_PROPERTIES};
+use winapi::um::dwrite::{DWRITE_SCRIPT_SHAPES_DEFAULT, DWRITE_TYPOGRAPHIC_FEATURES};
+use winapi::um::dwrite_1::{IDWriteTextAnalyzer1, DWRITE_JUSTIFICATION_OPPORTUNITY};
+use winapi::Interface;
+use feature_settings::RawFeatureSettings;
+use super::{DWriteFactory, FeatureSettings, FontFace, GlyphOffset, NumberSubstitution, ShapedRun};
+
+lazy_static! {
+    static ref SHARED_TEXT_ANALYZER_RAW_PTR: usize = {
+        unsafe {
+            let mut native: ComPtr<IDWriteTextAnalyzer> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateTextAnalyzer(native.getter_addrefs());
+            assert!(hr == 0);
+            native.forget() as usize
+        }
+    };
+}
+
+pub struct TextAnalyzer {
+    native: UnsafeCell<ComPtr<IDWriteTextAnalyzer>>,
+}
+
+impl TextAnalyzer {
+    pub fn create() -> TextAnalyzer {
+        unsafe {
+            let mut native: ComPtr<IDWriteTextAnalyzer> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateTextAnalyzer(native.getter_addrefs());
+            assert!(hr == 0);
+            TextAnalyzer::take(native)
+        }
+    }
+
+    // The analyzer belonging to the shared factory, created on first use.
+    // Analyzers hold no per-call state and are safe to use from any
+    // thread, so there's no need to create one per shaping call.
+    //
+    // This lives here rather than on a factory because the crate has no
+    // Factory type: everything goes through the one process-wide shared
+    // factory (DWriteFactory), and isolated factories can't be created,
+    // so a single cached analyzer is all there is to share.  If isolated
+    // factories are ever added, each will need its own analyzer; one from
+    // here must not be mixed with their font faces.
+    pub fn shared() -> TextAnalyzer {
+        let native = *SHARED_TEXT_ANALYZER_RAW_PTR as *mut IDWriteTextAnalyzer;
+        TextAnalyzer::take(ComPtr::from_ptr(native))
+    }
+
+    pub fn take(native: ComPtr<IDWriteTextAnalyzer>) -> TextAnalyzer {
+        TextAnalyzer {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteTextAnalyzer {
+        (*self.native.get()).as_ptr()
+    }
+
+    // Shapes `text` (UTF-16) with `font_face`: maps it to glyphs, applying
+    // the font's default features for the script plus any in
+    // `options.features`.
+    pub fn get_glyphs(&self, text: &[u16], font_face: &FontFace, options: &ShapingOptions) -> ShapedRun {
+        let locale = locale_or_default(options.locale);
+        let features = options.features.map(|features| features.to_raw(text.len()));
+        let (feature_ranges, feature_range_lengths, feature_range_count) = raw_feature_args(&features);
+        let number_substitution = options.number_substitution
+            .map(|number_substitution| unsafe { number_substitution.as_ptr() })
+            .unwrap_or(ptr::null_mut());
+        let mut cluster_map = vec![0; text.len()];
+        let mut text_props = vec![DWRITE_SHAPING_TEXT_PROPERTIES { bit_fields: 0 }; text.len()];
+
+        // The recommended initial guess, grown if it turns out too small.
+        let mut max_glyph_count = text.len() * 3 / 2 + 16;
+        loop {
+            let mut glyph_indices = vec![0; max_glyph_count];
+            let mut glyph_props = vec![DWRITE_SHAPING_GLYPH_PROPERTIES { bit_fields: 0 }; max_glyph_count];
+            let mut glyph_count = 0;
+            let hr = unsafe {
+                (*self.native.get()).GetGlyphs(text.as_ptr(),
+                                               text.len() as u32,
+                                               font_face.as_ptr(),
+                                               options.is_sideways as BOOL,
+                                               options.is_right_to_left as BOOL,
+                                               &options.script_analysis,
+                                               locale.as_ptr(),
+                                               number_substitution,
+                                               feature_ranges,
+                                               feature_range_lengths,
+                                               feature_range_count,
+                                               max_glyph_count as u32,
+                                               cluster_map.as_mut_ptr(),
+                                               text_props.as_mut_ptr(),
+                                               glyph_indices.as_mut_ptr(),
+                                               glyph_props.as_mut_ptr(),
+                                               &mut glyph_count)
+            };
+            if hr == HRESULT_FROM_WIN32(ERROR_INSUFFICIENT_BUFFER) {
+                max_glyph_count *= 2;
+                continue;
+            }
+            assert!(hr == 0);
+
+            glyph_indices.truncate(glyph_count as usize);
+            glyph_props.truncate(glyph_count as usize);
+            return ShapedRun { glyph_indices, cluster_map, text_props, glyph_props };
+        }
+    }
+
+    // Positions the glyphs of a run shaped by get_glyphs, returning each
+    // glyph's advance and offset in DIPs.  Kerning and mark positioning
+    // happen here, so pass the same options that were used for shaping.
+    pub fn get_glyph_placements(&self,
+                                text: &[u16],
+                                run: &ShapedRun,
+                                font_face: &FontFace,
+                                em_size: f32,
+                                options: &ShapingOptions)
+                                -> (Vec<f32>, Vec<GlyphOffset>) {
+        // The fields of a ShapedRun are public, so check they still fit
+        // together before DirectWrite reads through them.
+        assert!(text.len() == run.text_len() && run.text_props.len() == run.text_len());
+        assert!(run.glyph_props.len() == run.glyph_count());
+        let locale = locale_or_default(options.locale);
+        let features = options.features.map(|features| features.to_raw(text.len()));
+        let (feature_ranges, feature_range_lengths, feature_range_count) = raw_feature_args(&features);
+
+        let mut text_props = run.text_props.clone();
+        let mut advances = vec![0.; run.glyph_count()];
+        let mut offsets = vec![GlyphOffset { advanceOffset: 0., ascenderOffset: 0. }; run.glyph_count()];
+        unsafe {
+            let hr = (*self.native.get()).GetGlyphPlacements(text.as_ptr(),
+                                                             run.cluster_map.as_ptr(),
+                                                             text_props.as_mut_ptr(),
+                                                             text.len() as u32,
+                                                             run.glyph_indices.as_ptr(),
+                                                             run.glyph_props.as_ptr(),
+                                                             run.glyph_count() as u32,
+                                                             font_face.as_ptr(),
+                                                             em_size,
+                                                             options.is_sideways as BOOL,
+                                                             options.is_right_to_left as BOOL,
+                                                             &options.script_analysis,
+                                                             locale.as_ptr(),
+                                                             feature_ranges,
+                                                             feature_range_lengths,
+                                                             feature_range_count,
+                                                             advances.as_mut_ptr(),
+                                                             offsets.as_mut_ptr());
+            assert!(hr == 0);
+        }
+        (advances, offsets)
+    }
+
+    // How much each glyph of a shaped run may stretch or shrink when the
+    // line is justified.  Like the other justification methods, this
+    // needs IDWriteTextAnalyzer1 (Windows 8) and returns None without it.
+    pub fn get_justification_opportunities(&self,
+                                           text: &[u16],
+                                           run: &ShapedRun,
+                                           font_face: &FontFace,
+                                           em_size: f32,
+                                           options: &ShapingOptions)
+                                           -> Option<Vec<DWRITE_JUSTIFICATION_OPPORTUNITY>> {
+        assert!(text.len() == run.text_len() && run.glyph_props.len() == run.glyph_count());
+        let analyzer = self.analyzer1()?;
+        unsafe {
+            let mut opportunities = vec![zeroed(); run.glyph_count()];
+            let hr = analyzer.GetJustificationOpportunities(font_face.as_ptr(),
+                                                            em_size,
+                                                            options.script_analysis,
+                                                            text.len() as u32,
+                                                            run.glyph_count() as u32,
+                                                            text.as_ptr(),
+                                                            run.cluster_map.as_ptr(),
+                                                            run.glyph_props.as_ptr(),
+                                                            opportunities.as_mut_ptr());
+            assert!(hr == 0);
+            Some(opportunities)
+        }
+    }
+
+    // Spreads the difference between the run's width and `line_width` over
+    // its justification opportunities, e.g. by widening spaces.
+    pub fn justify_glyph_advances(&self,
+                                  line_width: f32,
+                                  opportunities: &[DWRITE_JUSTIFICATION_OPPORTUNITY],
+                                  advances: &[f32],
+                                  offsets: &[GlyphOffset])
+                                  -> Option<JustifiedAdvances> {
+        assert!(opportunities.len() == advances.len() && offsets.len() == advances.len());
+        let analyzer = self.analyzer1()?;
+        let mut justified = JustifiedAdvances {
+            advances: vec![0.; advances.len()],
+            offsets: vec![GlyphOffset { advanceOffset: 0., ascenderOffset: 0. }; advances.len()],
+        };
+        unsafe {
+            let hr = analyzer.JustifyGlyphAdvances(line_width,
+                                                   advances.len() as u32,
+                                                   opportunities.as_ptr(),
+                                                   advances.as_ptr(),
+                                                   offsets.as_ptr(),
+                                                   justified.advances.as_mut_ptr(),
+                                                   justified.offsets.as_mut_ptr());
+            assert!(hr == 0);
+        }
+        Some(justified)
+    }
+
+    // Turns justified advances back into glyphs, which for scripts such
+    // as Arabic means inserting kashidas rather than leaving gaps, so the
+    // result can have more glyphs than `run`.  `advances` are the run's
+    // advances before justification.
+    pub fn get_justified_glyphs(&self,
+                                run: &ShapedRun,
+                                font_face: &FontFace,
+                                em_size: f32,
+                                options: &ShapingOptions,
+                                advances: &[f32],
+                                justified: &JustifiedAdvances)
+                                -> Option<JustifiedRun> {
+        assert!(advances.len() == run.glyph_count() && run.glyph_props.len() == run.glyph_count());
+        assert!(justified.advances.len() == run.glyph_count() && justified.offsets.len() == run.glyph_count());
+        let analyzer = self.analyzer1()?;
+        let mut max_glyph_count = run.glyph_count() * 2 + 16;
+        loop {
+            let mut result = JustifiedRun {
+                cluster_map: vec![0; run.text_len()],
+                glyph_indices: vec![0; max_glyph_count],
+                advances: vec![0.; max_glyph_count],
+                offsets: vec![GlyphOffset { advanceOffset: 0., ascenderOffset: 0. }; max_glyph_count],
+            };
+            let mut glyph_count = 0;
+            let hr = unsafe {
+                analyzer.GetJustifiedGlyphs(font_face.as_ptr(),
+                                            em_size,
+                                            options.script_analysis,
+                                            run.text_len() as u32,
+                                            run.glyph_count() as u32,
+                                            max_glyph_count as u32,
+                                            run.cluster_map.as_ptr(),
+                                            run.glyph_indices.as_ptr(),
+                                            advances.as_ptr(),
+                                            justified.advances.as_ptr(),
+                                            justified.offsets.as_ptr(),
+                                            run.glyph_props.as_ptr(),
+                                            &mut glyph_count,
+                                            result.cluster_map.as_mut_ptr(),
+                                            result.glyph_indices.as_mut_ptr(),
+                                            result.advances.as_mut_ptr(),
+                                            result.offsets.as_mut_ptr())
+            };
+            if hr == HRESULT_FROM_WIN32(ERROR_INSUFFICIENT_BUFFER) {
+                max_glyph_count *= 2;
+                continue;
+            }
+            assert!(hr == 0);
+
+            result.glyph_indices.truncate(glyph_count as usize);
+            result.advances.truncate(glyph_count as usize);
+            result.offsets.truncate(glyph_count as usize);
+            return Some(result);
+        }
+    }
+
+    fn analyzer1(&self) -> Option<ComPtr<IDWriteTextAnalyzer1>> {
+        unsafe {
+            (*self.native.get()).query_interface::<IDWriteTextAnalyzer1>(&IDWriteTextAnalyzer1::uuidof())
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JustifiedAdvances {
+    pub advances: Vec<f32>,
+    pub offsets: Vec<GlyphOffset>,
+}
+
+// A run after justification, which may have gained glyphs.
+#[derive(Clone)]
+pub struct JustifiedRun {
+    pub cluster_map: Vec<u16>,
+    pub glyph_indices: Vec<u16>,
+    pub advances: Vec<f32>,
+    pub offsets: Vec<GlyphOffset>,
+}
+
+// How a run of text should be shaped.
+#[derive(Clone, Copy)]
+pub struct ShapingOptions<'a> {
+    pub is_sideways: bool,
+    pub is_right_to_left: bool,
+    // Should come from script analysis of the text; the default, zeroed,
+    // value shapes it as script-neutral text.
+    pub script_analysis: DWRITE_SCRIPT_ANALYSIS,
+    // Defaults to the system locale.
+    pub locale: Option<&'a str>,
+    // Features to apply on top of the font's defaults.
+    pub features: Option<&'a FeatureSettings>,
+    // Digit substitution.  Only shaping uses it: get_glyph_placements
+    // works from the glyphs get_glyphs already substituted.
+    pub number_substitution: Option<&'a NumberSubstitution>,
+}
+
+impl<'a> Default for ShapingOptions<'a> {
+    fn default() -> ShapingOptions<'a> {
+        ShapingOptions {
+            is_sideways: false,
+            is_right_to_left: false,
+            script_analysis: DWRITE_SCRIPT_ANALYSIS { script: 0, shapes: DWRITE_SCRIPT_SHAPES_DEFAULT },
+            locale: None,
+            features: None,
+            number_substitution: None,
+        }
+    }
+}
+
+fn locale_or_default(locale: Option<&str>) -> Vec<u16> {
+    locale.map(|locale| locale.to_owned()).unwrap_or_else(system_locale_name).to_wide_null()
+}
+
+fn raw_feature_args(features: &Option<RawFeatureSettings>)
+                    -> (*mut *const DWRITE_TYPOGRAPHIC_FEATURES, *const u32, u32) {
+    match *features {
+        Some(ref features) if !features.range_lengths.is_empty() => {
+            (features.range_pointers.as_ptr() as *mut _,
+             features.range_lengths.as_ptr(),
+             features.range_lengths.len() as u32)
+        }
+        _ => (ptr::null_mut(), ptr::null(), 0),
+    }
+}
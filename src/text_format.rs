@@ -0,0 +1,154 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+use std::ptr;
+
+use comptr::ComPtr;
+use winapi::um::dwrite::{IDWriteInlineObject, IDWriteTextFormat, DWRITE_TRIMMING, DWRITE_TRIMMING_GRANULARITY};
+use winapi::um::dwrite::{DWRITE_TRIMMING_GRANULARITY_CHARACTER, DWRITE_WORD_WRAPPING};
+use winapi::um::dwrite::{DWRITE_LINE_SPACING_METHOD, DWRITE_LINE_SPACING_METHOD_PROPORTIONAL};
+use winapi::um::dwrite_3::IDWriteTextFormat2;
+use winapi::shared::winerror::{E_NOINTERFACE, HRESULT};
+use winapi::Interface;
+use super::*;
+
+// What a TextLayout draws in place of text cut off by trimming.
+pub struct TrimmingSign {
+    native: UnsafeCell<ComPtr<IDWriteInlineObject>>,
+}
+
+impl TrimmingSign {
+    // The standard "\u{2026}" sign, in the font of `format`.
+    pub fn ellipsis(format: &TextFormat) -> TrimmingSign {
+        unsafe {
+            let mut native: ComPtr<IDWriteInlineObject> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateEllipsisTrimmingSign(format.as_ptr(), native.getter_addrefs());
+            assert!(hr == 0);
+            TrimmingSign::take(native)
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteInlineObject>) -> TrimmingSign {
+        TrimmingSign {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteInlineObject {
+        (*self.native.get()).as_ptr()
+    }
+}
+
+// The default formatting for a TextLayout: font, size and locale.  Layouts
+// copy the format when they are created, so changing it afterwards only
+// affects new layouts.
+pub struct TextFormat {
+    native: UnsafeCell<ComPtr<IDWriteTextFormat>>,
+}
+
+impl TextFormat {
+    // Uses the system font collection if `collection` is None.
+    pub fn new(family_name: &str,
+               collection: Option<&FontCollection>,
+               weight: FontWeight,
+               style: FontStyle,
+               stretch: FontStretch,
+               size: f32,
+               locale: &str)
+               -> TextFormat {
+        unsafe {
+            let mut native: ComPtr<IDWriteTextFormat> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateTextFormat(family_name.to_wide_null().as_ptr(),
+                                                         collection.map(|c| c.as_ptr()).unwrap_or(ptr::null_mut()),
+                                                         weight.t(),
+                                                         style.t(),
+                                                         stretch.t(),
+                                                         size,
+                                                         locale.to_wide_null().as_ptr(),
+                                                         native.getter_addrefs());
+            assert!(hr == 0);
+            TextFormat::take(native)
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteTextFormat>) -> TextFormat {
+        TextFormat {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteTextFormat {
+        (*self.native.get()).as_ptr()
+    }
+
+    // Text that overflows the layout box is cut at `granularity`
+    // boundaries and replaced by `sign`, if any.  With a delimiter, the
+    // text after the `delimiter_count`th occurrence of it from the end is
+    // kept, as when eliding the middle of a path.
+    pub fn set_trimming(&self,
+                        granularity: DWRITE_TRIMMING_GRANULARITY,
+                        delimiter: Option<char>,
+                        delimiter_count: u32,
+                        sign: Option<&TrimmingSign>) {
+        let trimming = DWRITE_TRIMMING {
+            granularity,
+            delimiter: delimiter.map(|c| c as u32).unwrap_or(0),
+            delimiterCount: delimiter_count,
+        };
+        unsafe {
+            let sign = sign.map(|sign| sign.as_ptr()).unwrap_or(ptr::null_mut());
+            let hr = (*self.native.get()).SetTrimming(&trimming, sign);
+            assert!(hr == 0);
+        }
+    }
+
+    // Trims at character granularity with an ellipsis, the usual choice for
+    // labels.  Combine with DWRITE_WORD_WRAPPING_NO_WRAP for single-line
+    // labels.
+    pub fn set_ellipsis_trimming(&self) {
+        let sign = TrimmingSign::ellipsis(self);
+        self.set_trimming(DWRITE_TRIMMING_GRANULARITY_CHARACTER, None, 0, Some(&sign));
+    }
+
+    pub fn set_word_wrapping(&self, word_wrapping: DWRITE_WORD_WRAPPING) {
+        unsafe {
+            let hr = (*self.native.get()).SetWordWrapping(word_wrapping);
+            assert!(hr == 0);
+        }
+    }
+
+    // With DWRITE_LINE_SPACING_METHOD_UNIFORM, every line is `line_height`
+    // DIPs tall with its baseline `baseline` DIPs below the top, like CSS
+    // line-height.  With PROPORTIONAL, both are multiples of the font's
+    // own values; that needs Windows 10, and fails with E_NOINTERFACE
+    // on older systems.  DEFAULT ignores both and uses the fonts' metrics.
+    pub fn set_line_spacing(&self,
+                            method: DWRITE_LINE_SPACING_METHOD,
+                            line_height: f32,
+                            baseline: f32)
+                            -> Result<(), HRESULT> {
+        unsafe {
+            if method == DWRITE_LINE_SPACING_METHOD_PROPORTIONAL &&
+                    (*self.native.get()).query_interface::<IDWriteTextFormat2>(&IDWriteTextFormat2::uuidof()).is_none() {
+                return Err(E_NOINTERFACE);
+            }
+            let hr = (*self.native.get()).SetLineSpacing(method, line_height, baseline);
+            if hr != 0 {
+                return Err(hr);
+            }
+        }
+        Ok(())
+    }
+
+    // (method, line height, baseline), as passed to set_line_spacing.
+    pub fn get_line_spacing(&self) -> (DWRITE_LINE_SPACING_METHOD, f32, f32) {
+        unsafe {
+            let (mut method, mut line_height, mut baseline) = (0, 0., 0.);
+            let hr = (*self.native.get()).GetLineSpacing(&mut method, &mut line_height, &mut baseline);
+            assert!(hr == 0);
+            (method, line_height, baseline)
+        }
+    }
+}
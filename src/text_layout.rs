@@ -0,0 +1,285 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+use std::mem::zeroed;
+use std::ops::Range;
+use std::ptr;
+
+use winapi::shared::winerror::{E_NOINTERFACE, ERROR_INSUFFICIENT_BUFFER, HRESULT, HRESULT_FROM_WIN32};
+
+use comptr::ComPtr;
+use inline_object_impl::CustomInlineObject;
+use winapi::um::dwrite::{IDWriteTextLayout, DWRITE_CLUSTER_METRICS, DWRITE_OVERHANG_METRICS, DWRITE_TEXT_METRICS, DWRITE_TEXT_RANGE};
+use winapi::um::dwrite_2::IDWriteTextLayout2;
+use winapi::um::unknwnbase::IUnknown;
+use winapi::Interface;
+use super::{DWriteFactory, FontCollection, FontFallback, FontStyle, FontWeight, InlineObject, TextFormat, Typography};
+use helpers::ToWide;
+
+// A cluster is the smallest unit of text the caret can move over: a
+// grapheme, a ligature, or a character with its combining marks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusterMetrics {
+    // Advance width in DIPs.
+    pub width: f32,
+    // In UTF-16 code units.
+    pub length: usize,
+    pub can_wrap_line_after: bool,
+    pub is_whitespace: bool,
+    pub is_newline: bool,
+    pub is_soft_hyphen: bool,
+    pub is_right_to_left: bool,
+}
+
+impl ClusterMetrics {
+    fn from_raw(metrics: &DWRITE_CLUSTER_METRICS) -> ClusterMetrics {
+        ClusterMetrics {
+            width: metrics.width,
+            length: metrics.length as usize,
+            can_wrap_line_after: metrics.canWrapLineAfter() != 0,
+            is_whitespace: metrics.isWhitespace() != 0,
+            is_newline: metrics.isNewline() != 0,
+            is_soft_hyphen: metrics.isSoftHyphen() != 0,
+            is_right_to_left: metrics.isRightToLeft() != 0,
+        }
+    }
+}
+
+// Laid-out text.  Text positions and ranges are in UTF-16 code units of
+// the text the layout was created with.
+pub struct TextLayout {
+    native: UnsafeCell<ComPtr<IDWriteTextLayout>>,
+}
+
+impl TextLayout {
+    pub fn new(text: &str, format: &TextFormat, max_width: f32, max_height: f32) -> TextLayout {
+        let text = text.to_wide();
+        unsafe {
+            let mut native: ComPtr<IDWriteTextLayout> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateTextLayout(text.as_ptr(),
+                                                         text.len() as u32,
+                                                         format.as_ptr(),
+                                                         max_width,
+                                                         max_height,
+                                                         native.getter_addrefs());
+            assert!(hr == 0);
+            TextLayout::take(native)
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteTextLayout>) -> TextLayout {
+        TextLayout {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteTextLayout {
+        (*self.native.get()).as_ptr()
+    }
+
+    pub fn get_metrics(&self) -> DWRITE_TEXT_METRICS {
+        unsafe {
+            let mut metrics: DWRITE_TEXT_METRICS = zeroed();
+            let hr = (*self.native.get()).GetMetrics(&mut metrics);
+            assert!(hr == 0);
+            metrics
+        }
+    }
+
+    // How far, in DIPs, ink extends past each edge of the layout box
+    // (max_width x max_height).  Positive values mean the text draws
+    // outside the box, as italics and swashes often do, so a surface sized
+    // from get_metrics alone would clip it.
+    pub fn get_overhang_metrics(&self) -> DWRITE_OVERHANG_METRICS {
+        unsafe {
+            let mut overhangs: DWRITE_OVERHANG_METRICS = zeroed();
+            let hr = (*self.native.get()).GetOverhangMetrics(&mut overhangs);
+            assert!(hr == 0);
+            overhangs
+        }
+    }
+
+    // The clusters of the text in logical order.  Their lengths add up to
+    // the length of the text.
+    pub fn get_cluster_metrics(&self) -> Vec<ClusterMetrics> {
+        unsafe {
+            let mut count = 0;
+            let hr = (*self.native.get()).GetClusterMetrics(ptr::null_mut(), 0, &mut count);
+            if hr != 0 {
+                assert!(hr == HRESULT_FROM_WIN32(ERROR_INSUFFICIENT_BUFFER));
+            }
+            let mut metrics: Vec<DWRITE_CLUSTER_METRICS> = vec![zeroed(); count as usize];
+            let hr = (*self.native.get()).GetClusterMetrics(metrics.as_mut_ptr(), count, &mut count);
+            assert!(hr == 0);
+            metrics.truncate(count as usize);
+            metrics.iter().map(ClusterMetrics::from_raw).collect()
+        }
+    }
+
+    // Replaces `range` of the text with `object`, which the layout then
+    // measures and draws in its place.  The range is usually a single
+    // U+FFFC OBJECT REPLACEMENT CHARACTER.
+    pub fn set_inline_object<T>(&self, object: T, range: Range<usize>) where T: InlineObject + 'static {
+        let object = CustomInlineObject::create(object);
+        unsafe {
+            let hr = (*self.native.get()).SetInlineObject(object.as_ptr(), text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    pub fn set_font_family(&self, family_name: &str, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetFontFamilyName(family_name.to_wide_null().as_ptr(),
+                                                            text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    pub fn set_font_collection(&self, collection: &FontCollection, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetFontCollection(collection.as_ptr(), text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    // In DIPs.
+    pub fn set_font_size(&self, size: f32, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetFontSize(size, text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    // Finds the largest font size between min_size and max_size at which
+    // the whole text fits in max_width x max_height, by binary search on
+    // the size and re-measuring with get_metrics.  The layout is left sized
+    // to that box and set to the chosen size, which is returned; if even
+    // min_size doesn't fit, that is used.
+    pub fn fit_font_size(&self, max_width: f32, max_height: f32, min_size: f32, max_size: f32) -> f32 {
+        // DirectWrite clips ranges to the text, so this covers all of it.
+        let whole_text = 0..u32::MAX as usize;
+        unsafe {
+            let hr = (*self.native.get()).SetMaxWidth(max_width);
+            assert!(hr == 0);
+            let hr = (*self.native.get()).SetMaxHeight(max_height);
+            assert!(hr == 0);
+        }
+
+        let fits = |size: f32| {
+            self.set_font_size(size, whole_text.clone());
+            let metrics = self.get_metrics();
+            metrics.width <= max_width && metrics.height <= max_height
+        };
+
+        let (mut low, mut high) = (min_size, max_size);
+        if fits(high) {
+            return high;
+        }
+        if !fits(low) {
+            self.set_font_size(low, whole_text);
+            return low;
+        }
+        // Invariant: low fits and high doesn't.  A twentieth of a DIP is
+        // finer than anyone will notice.
+        while high - low > 0.05 {
+            let middle = (low + high) / 2.;
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        self.set_font_size(low, whole_text);
+        low
+    }
+
+    pub fn set_font_weight(&self, weight: FontWeight, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetFontWeight(weight.t(), text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    pub fn set_font_style(&self, style: FontStyle, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetFontStyle(style.t(), text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    // Has the layout pick fonts for characters its own fonts lack from
+    // `fallback` instead of the system fallback.  Needs IDWriteTextLayout2
+    // (Windows 8.1); E_NOINTERFACE is returned if it isn't available.
+    pub fn set_font_fallback(&self, fallback: &FontFallback) -> Result<(), HRESULT> {
+        unsafe {
+            let layout = (*self.native.get())
+                .query_interface::<IDWriteTextLayout2>(&IDWriteTextLayout2::uuidof())
+                .ok_or(E_NOINTERFACE)?;
+            let hr = layout.SetFontFallback(fallback.as_ptr());
+            if hr != 0 {
+                return Err(hr);
+            }
+            Ok(())
+        }
+    }
+
+    pub fn set_underline(&self, underline: bool, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetUnderline(underline as i32, text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    pub fn set_strikethrough(&self, strikethrough: bool, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetStrikethrough(strikethrough as i32, text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    pub fn set_locale_name(&self, locale: &str, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetLocaleName(locale.to_wide_null().as_ptr(), text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    pub fn set_typography(&self, typography: &Typography, range: Range<usize>) {
+        unsafe {
+            let hr = (*self.native.get()).SetTypography(typography.as_ptr(), text_range(range));
+            assert!(hr == 0);
+        }
+    }
+
+    // Attaches an application-defined object, such as a
+    // ColorDrawingEffect, that the layout hands to the renderer along with
+    // each glyph run in `range`.  The layout holds a reference to it.
+    pub unsafe fn set_drawing_effect(&self, effect: *mut IUnknown, range: Range<usize>) {
+        let hr = (*self.native.get()).SetDrawingEffect(effect, text_range(range));
+        assert!(hr == 0);
+    }
+
+    pub fn get_drawing_effect(&self, position: usize) -> Option<ComPtr<IUnknown>> {
+        unsafe {
+            let mut effect: ComPtr<IUnknown> = ComPtr::new();
+            let hr = (*self.native.get()).GetDrawingEffect(position as u32,
+                                                           effect.getter_addrefs(),
+                                                           ptr::null_mut());
+            assert!(hr == 0);
+            if effect.as_ptr().is_null() {
+                None
+            } else {
+                Some(effect)
+            }
+        }
+    }
+}
+
+pub(crate) fn text_range(range: Range<usize>) -> DWRITE_TEXT_RANGE {
+    DWRITE_TEXT_RANGE {
+        startPosition: range.start as u32,
+        length: range.end.saturating_sub(range.start) as u32,
+    }
+}
@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use winapi::um::dwrite::DWRITE_MATRIX;
+
+// A 2D affine transform with the same layout and conventions as
+// DWRITE_MATRIX: points are row vectors, so
+//   x' = x * m11 + y * m21 + dx
+//   y' = x * m12 + y * m22 + dy
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub m11: f32,
+    pub m12: f32,
+    pub m21: f32,
+    pub m22: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform { m11: 1., m12: 0., m21: 0., m22: 1., dx: 0., dy: 0. }
+    }
+
+    // The transform from DIPs to device pixels for text drawn `scale` times
+    // larger and rotated by rotation_radians, on a surface with the given
+    // DPI, ready to pass (as a DWRITE_MATRIX) to GlyphRunAnalysis::create
+    // with a pixels_per_dip of 1, or to a bitmap render target's
+    // SetCurrentTransform.  With y pointing down, a
+    // positive rotation is clockwise on screen: at 90 degrees the text's
+    // baseline (+x) runs down the screen (+y).  DPI is applied last, so a
+    // rotated run on a surface with different horizontal and vertical DPI
+    // still comes out with square pixels in the font's own space.
+    pub fn for_rendering(dpi_x: f32, dpi_y: f32, rotation_radians: f32, scale: f32) -> Transform {
+        let (sin, cos) = rotation_radians.sin_cos();
+        let (x_scale, y_scale) = (dpi_x / 96., dpi_y / 96.);
+        Transform {
+            m11: scale * cos * x_scale,
+            m12: scale * sin * y_scale,
+            m21: -scale * sin * x_scale,
+            m22: scale * cos * y_scale,
+            dx: 0.,
+            dy: 0.,
+        }
+    }
+
+    pub fn transform_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.m11 + y * self.m21 + self.dx,
+         x * self.m12 + y * self.m22 + self.dy)
+    }
+
+    // The transform that maps transformed points back, e.g. device pixels
+    // back to em space for hit-testing.  None if the transform is singular
+    // (it squashes everything onto a line or a point), or not finite.
+    pub fn invert(&self) -> Option<Transform> {
+        let determinant = self.m11 * self.m22 - self.m12 * self.m21;
+        if determinant == 0. || !determinant.is_finite() {
+            return None;
+        }
+        Some(Transform {
+            m11: self.m22 / determinant,
+            m12: -self.m12 / determinant,
+            m21: -self.m21 / determinant,
+            m22: self.m11 / determinant,
+            dx: (self.m21 * self.dy - self.m22 * self.dx) / determinant,
+            dy: (self.m12 * self.dx - self.m11 * self.dy) / determinant,
+        })
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+impl From<DWRITE_MATRIX> for Transform {
+    fn from(matrix: DWRITE_MATRIX) -> Transform {
+        Transform {
+            m11: matrix.m11,
+            m12: matrix.m12,
+            m21: matrix.m21,
+            m22: matrix.m22,
+            dx: matrix.dx,
+            dy: matrix.dy,
+        }
+    }
+}
+
+impl From<Transform> for DWRITE_MATRIX {
+    fn from(transform: Transform) -> DWRITE_MATRIX {
+        DWRITE_MATRIX {
+            m11: transform.m11,
+            m12: transform.m12,
+            m21: transform.m21,
+            m22: transform.m22,
+            dx: transform.dx,
+            dy: transform.dy,
+        }
+    }
+}
@@ -5,6 +5,7 @@
 /* this is include!()'d in lib.rs */
 use std::mem;
 use winapi::um::dwrite::{DWRITE_FONT_STYLE, DWRITE_FONT_WEIGHT, DWRITE_FONT_STRETCH};
+use winapi::um::dwrite::{DWRITE_FONT_FACE_TYPE, DWRITE_FONT_FILE_TYPE};
 
 // mirrors DWRITE_FONT_WEIGHT
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Copy)]
@@ -102,6 +103,71 @@ impl FontStyle {
     pub fn from_u32(v: u32) -> FontStyle { unsafe { mem::transmute::<u32, FontStyle>(v) } }
 }
 
+// mirrors DWRITE_FONT_FACE_TYPE.  DirectWrite reports TrueType
+// collections (.ttc) as OpenTypeCollection.
+#[repr(u32)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum FaceType {
+    // OpenType with PostScript (CFF) outlines.
+    Cff = 0,
+    // OpenType with TrueType (glyf) outlines.
+    TrueType = 1,
+    OpenTypeCollection = 2,
+    Type1 = 3,
+    Vector = 4,
+    Bitmap = 5,
+    Unknown = 6,
+    // A bare CFF font, not wrapped in OpenType.
+    RawCff = 7,
+}
+
+impl FaceType {
+    pub fn to_u32(&self) -> u32 { *self as u32 }
+    pub fn from_u32(v: DWRITE_FONT_FACE_TYPE) -> FaceType {
+        match v {
+            0 => FaceType::Cff,
+            1 => FaceType::TrueType,
+            2 => FaceType::OpenTypeCollection,
+            3 => FaceType::Type1,
+            4 => FaceType::Vector,
+            5 => FaceType::Bitmap,
+            7 => FaceType::RawCff,
+            _ => FaceType::Unknown,
+        }
+    }
+}
+
+// mirrors DWRITE_FONT_FILE_TYPE, which is what FontFile analysis reports.
+// As with FaceType, TrueType collections are OpenTypeCollection.
+#[repr(u32)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum FontFileType {
+    Unknown = 0,
+    Cff = 1,
+    TrueType = 2,
+    OpenTypeCollection = 3,
+    Type1Pfm = 4,
+    Type1Pfb = 5,
+    Vector = 6,
+    Bitmap = 7,
+}
+
+impl FontFileType {
+    pub fn to_u32(&self) -> u32 { *self as u32 }
+    pub fn from_u32(v: DWRITE_FONT_FILE_TYPE) -> FontFileType {
+        match v {
+            1 => FontFileType::Cff,
+            2 => FontFileType::TrueType,
+            3 => FontFileType::OpenTypeCollection,
+            4 => FontFileType::Type1Pfm,
+            5 => FontFileType::Type1Pfb,
+            6 => FontFileType::Vector,
+            7 => FontFileType::Bitmap,
+            _ => FontFileType::Unknown,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct FontDescriptor {
     pub family_name: String,
@@ -0,0 +1,73 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cell::UnsafeCell;
+use std::mem::zeroed;
+
+use comptr::ComPtr;
+use feature_settings::is_valid_tag;
+use tables::make_opentype_tag;
+use winapi::shared::winerror::{E_INVALIDARG, HRESULT};
+use winapi::um::dwrite::{IDWriteTypography, DWRITE_FONT_FEATURE};
+use super::DWriteFactory;
+
+// A list of OpenType features to apply to a range of a TextLayout with
+// TextLayout::set_typography.
+pub struct Typography {
+    native: UnsafeCell<ComPtr<IDWriteTypography>>,
+}
+
+impl Typography {
+    pub fn new() -> Typography {
+        unsafe {
+            let mut native: ComPtr<IDWriteTypography> = ComPtr::new();
+            let hr = (*DWriteFactory()).CreateTypography(native.getter_addrefs());
+            assert!(hr == 0);
+            Typography::take(native)
+        }
+    }
+
+    pub fn take(native: ComPtr<IDWriteTypography>) -> Typography {
+        Typography {
+            native: UnsafeCell::new(native),
+        }
+    }
+
+    pub unsafe fn as_ptr(&self) -> *mut IDWriteTypography {
+        (*self.native.get()).as_ptr()
+    }
+
+    // Fails with E_INVALIDARG if the tag isn't a valid OpenType tag.
+    pub fn add_feature(&self, tag: [u8; 4], value: u32) -> Result<(), HRESULT> {
+        if !is_valid_tag(&tag) {
+            return Err(E_INVALIDARG);
+        }
+        unsafe {
+            let feature = DWRITE_FONT_FEATURE { nameTag: make_opentype_tag(&tag), parameter: value };
+            let hr = (*self.native.get()).AddFontFeature(feature);
+            if hr != 0 {
+                return Err(hr);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_features(&self) -> Vec<DWRITE_FONT_FEATURE> {
+        unsafe {
+            let count = (*self.native.get()).GetFontFeatureCount();
+            (0..count).map(|index| {
+                let mut feature: DWRITE_FONT_FEATURE = zeroed();
+                let hr = (*self.native.get()).GetFontFeature(index, &mut feature);
+                assert!(hr == 0);
+                feature
+            }).collect()
+        }
+    }
+}
+
+impl Default for Typography {
+    fn default() -> Typography {
+        Typography::new()
+    }
+}